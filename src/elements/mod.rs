@@ -7,9 +7,14 @@
 pub mod tokens;
 
 use crate::format::PlaceholderTemplate;
+use crate::references::bibliography::{assign_citation_fields, BibEntryPositions};
+use crate::references::citations::get_citation_list;
+use crate::references::cross_references::AssignLinkTitles;
 use crate::references::glossary::{GlossaryManager, GlossaryReference};
+use crate::references::index::{GetIndexEntries, IndexEntry};
 use crate::references::placeholders::ProcessPlaceholders;
 use crate::references::templates::{Template, TemplateVariable};
+use crate::settings::style_settings::CitationStyle;
 use crate::settings::Settings;
 use crate::utils::downloads::{DownloadManager, PendingDownload};
 use crate::utils::image_converting::{ImageConverter, PendingImage};
@@ -20,8 +25,9 @@ use bibliographix::references::bib_reference::BibRefAnchor;
 use image::ImageFormat;
 use mime::Mime;
 use parking_lot::Mutex;
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
 pub const SECTION: &str = "section";
@@ -54,6 +60,7 @@ pub enum Block {
     Section(Section),
     Paragraph(Paragraph),
     List(List),
+    DefinitionList(DefinitionList),
     Table(Table),
     CodeBlock(CodeBlock),
     MathBlock(MathBlock),
@@ -79,12 +86,14 @@ pub struct Document {
     pub(crate) is_root: bool,
     pub(crate) path: Option<String>,
     pub(crate) placeholders: Vec<Arc<RwLock<Placeholder>>>,
+    pub(crate) variables: HashMap<String, String>,
     pub config: Arc<Mutex<Settings>>,
     pub bibliography: BibManager,
     pub downloads: Arc<Mutex<DownloadManager>>,
     pub images: Arc<Mutex<ImageConverter>>,
     pub stylesheets: Vec<Arc<Mutex<PendingDownload>>>,
     pub glossary: Arc<Mutex<GlossaryManager>>,
+    pub(crate) bib_entry_positions: Arc<Mutex<BibEntryPositions>>,
 }
 
 #[derive(Clone, Debug)]
@@ -99,6 +108,9 @@ pub struct Header {
     pub(crate) size: u8,
     pub(crate) line: Line,
     pub(crate) anchor: String,
+    pub(crate) custom_anchor: bool,
+    pub(crate) number: Option<String>,
+    pub(crate) position: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -120,6 +132,25 @@ pub struct ListItem {
     pub(crate) children: Vec<ListItem>,
 }
 
+#[derive(Clone, Debug, Default)]
+pub struct DefinitionList {
+    pub(crate) groups: Vec<DefinitionGroup>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DefinitionGroup {
+    pub(crate) title: String,
+    pub(crate) definitions: Vec<Definition>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub(crate) key: String,
+    pub(crate) term: TextLine,
+    pub(crate) description: TextLine,
+    pub(crate) children: Vec<Definition>,
+}
+
 #[derive(Clone, Debug)]
 pub struct Table {
     pub(crate) header: Row,
@@ -161,7 +192,7 @@ pub struct ImportAnchor {
 
 #[derive(Clone, Debug)]
 pub struct InlineMetadata {
-    pub(crate) data: HashMap<String, MetadataValue>,
+    pub(crate) data: BTreeMap<String, MetadataValue>,
 }
 
 #[derive(Clone, Debug)]
@@ -188,8 +219,9 @@ pub enum Inline {
     Emoji(Emoji),
     Colored(Colored),
     Math(Math),
-    BibReference(Arc<RwLock<BibReference>>),
+    BibCitation(Arc<RwLock<BibCitation>>),
     GlossaryReference(Arc<Mutex<GlossaryReference>>),
+    IndexEntry(Arc<RwLock<IndexEntry>>),
     TemplateVar(Arc<RwLock<TemplateVariable>>),
     CharacterCode(CharacterCode),
     LineBreak,
@@ -241,6 +273,9 @@ pub struct Checkbox {
 pub struct Url {
     pub description: Option<Vec<Inline>>,
     pub url: String,
+    pub(crate) position: Option<String>,
+    /// The path of a linked local file relative to the document the url is defined in
+    pub(crate) local_path: Option<PathBuf>,
 }
 
 #[derive(Clone, Debug)]
@@ -255,6 +290,8 @@ pub struct Placeholder {
     pub(crate) name: String,
     pub(crate) value: Option<Element>,
     pub(crate) metadata: Option<InlineMetadata>,
+    pub(crate) citation_scope: Option<Vec<Arc<RwLock<BibCitation>>>>,
+    pub(crate) position: Option<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -321,12 +358,14 @@ impl Document {
             is_root: true,
             path: None,
             placeholders: Vec::new(),
+            variables: HashMap::new(),
             config: Arc::new(Mutex::new(Settings::default())),
             bibliography: BibManager::new(),
             stylesheets: Vec::new(),
             downloads: Arc::new(Mutex::new(DownloadManager::new())),
             images: Arc::new(Mutex::new(ImageConverter::new())),
             glossary: Arc::new(Mutex::new(GlossaryManager::new())),
+            bib_entry_positions: Arc::new(Mutex::new(BibEntryPositions::default())),
         }
     }
 
@@ -337,12 +376,14 @@ impl Document {
             is_root: false,
             path: None,
             placeholders: Vec::new(),
+            variables: self.variables.clone(),
             config: self.config.clone(),
             bibliography: self.bibliography.create_child(),
             stylesheets: Vec::new(),
             downloads: Arc::clone(&self.downloads),
             images: Arc::clone(&self.images),
             glossary: Arc::clone(&self.glossary),
+            bib_entry_positions: Arc::clone(&self.bib_entry_positions),
         }
     }
 
@@ -380,72 +421,123 @@ impl Document {
 
     /// Processes section and import elements
     ///
-    /// if it encounters a section it checks if the sections is of smaller order than the previous one
-    /// if thats the case it grabs the previous one and adds the section to its children
-    ///
-    /// if it encounters an import, it loads the imports top elements to its own
+    /// The elements of imported documents are inserted in place of the import.
+    /// Afterwards all sections are nested by their size so that imported sections end up
+    /// in the section they belong to regardless of the section they were imported in
     pub fn postprocess_imports(&mut self) {
-        let mut new_order: Vec<Block> = Vec::with_capacity(self.elements.len());
-        self.elements.reverse();
-        let mut count: usize = 0;
-        let mut last_section: Option<(u8, usize)> = None;
-
-        while let Some(element) = self.elements.pop() {
-            match element {
-                Block::Section(sec) => {
-                    if let Some((last_size, last_pos)) = last_section {
-                        if sec.header.size > last_size {
-                            let last = new_order.get_mut(last_pos).unwrap();
-                            if let Block::Section(p_sec) = last {
-                                p_sec.add_section(sec);
-                                continue;
-                            }
-                        }
-                    }
-                    last_section = Some((sec.header.size, count));
-                    new_order.push(Block::Section(sec));
-                }
-                Block::Import(imp) => {
-                    let arc_anchor = Arc::clone(&imp.anchor);
-                    let anchor = &mut arc_anchor.write().unwrap();
-
-                    if let Some(doc) = &mut anchor.document {
-                        self.placeholders.append(&mut doc.placeholders);
-                        doc.elements.reverse();
-                        self.elements.append(&mut doc.elements);
-                        anchor.document = None;
-                        continue;
-                    } else {
-                        new_order.push(Block::Import(imp));
-                    }
-                }
-                _ => {
-                    if let Some((_, last_pos)) = last_section {
-                        let last = new_order.get_mut(last_pos).unwrap();
-                        if let Block::Section(p_sec) = last {
-                            p_sec.add_element(element);
-                            continue;
-                        }
-                    }
-                    new_order.push(element)
-                }
-            }
-            count += 1;
-        }
-        self.elements = new_order;
+        let mut blocks = Vec::with_capacity(self.elements.len());
+        flatten_sections(
+            mem::take(&mut self.elements),
+            &mut blocks,
+            &mut self.placeholders,
+        );
+        self.elements = nest_sections(blocks);
     }
 
     pub fn post_process(&mut self) {
+        self.process_variables();
         self.postprocess_imports();
+        self.process_citation_scopes();
         if self.is_root {
             self.process_definitions();
             self.bibliography.assign_entries_to_references();
+            self.process_citations();
+            self.assign_citation_ids();
             self.glossary.lock().assign_entries_to_references();
+            self.assign_header_anchors();
+            self.assign_section_numbers();
+            self.assign_cross_references();
+            self.assign_index_ids();
             self.process_placeholders();
             self.process_media();
         }
     }
 
+    /// Assigns the fields used by references in the text to the cited entries
+    fn process_citations(&self) {
+        let (style, l10n) = {
+            let config = self.config.lock();
+            (config.style.clone(), config.get_localisation())
+        };
+        assign_citation_fields(
+            self.bibliography.get_entry_list_by_occurrence(),
+            &style,
+            &l10n,
+        );
+    }
+
+    /// Assigns a unique id to every reference in the text so that
+    /// bibliography entries can link back to the places they were cited at
+    fn assign_citation_ids(&self) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for citation in get_citation_list(&self.elements) {
+            for reference in &mut citation.write().unwrap().references {
+                let count = counts.entry(reference.key.clone()).or_insert(0);
+                *count += 1;
+                reference.id = Some(format!("cite-{}-{}", reference.key, count));
+            }
+        }
+    }
+
+    /// Makes the generated anchors of all headers unique by appending a number
+    /// to anchors that are already in use. Explicit anchors are kept as they are
+    fn assign_header_anchors(&mut self) {
+        if self.config.lock().features.legacy_header_anchors {
+            return;
+        }
+        let mut used_anchors = HashSet::new();
+        for element in &self.elements {
+            if let Block::Section(sec) = element {
+                sec.get_custom_anchors(&mut used_anchors);
+            }
+        }
+        for element in &mut self.elements {
+            if let Block::Section(sec) = element {
+                sec.make_anchors_unique(&mut used_anchors);
+            }
+        }
+    }
+
+    /// Numbers the sections if enabled in the settings or the metadata of a section
+    fn assign_section_numbers(&mut self) {
+        let numbered = self.config.lock().features.numbered_headings;
+        let mut count = 0;
+
+        for element in &mut self.elements {
+            if let Block::Section(sec) = element {
+                sec.assign_numbers(None, &mut count, numbered);
+            }
+        }
+    }
+
+    /// Assigns the numbered title of the section a link points to
+    /// to every link to a section without a description
+    fn assign_cross_references(&mut self) {
+        let mut titles = HashMap::new();
+        for element in &self.elements {
+            if let Block::Section(sec) = element {
+                sec.get_titles(&mut titles);
+            }
+        }
+        for element in &mut self.elements {
+            element.assign_link_titles(&titles);
+        }
+    }
+
+    /// Assigns a unique id to every index term in the text
+    /// so that the index can link to its occurrences
+    fn assign_index_ids(&self) {
+        for (index, entry) in self
+            .elements
+            .iter()
+            .flat_map(|b| b.get_index_entries())
+            .enumerate()
+        {
+            entry.write().unwrap().id = Some(format!("idx-{}", index + 1));
+        }
+    }
+
     fn process_media(&self) {
         let downloads = Arc::clone(&self.downloads);
         if self.config.lock().features.embed_external {
@@ -508,6 +600,78 @@ impl Section {
         list
     }
 
+    /// Adds the explicit anchors of the section and its subsections to the set
+    fn get_custom_anchors(&self, anchors: &mut HashSet<String>) {
+        if self.header.custom_anchor {
+            anchors.insert(self.header.anchor.clone());
+        }
+        for element in &self.elements {
+            if let Block::Section(sec) = element {
+                sec.get_custom_anchors(anchors);
+            }
+        }
+    }
+
+    /// Appends a number to the generated anchors of the section
+    /// and its subsections if they are already in use
+    fn make_anchors_unique(&mut self, used_anchors: &mut HashSet<String>) {
+        if !self.header.custom_anchor {
+            let anchor = self.header.anchor.clone();
+            let mut count = 0;
+
+            while used_anchors.contains(&self.header.anchor) {
+                count += 1;
+                self.header.anchor = format!("{}-{}", anchor, count);
+            }
+            used_anchors.insert(self.header.anchor.clone());
+        }
+        for element in &mut self.elements {
+            if let Block::Section(sec) = element {
+                sec.make_anchors_unique(used_anchors);
+            }
+        }
+    }
+
+    /// Numbers the section and its subsections following the number of the parent.
+    /// The `numbered` metadata of a section overrides the setting for the section
+    /// and its subsections. Sections that are hidden in the toc are not numbered
+    fn assign_numbers(&mut self, parent: Option<&str>, count: &mut usize, numbered: bool) {
+        if self.is_hidden_in_toc() {
+            return;
+        }
+        let numbered = match &self.metadata {
+            Some(meta) if meta.data.contains_key("numbered") => meta.get_bool("numbered"),
+            _ => numbered,
+        };
+        if numbered {
+            *count += 1;
+            self.header.number = Some(match parent {
+                Some(parent) => format!("{}.{}", parent, count),
+                None => count.to_string(),
+            });
+        }
+        let number = self.header.number.clone();
+        let mut child_count = 0;
+
+        for element in &mut self.elements {
+            if let Block::Section(sec) = element {
+                sec.assign_numbers(number.as_deref(), &mut child_count, numbered);
+            }
+        }
+    }
+
+    /// Adds the titles of the section and its subsections to the map of anchors
+    fn get_titles(&self, titles: &mut HashMap<String, Vec<Inline>>) {
+        titles
+            .entry(self.header.anchor.clone())
+            .or_insert_with(|| self.header.get_title().subtext);
+        for element in &self.elements {
+            if let Block::Section(sec) = element {
+                sec.get_titles(titles);
+            }
+        }
+    }
+
     pub(crate) fn is_hidden_in_toc(&self) -> bool {
         if let Some(meta) = &self.metadata {
             meta.get_bool("toc-hidden")
@@ -515,42 +679,72 @@ impl Section {
             false
         }
     }
+}
 
-    /// adds a child section to the section
-    /// It either adds it directly to its elements or iterates through its children to
-    /// add it to the fitting one
-    pub(crate) fn add_section(&mut self, section: Section) {
-        if section.header.size == self.header.size + 1 {
-            self.elements.push(Block::Section(section))
-        } else {
-            let has_parent = RwLock::new(AtomicBool::new(true));
-            let iterator = self.elements.iter_mut().rev().filter(|e| {
-                if let Block::Section(sec) = e {
-                    if sec.header.size > section.header.size {
-                        has_parent.write().unwrap().store(true, Ordering::Relaxed);
-                        true
-                    } else {
-                        false
-                    }
+/// Moves the elements of imported documents and sections into the given list
+/// so that the sections can be nested again by their size
+fn flatten_sections(
+    elements: Vec<Block>,
+    blocks: &mut Vec<Block>,
+    placeholders: &mut Vec<Arc<RwLock<Placeholder>>>,
+) {
+    for element in elements {
+        match element {
+            Block::Import(imp) => {
+                let document = imp.anchor.write().unwrap().document.take();
+
+                if let Some(mut doc) = document {
+                    placeholders.append(&mut doc.placeholders);
+                    flatten_sections(doc.elements, blocks, placeholders);
                 } else {
-                    false
+                    blocks.push(Block::Import(imp));
                 }
-            });
+            }
+            Block::Section(mut sec) => {
+                let elements = mem::take(&mut sec.elements);
+                blocks.push(Block::Section(sec));
+                flatten_sections(elements, blocks, placeholders);
+            }
+            _ => blocks.push(element),
+        }
+    }
+}
 
-            if has_parent.read().unwrap().load(Ordering::Relaxed) {
-                for sec in iterator {
-                    if let Block::Section(sec) = sec {
-                        if sec.header.size < section.header.size {
-                            sec.add_section(section);
-                            break;
-                        }
-                    }
-                }
-            } else {
-                self.elements.push(Block::Section(section));
+/// Nests a flat list of blocks so that every section contains the
+/// following blocks until a section of the same or a smaller size
+fn nest_sections(blocks: Vec<Block>) -> Vec<Block> {
+    let mut elements = Vec::with_capacity(blocks.len());
+    let mut open_sections: Vec<Section> = Vec::new();
+
+    for block in blocks {
+        if let Block::Section(sec) = block {
+            while open_sections
+                .last()
+                .map(|open| open.header.size >= sec.header.size)
+                .unwrap_or(false)
+            {
+                let closed = open_sections.pop().unwrap();
+                add_nested_block(&mut open_sections, &mut elements, Block::Section(closed));
             }
+            open_sections.push(sec);
+        } else {
+            add_nested_block(&mut open_sections, &mut elements, block);
         }
     }
+    while let Some(closed) = open_sections.pop() {
+        add_nested_block(&mut open_sections, &mut elements, Block::Section(closed));
+    }
+
+    elements
+}
+
+/// Adds the block to the innermost open section or to the elements if no section is open
+fn add_nested_block(open_sections: &mut [Section], elements: &mut Vec<Block>, block: Block) {
+    if let Some(parent) = open_sections.last_mut() {
+        parent.add_element(block);
+    } else {
+        elements.push(block);
+    }
 }
 
 impl Header {
@@ -558,16 +752,34 @@ impl Header {
         Self {
             size: 0,
             anchor,
+            custom_anchor: false,
+            number: None,
             line: content,
+            position: None,
         }
     }
 
     pub fn get_anchor(&self) -> RefLink {
         RefLink {
-            description: self.line.as_raw_text().as_plain_line(),
+            description: self.get_title(),
             reference: self.anchor.clone(),
         }
     }
+
+    /// Returns the plain text of the header prefixed with the section number
+    pub fn get_title(&self) -> TextLine {
+        let mut title = self.line.as_raw_text().as_plain_line();
+        if let Some(number) = &self.number {
+            title.subtext.insert(
+                0,
+                Inline::Plain(PlainText {
+                    value: format!("{} ", number),
+                }),
+            );
+        }
+
+        title
+    }
 }
 
 impl Paragraph {
@@ -595,6 +807,35 @@ impl List {
     }
 }
 
+impl DefinitionList {
+    pub fn new() -> Self {
+        Self { groups: Vec::new() }
+    }
+
+    /// Adds a definition to the group with the given title.
+    /// The group is created if it isn't the last one
+    pub fn add_definition(&mut self, title: String, definition: Definition) {
+        match self.groups.last_mut() {
+            Some(group) if group.title == title => group.definitions.push(definition),
+            _ => self.groups.push(DefinitionGroup {
+                title,
+                definitions: vec![definition],
+            }),
+        }
+    }
+}
+
+impl Definition {
+    pub fn new(key: String, term: TextLine, description: TextLine) -> Self {
+        Self {
+            key,
+            term,
+            description,
+            children: Vec::new(),
+        }
+    }
+}
+
 impl ListItem {
     pub fn new(text: Line, level: u16, ordered: bool) -> Self {
         Self {
@@ -657,7 +898,12 @@ impl Row {
 
 impl Url {
     pub fn new(description: Option<Vec<Inline>>, url: String) -> Self {
-        Self { description, url }
+        Self {
+            description,
+            url,
+            position: None,
+            local_path: None,
+        }
     }
 }
 
@@ -705,6 +951,8 @@ impl Placeholder {
             name,
             value: None,
             metadata,
+            citation_scope: None,
+            position: None,
         }
     }
 
@@ -796,6 +1044,30 @@ pub struct BibReference {
     pub(crate) key: String,
     pub(crate) entry_anchor: Arc<Mutex<BibRefAnchor>>,
     pub(crate) display: Option<String>,
+    pub(crate) missing_text: String,
+    pub(crate) prefix: Option<String>,
+    pub(crate) locator: Option<String>,
+    pub(crate) id: Option<String>,
+    pub(crate) number: Option<String>,
+    pub(crate) target: Option<String>,
+    pub(crate) position: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BibCitation {
+    pub(crate) references: Vec<BibReference>,
+    pub(crate) style: CitationStyle,
+    pub(crate) textual: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum CitationPart {
+    Text(String),
+    Reference {
+        target: String,
+        text: String,
+        ids: Vec<String>,
+    },
 }
 
 impl BibReference {
@@ -804,28 +1076,224 @@ impl BibReference {
             key: key.to_string(),
             display,
             entry_anchor: anchor,
+            missing_text: "citation needed".to_string(),
+            prefix: None,
+            locator: None,
+            id: None,
+            number: None,
+            target: None,
+            position: None,
         }
     }
 
     pub(crate) fn get_formatted(&self) -> String {
-        if let Some(entry) = &self.entry_anchor.lock().entry {
-            let entry = entry.lock();
+        let display = self
+            .display
+            .clone()
+            .unwrap_or_else(|| "{{key}}".to_string());
+
+        self.render_template(display)
+            .unwrap_or_else(|| self.missing_text.clone())
+    }
+
+    /// Returns the anchor of the bibliography entry the reference links to
+    pub(crate) fn get_target(&self) -> String {
+        self.target.clone().unwrap_or_else(|| self.key.clone())
+    }
+
+    /// Returns if the reference consists of nothing but the entries number
+    fn get_plain_number(&self) -> Option<usize> {
+        if self.prefix.is_some() || self.locator.is_some() {
+            return None;
+        }
+        self.get_formatted().parse().ok()
+    }
 
-            if let Some(display) = &self.display {
-                let mut template = PlaceholderTemplate::new(display.clone());
-                let mut value_map = HashMap::new();
-                value_map.insert("key".to_string(), entry.key());
+    /// Returns the formatted reference together with its prefix and locator
+    fn get_decorated(&self) -> String {
+        let mut text = self.get_formatted();
 
-                if let Some(ord) = entry.raw_fields.get("ord") {
-                    value_map.insert("number".to_string(), ord.clone());
+        if let Some(prefix) = &self.prefix {
+            text = format!("{} {}", prefix, text);
+        }
+        if let Some(locator) = &self.locator {
+            text = format!("{}, {}", text, locator);
+        }
+
+        text
+    }
+
+    /// Renders the given template with the fields of the referenced entry
+    fn render_template(&self, display: String) -> Option<String> {
+        let entry = self.entry_anchor.lock().entry.clone()?;
+        let entry = entry.lock();
+        let mut template = PlaceholderTemplate::new(display);
+        let mut value_map = HashMap::new();
+        value_map.insert("key".to_string(), entry.key());
+
+        if let Some(number) = &self.number {
+            value_map.insert("number".to_string(), number.clone());
+        } else if let Some(ord) = entry.raw_fields.get("ord") {
+            value_map.insert("number".to_string(), ord.clone());
+        }
+        if let Some(author) = entry.raw_fields.get("cite_author") {
+            value_map.insert("author".to_string(), author.clone());
+        }
+        if let Some(year) = entry.raw_fields.get("year") {
+            value_map.insert("year".to_string(), year.clone());
+        }
+        if let Some(title) = entry.raw_fields.get("title") {
+            value_map.insert("title".to_string(), title.clone());
+        }
+        template.set_replacements(value_map);
+
+        Some(template.render())
+    }
+}
+
+impl BibCitation {
+    pub fn new(references: Vec<BibReference>, style: CitationStyle, textual: bool) -> Self {
+        Self {
+            references,
+            style,
+            textual,
+        }
+    }
+
+    /// Returns if the citation is rendered as superscript
+    pub fn is_superscript(&self) -> bool {
+        !self.textual && self.style.is_superscript()
+    }
+
+    /// Returns the text and reference parts of the formatted citation
+    pub(crate) fn get_parts(&self) -> Vec<CitationPart> {
+        if self.textual {
+            return self.get_textual_parts();
+        }
+        let (open, close) = self.style.get_brackets();
+        let mut parts = Vec::new();
+
+        if !open.is_empty() {
+            parts.push(CitationPart::Text(open.to_string()));
+        }
+        for (index, item) in self.get_items().into_iter().enumerate() {
+            if index > 0 {
+                parts.push(CitationPart::Text(self.style.get_separator().to_string()));
+            }
+            parts.push(item);
+        }
+        if !close.is_empty() {
+            parts.push(CitationPart::Text(close.to_string()));
+        }
+
+        parts
+    }
+
+    /// Returns the parts of a citation that is part of the sentence
+    /// like `Smith (2020)`
+    fn get_textual_parts(&self) -> Vec<CitationPart> {
+        let (open, close) = match self.style.get_brackets() {
+            ("", "") => ("[", "]"),
+            brackets => brackets,
+        };
+        let mut parts = Vec::new();
+
+        for (index, reference) in self.references.iter().enumerate() {
+            if index > 0 {
+                parts.push(CitationPart::Text(self.style.get_separator().to_string()));
+            }
+            let author = reference.render_template("{{author}}".to_string());
+            let text = if let Some(author) = author {
+                let mut inner = reference
+                    .render_template(self.style.get_textual_display().to_string())
+                    .unwrap_or_default();
+                if let Some(locator) = &reference.locator {
+                    inner = format!("{}, {}", inner, locator);
+                }
+                if author.is_empty() {
+                    format!("{}{}{}", open, inner, close)
+                } else {
+                    format!("{} {}{}{}", author, open, inner, close)
+                }
+            } else {
+                reference.missing_text.clone()
+            };
+            if let Some(prefix) = &reference.prefix {
+                parts.push(CitationPart::Text(format!("{} ", prefix)));
+            }
+            parts.push(CitationPart::Reference {
+                target: reference.get_target(),
+                text,
+                ids: reference.id.iter().cloned().collect(),
+            });
+        }
+
+        parts
+    }
+
+    /// Returns the reference parts of the citation.
+    /// For numbered styles plain numbers are sorted and ranges of
+    /// three or more consecutive numbers are compressed to `1–3`.
+    fn get_items(&self) -> Vec<CitationPart> {
+        let to_part = |reference: &BibReference| CitationPart::Reference {
+            target: reference.get_target(),
+            text: reference.get_decorated(),
+            ids: reference.id.iter().cloned().collect(),
+        };
+        if !self.style.is_numbered() {
+            return self.references.iter().map(to_part).collect();
+        }
+        let mut numbers: Vec<(usize, &BibReference)> = Vec::new();
+        let mut others = Vec::new();
+
+        for reference in &self.references {
+            if let Some(number) = reference.get_plain_number() {
+                numbers.push((number, reference));
+            } else {
+                others.push(to_part(reference));
+            }
+        }
+        numbers.sort_by_key(|(number, _)| *number);
+        let mut items = Vec::new();
+        let mut index = 0;
+
+        while index < numbers.len() {
+            let mut end = index;
+            let mut count = 1;
+            while end + 1 < numbers.len() && numbers[end + 1].0 <= numbers[end].0 + 1 {
+                if numbers[end + 1].0 > numbers[end].0 {
+                    count += 1;
+                }
+                end += 1;
+            }
+            let range = &numbers[index..=end];
+
+            if count >= 3 {
+                items.push(CitationPart::Reference {
+                    target: range[0].1.get_target(),
+                    text: format!("{}–{}", range[0].0, range[range.len() - 1].0),
+                    ids: range.iter().filter_map(|(_, r)| r.id.clone()).collect(),
+                });
+            } else {
+                let mut last: Option<usize> = None;
+                for (number, reference) in range {
+                    if last == Some(*number) {
+                        if let (Some(CitationPart::Reference { ids, .. }), Some(id)) =
+                            (items.last_mut(), reference.id.clone())
+                        {
+                            ids.push(id);
+                        }
+                    } else {
+                        items.push(to_part(reference));
+                    }
+                    last = Some(*number);
                 }
-                template.set_replacements(value_map);
-                return template.render();
             }
-            return format!("{}", entry.key());
+            index = end + 1;
         }
+        items.append(&mut others);
 
-        return "citation needed".to_string();
+        items
     }
 }
 
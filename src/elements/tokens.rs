@@ -42,6 +42,7 @@ pub(crate) const MATH: char = '$';
 pub(crate) const DOLLAR: char = '$';
 pub(crate) const AMPERSAND: char = '&';
 pub(crate) const QUESTION_MARK: char = '?';
+pub(crate) const AT: char = '@';
 
 // aliases
 
@@ -68,6 +69,9 @@ pub(crate) const COLOR_CLOSE: char = L_BRACKET;
 pub(crate) const BIBREF_OPEN: char = R_BRACKET;
 pub(crate) const BIBREF_REF: char = UP;
 pub(crate) const BIBREF_CLOSE: char = L_BRACKET;
+pub(crate) const BIBREF_TEXTUAL: char = AT;
+pub(crate) const BIBREF_SEPARATOR: char = SEMICOLON;
+pub(crate) const BIBREF_LOCATOR: char = COMMA;
 pub(crate) const BIB_KEY_OPEN: char = R_BRACKET;
 pub(crate) const BIB_KEY_CLOSE: char = L_BRACKET;
 pub(crate) const BIB_DATA_START: char = COLON;
@@ -88,6 +92,8 @@ pub(crate) const CHARACTER_START: char = AMPERSAND;
 pub(crate) const CHARACTER_STOP: char = SEMICOLON;
 
 pub(crate) const GLOSSARY_REF_START: char = TILDE;
+pub(crate) const GLOSSARY_REF_DISPLAY: char = COLON;
+pub(crate) const GLOSSARY_DEF_OPEN: char = META_OPEN;
 
 // Reference Anchors
 
@@ -101,6 +107,52 @@ pub(crate) const REF_STOP: char = L_BRACKET;
 pub(crate) const REF_DESC_START: char = R_PARENTH;
 pub(crate) const REF_DESC_STOP: char = L_PARENTH;
 
+// Index
+
+pub(crate) const INDEX_START: &[char] = &[R_BRACKET, BANG];
+pub(crate) const INDEX_STOP: char = L_BRACKET;
+pub(crate) const INDEX_SUB: char = BANG;
+pub(crate) const INDEX_CROSS_REF: char = PIPE;
+
+// Conditionals
+
+pub(crate) const SQ_COND_IF: &[char] = &[
+    PHOLDER_OPEN,
+    PHOLDER_OPEN,
+    'i',
+    'f',
+    PHOLDER_CLOSE,
+    PHOLDER_CLOSE,
+];
+pub(crate) const SQ_COND_ELSE: &[char] = &[
+    PHOLDER_OPEN,
+    PHOLDER_OPEN,
+    'e',
+    'l',
+    's',
+    'e',
+    PHOLDER_CLOSE,
+    PHOLDER_CLOSE,
+];
+pub(crate) const SQ_COND_END: &[char] = &[
+    PHOLDER_OPEN,
+    PHOLDER_OPEN,
+    'e',
+    'n',
+    'd',
+    'i',
+    'f',
+    PHOLDER_CLOSE,
+    PHOLDER_CLOSE,
+];
+
+// Front matter
+
+pub(crate) const SQ_FRONT_MATTER_TOML: &[char] = &[PLUS, PLUS, PLUS, LB];
+pub(crate) const SQ_FRONT_MATTER_TOML_END: &[char] = &[LB, PLUS, PLUS, PLUS];
+pub(crate) const SQ_FRONT_MATTER_YAML: &[char] = &[MINUS, MINUS, MINUS, LB];
+pub(crate) const SQ_FRONT_MATTER_YAML_END: &[char] = &[LB, MINUS, MINUS, MINUS];
+
 // Arrows
 
 pub(crate) const A_RIGHT_ARROW: &'static [char] = &['-', '-', '>'];
@@ -125,6 +177,9 @@ pub(crate) const BLOCK_SPECIAL_CHARS: &'static [&[char]] = &[
     &[IMPORT_START, IMPORT_OPEN],
     &SQ_CENTERED_START,
     &SQ_MATH,
+    SQ_COND_IF,
+    SQ_COND_ELSE,
+    SQ_COND_END,
 ];
 
 pub(crate) const INLINE_SPECIAL_CHARS: &'static [char] = &[
@@ -151,6 +206,7 @@ pub(crate) const INLINE_SPECIAL_SEQUENCES: &'static [&'static [char]] = &[
     A_LEFT_RIGHT_ARROW,
     ANCHOR_START,
     REF_START,
+    INDEX_START,
 ];
 
 pub(crate) const LIST_SPECIAL_CHARS: [char; 14] = [
@@ -11,8 +11,10 @@ use crate::format::html::to_html::ToHtml;
 use crate::settings::Settings;
 use crate::utils::caching::CacheStorage;
 use bibliographix::Mutex;
+use chrono::NaiveDateTime;
 use headless_chrome::protocol::page::PrintToPdfOptions;
 use headless_chrome::{Browser, LaunchOptionsBuilder, Tab};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::fs::OpenOptions;
 use std::io::BufWriter;
@@ -30,6 +32,8 @@ pub fn render_to_pdf(document: Document) -> PdfRenderingResult<Vec<u8>> {
     file_path = cache.get_file_path(&file_path);
     let config = document.config.clone();
     let mathjax = config.lock().features.include_mathjax;
+    let reproducible = config.lock().is_reproducible();
+    let timestamp = config.lock().get_timestamp();
 
     let handle = thread::spawn({
         let file_path = file_path.clone();
@@ -60,7 +64,11 @@ pub fn render_to_pdf(document: Document) -> PdfRenderingResult<Vec<u8>> {
         wait_for_mathjax(&tab, Duration::from_secs(60))?;
     }
     log::info!("Rendering pdf...");
-    let result = tab.print_to_pdf(Some(get_pdf_options(config)))?;
+    let mut result = tab.print_to_pdf(Some(get_pdf_options(config)))?;
+
+    if reproducible {
+        make_reproducible(&mut result, &timestamp);
+    }
     log::info!("Removing temporary html...");
     fs::remove_file(file_path)?;
 
@@ -125,3 +133,80 @@ fn get_pdf_options(config: Arc<Mutex<Settings>>) -> PrintToPdfOptions {
         prefer_css_page_size: None,
     }
 }
+
+/// Replaces the creation and modification dates of the pdf with the given timestamp
+/// and the document id with a hash of the content.
+/// All values keep their length so that the cross reference table stays valid.
+fn make_reproducible(pdf: &mut Vec<u8>, timestamp: &NaiveDateTime) {
+    let date = timestamp.format("D:%Y%m%d%H%M%S").to_string();
+    let dates = [
+        format!("{}+00'00'", date),
+        format!("{}+00'00", date),
+        format!("{}Z", date),
+        date,
+    ];
+
+    for key in &[&b"/CreationDate"[..], &b"/ModDate"[..]] {
+        for (start, end) in find_values(pdf, key, b'(', b')') {
+            if let Some(date) = dates.iter().find(|d| d.len() == end - start) {
+                pdf[start..end].copy_from_slice(date.as_bytes());
+            } else {
+                log::warn!(
+                    "Failed to replace the pdf date {}",
+                    String::from_utf8_lossy(&pdf[start..end])
+                );
+            }
+        }
+    }
+
+    let id_ranges: Vec<(usize, usize)> = find_values(pdf, b"/ID", b'[', b']')
+        .into_iter()
+        .flat_map(|(start, end)| {
+            find_values(&pdf[start..end], b"", b'<', b'>')
+                .into_iter()
+                .map(move |(s, e)| (start + s, start + e))
+        })
+        .collect();
+    for (start, end) in &id_ranges {
+        pdf[*start..*end].iter_mut().for_each(|b| *b = b'0');
+    }
+    let hash = Sha256::digest(&pdf[..])
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    for (start, end) in id_ranges {
+        let id = hash.bytes().cycle().take(end - start).collect::<Vec<u8>>();
+        pdf[start..end].copy_from_slice(&id);
+    }
+}
+
+/// Returns the ranges of the values enclosed by the open and close byte
+/// that directly follow the given key
+fn find_values(data: &[u8], key: &[u8], open: u8, close: u8) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut index = 0;
+
+    while index + key.len() < data.len() {
+        if !data[index..].starts_with(key) {
+            index += 1;
+            continue;
+        }
+        let mut start = index + key.len();
+        while start < data.len() && data[start].is_ascii_whitespace() {
+            start += 1;
+        }
+        if start >= data.len() || data[start] != open {
+            index += 1;
+            continue;
+        }
+        start += 1;
+        if let Some(length) = data[start..].iter().position(|b| *b == close) {
+            ranges.push((start, start + length));
+            index = start + length + 1;
+        } else {
+            break;
+        }
+    }
+
+    ranges
+}
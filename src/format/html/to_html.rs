@@ -8,7 +8,8 @@ use crate::elements::*;
 use crate::format::html::html_writer::HTMLWriter;
 use crate::format::style::{get_code_theme_for_theme, get_css_for_theme};
 use crate::format::PlaceholderTemplate;
-use crate::references::glossary::{GlossaryDisplay, GlossaryReference};
+use crate::references::glossary::GlossaryReference;
+use crate::references::index::IndexEntry;
 use crate::references::templates::{Template, TemplateVariable};
 use asciimath_rs::format::mathml::ToMathML;
 use htmlescape::encode_attribute;
@@ -61,12 +62,13 @@ impl ToHtml for Inline {
             Inline::Checkbox(checkbox) => checkbox.to_html(writer),
             Inline::Emoji(emoji) => emoji.to_html(writer),
             Inline::Colored(colored) => colored.to_html(writer),
-            Inline::BibReference(bibref) => bibref.read().unwrap().to_html(writer),
+            Inline::BibCitation(citation) => citation.read().unwrap().to_html(writer),
             Inline::TemplateVar(var) => var.read().unwrap().to_html(writer),
             Inline::Math(m) => m.to_html(writer),
             Inline::LineBreak => writer.write("<br>".to_string()),
             Inline::CharacterCode(code) => code.to_html(writer),
             Inline::GlossaryReference(gloss) => gloss.lock().to_html(writer),
+            Inline::IndexEntry(entry) => entry.read().unwrap().to_html(writer),
             Inline::Arrow(a) => a.to_html(writer),
             Inline::Anchor(a) => a.to_html(writer),
         }
@@ -78,6 +80,7 @@ impl ToHtml for Block {
         match self {
             Block::Paragraph(para) => para.to_html(writer),
             Block::List(list) => list.to_html(writer),
+            Block::DefinitionList(list) => list.to_html(writer),
             Block::Table(table) => table.to_html(writer),
             Block::CodeBlock(code) => code.to_html(writer),
             Block::Quote(quote) => quote.to_html(writer),
@@ -242,6 +245,11 @@ impl ToHtml for Header {
         writer.write(" id=\"".to_string())?;
         writer.write_attribute(self.anchor.clone())?;
         writer.write("\">".to_string())?;
+        if let Some(number) = &self.number {
+            writer.write("<span class=\"sectionNumber\">".to_string())?;
+            writer.write_escaped(number.clone())?;
+            writer.write("</span> ".to_string())?;
+        }
         self.line.to_html(writer)?;
 
         writer.write(format!("</h{}>", self.size))
@@ -285,6 +293,44 @@ impl ToHtml for List {
     }
 }
 
+impl ToHtml for DefinitionList {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<div class=\"definitionList\">".to_string())?;
+        for group in &self.groups {
+            writer
+                .write("<div class=\"definitionGroup\"><span class=\"groupTitle\">".to_string())?;
+            writer.write_escaped(group.title.clone())?;
+            writer.write("</span><dl>".to_string())?;
+            for definition in &group.definitions {
+                definition.to_html(writer)?;
+            }
+            writer.write("</dl></div>".to_string())?;
+        }
+
+        writer.write("</div>".to_string())
+    }
+}
+
+impl ToHtml for Definition {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<dt id=\"".to_string())?;
+        writer.write_attribute(self.key.clone())?;
+        writer.write("\">".to_string())?;
+        self.term.to_html(writer)?;
+        writer.write("</dt><dd>".to_string())?;
+        self.description.to_html(writer)?;
+        if !self.children.is_empty() {
+            writer.write("<dl>".to_string())?;
+            for child in &self.children {
+                child.to_html(writer)?;
+            }
+            writer.write("</dl>".to_string())?;
+        }
+
+        writer.write("</dd>".to_string())
+    }
+}
+
 impl ToHtml for ListItem {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<li>".to_string())?;
@@ -629,14 +675,39 @@ impl ToHtml for Colored {
     }
 }
 
-impl ToHtml for BibReference {
-    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        writer.write("<sup><a href=\"#".to_string())?;
-        writer.write_attribute(self.key.clone())?;
-        writer.write("\">".to_string())?;
-        writer.write(self.get_formatted())?;
+impl ToHtml for BibCitation {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        if self.is_superscript() {
+            writer.write("<sup>".to_string())?;
+        }
+        for part in self.get_parts() {
+            match part {
+                CitationPart::Text(text) => writer.write_escaped(text)?,
+                CitationPart::Reference { target, text, ids } => {
+                    for id in ids.iter().skip(1) {
+                        writer.write("<span id=\"".to_string())?;
+                        writer.write_attribute(id.clone())?;
+                        writer.write("\"></span>".to_string())?;
+                    }
+                    writer.write("<a".to_string())?;
+                    if let Some(id) = ids.first() {
+                        writer.write(" id=\"".to_string())?;
+                        writer.write_attribute(id.clone())?;
+                        writer.write("\"".to_string())?;
+                    }
+                    writer.write(" href=\"#".to_string())?;
+                    writer.write_attribute(target)?;
+                    writer.write("\">".to_string())?;
+                    writer.write_escaped(text)?;
+                    writer.write("</a>".to_string())?;
+                }
+            }
+        }
+        if self.is_superscript() {
+            writer.write("</sup>".to_string())?;
+        }
 
-        writer.write("</a></sup>".to_string())
+        Ok(())
     }
 }
 
@@ -684,15 +755,11 @@ impl ToHtml for Anchor {
 
 impl ToHtml for GlossaryReference {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
-        if let Some(entry) = &self.entry {
-            let entry = entry.lock();
+        if let (Some(entry), Some(text)) = (&self.entry, self.get_text()) {
             writer.write("<a class=\"glossaryReference\" href=\"#".to_string())?;
-            writer.write_attribute(self.short.clone())?;
+            writer.write_attribute(entry.lock().short.clone())?;
             writer.write("\">".to_string())?;
-            match self.display {
-                GlossaryDisplay::Short => writer.write_escaped(entry.short.clone())?,
-                GlossaryDisplay::Long => writer.write_escaped(entry.long.clone())?,
-            }
+            writer.write_escaped(text)?;
             writer.write("</a>".to_string())?;
         } else {
             writer.write_escaped(format!("~{}", self.short.clone()))?;
@@ -702,6 +769,19 @@ impl ToHtml for GlossaryReference {
     }
 }
 
+impl ToHtml for IndexEntry {
+    fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
+        writer.write("<span class=\"indexEntry\"".to_string())?;
+        if let Some(id) = &self.id {
+            writer.write(" id=\"".to_string())?;
+            writer.write_attribute(id.clone())?;
+            writer.write("\"".to_string())?;
+        }
+
+        writer.write("></span>".to_string())
+    }
+}
+
 impl ToHtml for Arrow {
     fn to_html(&self, writer: &mut HTMLWriter) -> io::Result<()> {
         writer.write("<span class=\"arrow\">".to_string())?;
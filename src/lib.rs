@@ -6,6 +6,7 @@
 
 pub mod elements;
 pub mod format;
+pub mod lint;
 pub mod parser;
 pub mod references;
 pub mod settings;
@@ -0,0 +1,299 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Document, Url};
+use crate::lint::structure::StructureLinter;
+use crate::lint::{LintIssue, LintSeverity};
+use crate::utils::caching::CacheStorage;
+use crate::utils::downloads::{check_url, is_remote};
+use crate::utils::parsing::get_local_path;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const R_DEAD_LOCAL_LINK: &str = "dead-local-link";
+pub const R_MISSING_FILE: &str = "missing-file";
+pub const R_BROKEN_URL: &str = "broken-url";
+
+const K_URL: &str = "url";
+
+/// The time to wait for a response when checking urls online
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A link in the document with the position it was defined at
+/// and the path of the file it points to if it is a link to a local file
+#[derive(Clone, Debug)]
+pub struct Link {
+    pub url: String,
+    pub position: Option<String>,
+    pub path: Option<PathBuf>,
+}
+
+/// Checks if remote urls can be reached.
+/// Successful checks are cached for a day if a cache is set
+#[derive(Clone, Debug)]
+pub struct UrlChecker {
+    client: reqwest::blocking::Client,
+    cache: Option<CacheStorage>,
+}
+
+impl Link {
+    pub fn new(url: String, position: Option<String>) -> Self {
+        Self {
+            url,
+            position,
+            path: None,
+        }
+    }
+
+    pub fn from_url(url: &Url) -> Self {
+        Self {
+            url: url.url.clone(),
+            position: url.position.clone(),
+            path: url.local_path.clone(),
+        }
+    }
+
+    /// Returns the anchor if the link points to a location in the document
+    fn get_anchor(&self) -> Option<&str> {
+        self.url.strip_prefix('#')
+    }
+}
+
+impl UrlChecker {
+    /// Creates a new checker that sends requests with the given client
+    /// and caches successful checks in the given cache
+    pub fn new(client: reqwest::blocking::Client, cache: Option<CacheStorage>) -> Self {
+        Self { client, cache }
+    }
+
+    /// Creates a checker with a client that times out after ten seconds
+    pub fn with_timeout(cache: Option<CacheStorage>) -> reqwest::Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self::new(client, cache))
+    }
+
+    /// Checks if the url can be reached
+    fn check(&self, url: &str) -> Result<(), String> {
+        check_url(url, &self.client, self.cache.as_ref())
+    }
+}
+
+/// Checks all links of the document including urls of bibliography entries.
+/// Links to anchors have to point to a header or anchor in the document
+/// and links to local files have to point to an existing file.
+/// Remote urls are only checked if a url checker is given
+pub fn check_links(document: &Document, checker: Option<&UrlChecker>) -> Vec<LintIssue> {
+    let linter = StructureLinter::from_document(document);
+    let mut links = linter.links.clone();
+    links.append(&mut get_bibliography_links(document));
+
+    let mut issues = check_anchor_links(&links, &linter.targets);
+    issues.append(&mut check_local_files(&links));
+
+    if let Some(checker) = checker {
+        let urls = group_links(links.iter().filter(|l| is_remote(&l.url)));
+        issues.append(&mut check_remote_links(urls, checker));
+    }
+
+    issues
+}
+
+/// Returns an issue for every link to a local file that doesn't exist.
+/// The paths are resolved relative to the document the link is defined in
+pub(crate) fn check_local_files(links: &[Link]) -> Vec<LintIssue> {
+    let files = links
+        .iter()
+        .filter_map(|link| {
+            let path = link.path.as_ref()?.to_string_lossy().to_string();
+            if is_remote(&path) {
+                None
+            } else {
+                Some(Link::new(path, link.position.clone()))
+            }
+        })
+        .collect::<Vec<Link>>();
+
+    group_links(files.iter())
+        .into_iter()
+        .filter(|(path, _)| !Path::new(path).exists())
+        .map(|(path, positions)| {
+            LintIssue::new(
+                R_MISSING_FILE,
+                LintSeverity::Error,
+                format!("The linked file '{}' does not exist", path),
+                positions,
+            )
+        })
+        .collect()
+}
+
+/// Returns an issue for every link to an anchor that doesn't exist
+pub(crate) fn check_anchor_links(links: &[Link], targets: &HashSet<String>) -> Vec<LintIssue> {
+    let anchors = group_links(
+        links
+            .iter()
+            .filter(|l| matches!(l.get_anchor(), Some(a) if !targets.contains(a))),
+    );
+
+    anchors
+        .into_iter()
+        .map(|(link, positions)| {
+            LintIssue::new(
+                R_DEAD_LOCAL_LINK,
+                LintSeverity::Error,
+                format!("The link target '{}' does not exist", link),
+                positions,
+            )
+        })
+        .collect()
+}
+
+/// Sends a request to every url in parallel and returns an issue for every url
+/// that can't be reached
+fn check_remote_links(urls: BTreeMap<String, Vec<String>>, checker: &UrlChecker) -> Vec<LintIssue> {
+    urls.into_par_iter()
+        .filter_map(|(url, positions)| {
+            log::debug!("Checking {}", url);
+            checker.check(&url).err().map(|e| {
+                LintIssue::new(
+                    R_BROKEN_URL,
+                    LintSeverity::Error,
+                    format!("The url '{}' can't be reached: {}", url, e),
+                    positions,
+                )
+            })
+        })
+        .collect()
+}
+
+/// Returns the urls of the bibliography entries with the position of the entry.
+/// Local paths are resolved relative to the document
+fn get_bibliography_links(document: &Document) -> Vec<Link> {
+    let dictionary = document.bibliography.entry_dictionary();
+    let dictionary = dictionary.lock();
+    let positions = document.bib_entry_positions.lock();
+    let directory = document
+        .path
+        .as_ref()
+        .and_then(|p| Path::new(p).parent())
+        .unwrap_or_else(|| Path::new(""));
+
+    positions
+        .get_entries()
+        .iter()
+        .filter_map(|(key, positions)| {
+            let entry = dictionary.get(key)?;
+            let url = entry.lock().raw_fields.get(K_URL).cloned()?;
+
+            let mut link = Link::new(url, positions.first().cloned());
+            link.path = get_local_path(&link.url).map(|p| directory.join(p));
+
+            Some(link)
+        })
+        .collect()
+}
+
+/// Groups the links by their url
+fn group_links<'a, I: Iterator<Item = &'a Link>>(links: I) -> BTreeMap<String, Vec<String>> {
+    let mut grouped: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for link in links {
+        let positions = grouped.entry(link.url.clone()).or_default();
+        if let Some(position) = &link.position {
+            if !positions.contains(position) {
+                positions.push(position.clone());
+            }
+        }
+    }
+
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParserOptions;
+    use crate::utils::downloads::tests::{create_test_cache, start_test_server};
+    use crate::Parser;
+    use std::fs;
+
+    /// Parses the document written to a new temporary directory
+    /// that contains the given empty files and returns the directory
+    fn parse_document(name: &str, content: &str, files: &[&str]) -> (Document, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("snekdown-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        fs::write(dir.join("main.sd"), content).unwrap();
+        let document =
+            Parser::with_defaults(ParserOptions::default().add_path(dir.join("main.sd"))).parse();
+
+        (document, dir)
+    }
+
+    #[test]
+    fn it_resolves_local_files_relative_to_the_document() {
+        let (document, dir) = parse_document(
+            "local-links",
+            "[other](other.sd#section) [missing](missing.sd)\n",
+            &["other.sd"],
+        );
+        let issues = check_links(&document, None);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, R_MISSING_FILE);
+        assert!(issues[0].message.contains("missing.sd"));
+    }
+
+    #[test]
+    fn it_checks_remote_links() {
+        let (url, requests) = start_test_server(|head| {
+            if head.starts_with("head /ok ") {
+                "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string()
+            }
+        });
+        let (document, dir) = parse_document(
+            "remote-links",
+            &format!("[ok]({url}/ok) [missing]({url}/missing)\n", url = url),
+            &[],
+        );
+        let _ = fs::remove_dir_all(&dir);
+        let cache = create_test_cache("links");
+        let client = reqwest::blocking::Client::new();
+        let count_ok_requests = || {
+            requests
+                .lock()
+                .iter()
+                .filter(|r| r.starts_with("head /ok "))
+                .count()
+        };
+
+        let cached_checker = UrlChecker::new(client.clone(), Some(cache.clone()));
+        for (checker, ok_requests) in &[
+            (&cached_checker, 1),
+            (&cached_checker, 1),
+            (&UrlChecker::new(client, None), 2),
+        ] {
+            let issues = check_links(&document, Some(checker));
+            assert_eq!(issues.len(), 1);
+            assert_eq!(issues[0].rule, R_BROKEN_URL);
+            assert!(issues[0].message.contains("/missing"));
+            assert_eq!(count_ok_requests(), *ok_requests);
+        }
+        let _ = cache.clear();
+    }
+}
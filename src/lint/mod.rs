@@ -0,0 +1,209 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::lint::links::*;
+use crate::lint::references::*;
+use crate::lint::structure::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+pub mod links;
+pub mod references;
+pub mod structure;
+
+const L_OFF: &str = "off";
+const L_WARNING: &str = "warning";
+const L_ERROR: &str = "error";
+
+/// All rules that can be configured in the `lint` section of the manifest
+pub const RULES: &[&str] = &[
+    R_UNDEFINED_CITATION,
+    R_UNUSED_BIB_ENTRY,
+    R_DUPLICATE_BIB_ENTRY,
+    R_UNDEFINED_GLOSSARY_REFERENCE,
+    R_UNUSED_GLOSSARY_ENTRY,
+    R_DUPLICATE_GLOSSARY_ENTRY,
+    R_SKIPPED_HEADING_LEVEL,
+    R_EMPTY_SECTION,
+    R_MISSING_ALT_TEXT,
+    R_DEAD_LOCAL_LINK,
+    R_DUPLICATE_ANCHOR,
+    R_UNKNOWN_PLACEHOLDER,
+    R_MISSING_FILE,
+    R_BROKEN_URL,
+];
+
+/// The severity of an issue found by the linter
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    Warning,
+    Error,
+}
+
+/// An issue found while linting a document
+#[derive(Clone, Debug, Serialize)]
+pub struct LintIssue {
+    pub rule: &'static str,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub positions: Vec<String>,
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} [{}]", self.message, self.rule)?;
+        for position in &self.positions {
+            writeln!(f, "\t--> {}", position)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LintIssue {
+    pub fn new(
+        rule: &'static str,
+        severity: LintSeverity,
+        message: String,
+        positions: Vec<String>,
+    ) -> Self {
+        Self {
+            rule,
+            severity,
+            message,
+            positions,
+        }
+    }
+
+    /// Returns if the issue is an error
+    pub fn is_error(&self) -> bool {
+        self.severity == LintSeverity::Error
+    }
+
+    /// Logs the issue as an error or warning
+    pub fn log(&self) {
+        if self.is_error() {
+            log::error!("{}", self);
+        } else {
+            log::warn!("{}", self);
+        }
+    }
+}
+
+/// Checks the processed document for issues.
+/// The severity of every rule can be changed in the `lint` section of the manifest
+/// with `off` disabling the rule
+pub fn lint_document(document: &Document) -> Vec<LintIssue> {
+    let mut issues = lint_references(document);
+    issues.append(&mut lint_structure(document));
+
+    apply_rule_levels(document, issues)
+}
+
+/// Checks that all links of the document point to existing anchors, files and
+/// with a url checker also to urls that can be reached
+pub fn lint_links(document: &Document, checker: Option<&UrlChecker>) -> Vec<LintIssue> {
+    apply_rule_levels(document, check_links(document, checker))
+}
+
+/// Assigns the configured severities to the issues and removes issues of disabled rules
+fn apply_rule_levels(document: &Document, issues: Vec<LintIssue>) -> Vec<LintIssue> {
+    let levels = get_rule_levels(&document.config.lock().lint);
+
+    issues
+        .into_iter()
+        .filter_map(|mut issue| match levels.get(issue.rule) {
+            Some(Some(severity)) => {
+                issue.severity = *severity;
+                Some(issue)
+            }
+            Some(None) => None,
+            None => Some(issue),
+        })
+        .collect()
+}
+
+/// Returns the configured severities of the rules with `None` for disabled rules
+fn get_rule_levels(config: &HashMap<String, String>) -> HashMap<String, Option<LintSeverity>> {
+    let mut levels = HashMap::new();
+
+    for (rule, level) in config {
+        if !RULES.contains(&rule.as_str()) {
+            log::warn!("Unknown lint rule '{}'", rule);
+            continue;
+        }
+        let severity = match level.to_lowercase().as_str() {
+            L_OFF => None,
+            L_WARNING => Some(LintSeverity::Warning),
+            L_ERROR => Some(LintSeverity::Error),
+            _ => {
+                log::warn!(
+                    "Invalid level '{}' for lint rule '{}'. Expected one of {}, {} or {}",
+                    level,
+                    rule,
+                    L_OFF,
+                    L_WARNING,
+                    L_ERROR
+                );
+                continue;
+            }
+        };
+        levels.insert(rule.clone(), severity);
+    }
+
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::parse_test_files;
+
+    #[test]
+    fn it_applies_the_configured_rule_levels() {
+        let document = parse_test_files(
+            "lint-levels",
+            &[
+                ("main.sd", "# Empty\n# Full\n\n!(image.png) [^missing]\n"),
+                (
+                    "Manifest.toml",
+                    "[lint]\nempty-section = \"off\"\nmissing-alt-text = \"Error\"\n\
+                    undefined-citation = \"warning\"\nunknown-rule = \"off\"\n",
+                ),
+            ],
+        );
+        let issues = lint_document(&document);
+        let get_severity = |rule: &str| {
+            issues
+                .iter()
+                .find(|issue| issue.rule == rule)
+                .map(|issue| issue.severity)
+        };
+
+        assert_eq!(get_severity(R_EMPTY_SECTION), None);
+        assert_eq!(get_severity(R_MISSING_ALT_TEXT), Some(LintSeverity::Error));
+        assert_eq!(
+            get_severity(R_UNDEFINED_CITATION),
+            Some(LintSeverity::Warning)
+        );
+    }
+
+    #[test]
+    fn it_ignores_invalid_rule_levels() {
+        let config = maplit::hashmap! {
+            R_EMPTY_SECTION.to_string() => "sometimes".to_string(),
+            "unknown-rule".to_string() => "off".to_string(),
+            R_BROKEN_URL.to_string() => "OFF".to_string(),
+        };
+        let levels = get_rule_levels(&config);
+
+        assert_eq!(levels.len(), 1);
+        assert_eq!(levels[R_BROKEN_URL], None);
+    }
+}
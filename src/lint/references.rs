@@ -0,0 +1,236 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::lint::{LintIssue, LintSeverity};
+use crate::references::citations::get_citation_list;
+use std::collections::{BTreeMap, HashSet};
+
+pub const R_UNDEFINED_CITATION: &str = "undefined-citation";
+pub const R_UNUSED_BIB_ENTRY: &str = "unused-bib-entry";
+pub const R_DUPLICATE_BIB_ENTRY: &str = "duplicate-bib-entry";
+pub const R_UNDEFINED_GLOSSARY_REFERENCE: &str = "undefined-glossary-reference";
+pub const R_UNUSED_GLOSSARY_ENTRY: &str = "unused-glossary-entry";
+pub const R_DUPLICATE_GLOSSARY_ENTRY: &str = "duplicate-glossary-entry";
+
+/// Checks the bibliography and glossary for undefined references,
+/// entries that are never referenced and keys that are defined multiple times
+pub fn lint_references(document: &Document) -> Vec<LintIssue> {
+    let mut issues = lint_bibliography(document);
+    issues.append(&mut lint_glossary(document));
+
+    issues
+}
+
+fn lint_bibliography(document: &Document) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut cited_keys = HashSet::new();
+    let mut undefined: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for citation in get_citation_list(&document.elements) {
+        for reference in &citation.read().unwrap().references {
+            cited_keys.insert(reference.key.clone());
+
+            if reference.entry_anchor.lock().entry.is_none() {
+                add_position(
+                    undefined.entry(reference.key.clone()).or_default(),
+                    &reference.position,
+                );
+            }
+        }
+    }
+    for (key, positions) in undefined {
+        issues.push(LintIssue::new(
+            R_UNDEFINED_CITATION,
+            LintSeverity::Error,
+            format!("The bibliography entry '{}' is not defined", key),
+            positions,
+        ));
+    }
+    let entry_positions = document.bib_entry_positions.lock();
+
+    for (key, positions) in entry_positions.get_entries() {
+        if positions.len() > 1 {
+            issues.push(LintIssue::new(
+                R_DUPLICATE_BIB_ENTRY,
+                LintSeverity::Error,
+                format!(
+                    "The bibliography entry '{}' is defined {} times",
+                    key,
+                    positions.len()
+                ),
+                positions.clone(),
+            ));
+        }
+        if !cited_keys.contains(key) {
+            issues.push(LintIssue::new(
+                R_UNUSED_BIB_ENTRY,
+                LintSeverity::Warning,
+                format!("The bibliography entry '{}' is never cited", key),
+                positions.clone(),
+            ));
+        }
+    }
+
+    issues
+}
+
+fn lint_glossary(document: &Document) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let glossary = document.glossary.lock();
+    let mut undefined: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for reference in glossary.get_undefined_references() {
+        let reference = reference.lock();
+        add_position(
+            undefined.entry(reference.short.clone()).or_default(),
+            &reference.position,
+        );
+    }
+    for (key, positions) in undefined {
+        issues.push(LintIssue::new(
+            R_UNDEFINED_GLOSSARY_REFERENCE,
+            LintSeverity::Error,
+            format!("The glossary entry '{}' is not defined", key),
+            positions,
+        ));
+    }
+    for (duplicate, entry) in glossary.get_duplicate_entries() {
+        issues.push(LintIssue::new(
+            R_DUPLICATE_GLOSSARY_ENTRY,
+            LintSeverity::Error,
+            format!(
+                "The glossary entry '{}' is defined multiple times",
+                duplicate.short
+            ),
+            vec![duplicate.get_position(), entry.lock().get_position()],
+        ));
+    }
+    for entry in glossary.get_unused_entries() {
+        let entry = entry.lock();
+        issues.push(LintIssue::new(
+            R_UNUSED_GLOSSARY_ENTRY,
+            LintSeverity::Warning,
+            format!("The glossary entry '{}' is never referenced", entry.short),
+            vec![entry.get_position()],
+        ));
+    }
+
+    issues
+}
+
+/// Adds the position to the list if it is known and not already contained
+fn add_position(positions: &mut Vec<String>, position: &Option<String>) {
+    if let Some(position) = position {
+        if !positions.contains(position) {
+            positions.push(position.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::parse_test_files;
+
+    /// Returns the issues of the given rule found in the document
+    fn get_issues(name: &str, files: &[(&str, &str)], rule: &str) -> Vec<LintIssue> {
+        lint_references(&parse_test_files(name, files))
+            .into_iter()
+            .filter(|issue| issue.rule == rule)
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_undefined_citations() {
+        let issues = get_issues(
+            "lint-undefined-citation",
+            &[("main.sd", "Text [^missing] and [^missing]\n")],
+            R_UNDEFINED_CITATION,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Error);
+        assert!(issues[0].message.contains("'missing'"));
+        assert_eq!(issues[0].positions.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_unused_bib_entries() {
+        let issues = get_issues(
+            "lint-unused-bib-entry",
+            &[(
+                "main.sd",
+                "Text [^a]\n\n[a]: https://a.com\n[b]: https://b.com\n",
+            )],
+            R_UNUSED_BIB_ENTRY,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Warning);
+        assert!(issues[0].message.contains("'b'"));
+    }
+
+    #[test]
+    fn it_reports_duplicate_bib_entries() {
+        let issues = get_issues(
+            "lint-duplicate-bib-entry",
+            &[(
+                "main.sd",
+                "Text [^a]\n\n[a]: https://a.com\n[a]: https://b.com\n",
+            )],
+            R_DUPLICATE_BIB_ENTRY,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Error);
+        assert_eq!(issues[0].positions.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_undefined_glossary_references() {
+        let issues = get_issues(
+            "lint-undefined-glossary-reference",
+            &[("main.sd", "Text ~~SDK\n")],
+            R_UNDEFINED_GLOSSARY_REFERENCE,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Error);
+        assert!(issues[0].message.contains("'SDK'"));
+    }
+
+    #[test]
+    fn it_reports_unused_glossary_entries() {
+        let issues = get_issues(
+            "lint-unused-glossary-entry",
+            &[
+                ("main.sd", "<[glossary.toml][type=glossary]\n\nText ~~API\n"),
+                (
+                    "glossary.toml",
+                    "[API]\nlong = \"Interface\"\ndescription = \"An interface\"\n\n\
+                    [SDK]\nlong = \"Kit\"\ndescription = \"A kit\"\n",
+                ),
+            ],
+            R_UNUSED_GLOSSARY_ENTRY,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Warning);
+        assert!(issues[0].message.contains("'SDK'"));
+    }
+
+    #[test]
+    fn it_reports_duplicate_glossary_entries() {
+        let issues = get_issues(
+            "lint-duplicate-glossary-entry",
+            &[(
+                "main.sd",
+                "~~API[long=\"Interface\", description=\"First\"]\n\n\
+                ~~API[long=\"Interface\", description=\"Second\"]\n",
+            )],
+            R_DUPLICATE_GLOSSARY_ENTRY,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, LintSeverity::Error);
+        assert_eq!(issues[0].positions.len(), 2);
+    }
+}
@@ -0,0 +1,339 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{
+    Block, Definition, Document, Element, Inline, Line, ListItem, Placeholder, Section, TextLine,
+    Url,
+};
+use crate::lint::links::{check_anchor_links, check_local_files, Link};
+use crate::lint::{LintIssue, LintSeverity};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+pub const R_SKIPPED_HEADING_LEVEL: &str = "skipped-heading-level";
+pub const R_EMPTY_SECTION: &str = "empty-section";
+pub const R_MISSING_ALT_TEXT: &str = "missing-alt-text";
+pub const R_DUPLICATE_ANCHOR: &str = "duplicate-anchor";
+pub const R_UNKNOWN_PLACEHOLDER: &str = "unknown-placeholder";
+
+/// Walks the document tree and collects the links
+/// and the anchors they can point to
+#[derive(Default)]
+pub(crate) struct StructureLinter {
+    issues: Vec<LintIssue>,
+    header_anchors: BTreeMap<String, Vec<String>>,
+    pub(crate) targets: HashSet<String>,
+    pub(crate) links: Vec<Link>,
+}
+
+/// Checks the structure of the document for skipped heading levels, empty sections,
+/// images without a description, links to anchors or local files that don't exist,
+/// headers with the same anchor and placeholders without a value
+pub fn lint_structure(document: &Document) -> Vec<LintIssue> {
+    let mut linter = StructureLinter::from_document(document);
+
+    for (anchor, positions) in &linter.header_anchors {
+        if positions.len() > 1 {
+            linter.issues.push(LintIssue::new(
+                R_DUPLICATE_ANCHOR,
+                LintSeverity::Error,
+                format!(
+                    "The anchor '{}' is used by {} headers",
+                    anchor,
+                    positions.len()
+                ),
+                positions.clone(),
+            ));
+        }
+    }
+    linter
+        .issues
+        .append(&mut check_anchor_links(&linter.links, &linter.targets));
+    linter.issues.append(&mut check_local_files(&linter.links));
+    for placeholder in &document.placeholders {
+        let placeholder = placeholder.read().unwrap();
+        if placeholder.value.is_none() {
+            linter.issues.push(LintIssue::new(
+                R_UNKNOWN_PLACEHOLDER,
+                LintSeverity::Warning,
+                format!("Unknown placeholder [[{}]]", placeholder.name),
+                placeholder.position.iter().cloned().collect(),
+            ));
+        }
+    }
+
+    linter.issues
+}
+
+impl StructureLinter {
+    /// Walks the given document
+    pub(crate) fn from_document(document: &Document) -> Self {
+        let mut linter = Self::default();
+        linter.lint_blocks(&document.elements);
+
+        linter
+    }
+
+    fn lint_blocks(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            self.lint_block(block);
+        }
+    }
+
+    fn lint_block(&mut self, block: &Block) {
+        match block {
+            Block::Section(sec) => self.lint_section(sec),
+            Block::Paragraph(par) => par.elements.iter().for_each(|l| self.lint_line(l)),
+            Block::List(list) => list.items.iter().for_each(|i| self.lint_list_item(i)),
+            Block::DefinitionList(list) => list
+                .groups
+                .iter()
+                .flat_map(|g| g.definitions.iter())
+                .for_each(|d| self.lint_definition(d)),
+            Block::Table(table) => std::iter::once(&table.header)
+                .chain(table.rows.iter())
+                .flat_map(|row| row.cells.iter())
+                .for_each(|cell| self.lint_line(&cell.text)),
+            Block::Quote(q) => q.text.iter().for_each(|t| self.lint_text_line(t)),
+            Block::Import(imp) => {
+                if let Some(doc) = &imp.anchor.read().unwrap().document {
+                    self.lint_blocks(&doc.elements);
+                }
+            }
+            Block::Placeholder(pholder) => self.lint_placeholder(pholder),
+            _ => {}
+        }
+    }
+
+    fn lint_section(&mut self, sec: &Section) {
+        let header = &sec.header;
+        let position: Vec<String> = header.position.iter().cloned().collect();
+        self.header_anchors
+            .entry(header.anchor.clone())
+            .or_default()
+            .append(&mut position.clone());
+        self.targets.insert(header.anchor.clone());
+
+        if sec.elements.iter().all(|b| matches!(b, Block::Null)) {
+            self.issues.push(LintIssue::new(
+                R_EMPTY_SECTION,
+                LintSeverity::Warning,
+                format!("The section '{}' is empty", get_plain_text(&header.line)),
+                position.clone(),
+            ));
+        }
+        for block in &sec.elements {
+            if let Block::Section(sub) = block {
+                if sub.header.size > header.size + 1 {
+                    self.issues.push(LintIssue::new(
+                        R_SKIPPED_HEADING_LEVEL,
+                        LintSeverity::Warning,
+                        format!(
+                            "The heading level jumps from {} to {}",
+                            header.size, sub.header.size
+                        ),
+                        sub.header.position.iter().cloned().collect(),
+                    ));
+                }
+            }
+        }
+        self.lint_line(&header.line);
+        self.lint_blocks(&sec.elements);
+    }
+
+    fn lint_list_item(&mut self, item: &ListItem) {
+        self.lint_line(&item.text);
+        item.children.iter().for_each(|c| self.lint_list_item(c));
+    }
+
+    fn lint_definition(&mut self, definition: &Definition) {
+        self.targets.insert(definition.key.clone());
+        self.lint_text_line(&definition.term);
+        self.lint_text_line(&definition.description);
+        definition
+            .children
+            .iter()
+            .for_each(|c| self.lint_definition(c));
+    }
+
+    fn lint_line(&mut self, line: &Line) {
+        match line {
+            Line::Text(text) => self.lint_text_line(text),
+            Line::Centered(center) => self.lint_text_line(&center.line),
+            Line::Anchor(anchor) => {
+                self.targets.insert(anchor.key.clone());
+                self.lint_line(&anchor.inner);
+            }
+            Line::RefLink(link) => {
+                self.links
+                    .push(Link::new(format!("#{}", link.reference), None));
+                self.lint_text_line(&link.description);
+            }
+            _ => {}
+        }
+    }
+
+    fn lint_text_line(&mut self, line: &TextLine) {
+        self.lint_inlines(&line.subtext);
+    }
+
+    fn lint_inlines(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            self.lint_inline(inline);
+        }
+    }
+
+    fn lint_inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Url(url) => self.lint_url(url),
+            Inline::Image(image) => {
+                self.links.push(Link::from_url(&image.url));
+                let has_description = image
+                    .url
+                    .description
+                    .as_ref()
+                    .map(|d| !get_inline_text(d).trim().is_empty())
+                    .unwrap_or(false);
+                if !has_description {
+                    self.issues.push(LintIssue::new(
+                        R_MISSING_ALT_TEXT,
+                        LintSeverity::Warning,
+                        format!("The image '{}' has no description", image.url.url),
+                        image.url.position.iter().cloned().collect(),
+                    ));
+                }
+            }
+            Inline::Anchor(anchor) => {
+                self.targets.insert(anchor.key.clone());
+                self.lint_line(&anchor.inner);
+            }
+            Inline::IndexEntry(entry) => {
+                if let Some(id) = &entry.read().unwrap().id {
+                    self.targets.insert(id.clone());
+                }
+            }
+            Inline::Placeholder(pholder) => self.lint_placeholder(pholder),
+            Inline::Bold(bo) => self.lint_inlines(&bo.value),
+            Inline::Italic(it) => self.lint_inlines(&it.value),
+            Inline::Underlined(un) => self.lint_inlines(&un.value),
+            Inline::Striked(st) => self.lint_inlines(&st.value),
+            Inline::Superscript(sup) => self.lint_inlines(&sup.value),
+            Inline::Colored(col) => self.lint_inline(&col.value),
+            _ => {}
+        }
+    }
+
+    fn lint_url(&mut self, url: &Url) {
+        self.links.push(Link::from_url(url));
+        if let Some(description) = &url.description {
+            self.lint_inlines(description);
+        }
+    }
+
+    /// Lints the value of the placeholder as it is rendered in its place
+    fn lint_placeholder(&mut self, pholder: &Arc<RwLock<Placeholder>>) {
+        match &pholder.read().unwrap().value {
+            Some(Element::Block(block)) => self.lint_block(block),
+            Some(Element::Line(line)) => self.lint_line(line),
+            Some(Element::Inline(inline)) => self.lint_inline(inline),
+            None => {}
+        }
+    }
+}
+
+fn get_plain_text(line: &Line) -> String {
+    get_inline_text(&line.as_raw_text().subtext)
+}
+
+fn get_inline_text(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|i| i.as_plain_text().value)
+        .collect::<String>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::links::R_DEAD_LOCAL_LINK;
+    use crate::utils::testing::parse_test_files;
+
+    /// Returns the issues of the given rule found in the document text
+    fn get_issues(name: &str, text: &str, rule: &str) -> Vec<LintIssue> {
+        lint_structure(&parse_test_files(name, &[("main.sd", text)]))
+            .into_iter()
+            .filter(|issue| issue.rule == rule)
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_skipped_heading_levels() {
+        let issues = get_issues(
+            "lint-skipped-heading-level",
+            "# A\n\n### C\n\nText\n\n## B\n\nText\n",
+            R_SKIPPED_HEADING_LEVEL,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "The heading level jumps from 1 to 3");
+    }
+
+    #[test]
+    fn it_reports_empty_sections() {
+        let issues = get_issues(
+            "lint-empty-section",
+            "# Empty\n# Full\n\nText\n",
+            R_EMPTY_SECTION,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "The section 'Empty' is empty");
+    }
+
+    #[test]
+    fn it_reports_images_without_description() {
+        let issues = get_issues(
+            "lint-missing-alt-text",
+            "!(plain.png) ![A description](described.png) ![ ](blank.png)\n",
+            R_MISSING_ALT_TEXT,
+        );
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].message.contains("plain.png"));
+        assert!(issues[1].message.contains("blank.png"));
+    }
+
+    #[test]
+    fn it_reports_dead_local_links() {
+        let issues = get_issues(
+            "lint-dead-local-link",
+            "# Title\n\n[title](#title) [nowhere](#nowhere)\n",
+            R_DEAD_LOCAL_LINK,
+        );
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("#nowhere"));
+    }
+
+    #[test]
+    fn it_reports_duplicate_anchors() {
+        let issues = get_issues(
+            "lint-duplicate-anchor",
+            "# A {#same}\n\nText\n\n# B {#same}\n\nText\n\n# C\n\nText\n\n# C\n\nText\n",
+            R_DUPLICATE_ANCHOR,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "The anchor 'same' is used by 2 headers");
+        assert_eq!(issues[0].positions.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_unknown_placeholders() {
+        let issues = get_issues(
+            "lint-unknown-placeholder",
+            "[[date]] [[unknown]]\n",
+            R_UNKNOWN_PLACEHOLDER,
+        );
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "Unknown placeholder [[unknown]]");
+    }
+}
@@ -11,12 +11,16 @@ use notify::{watcher, RecursiveMode, Watcher};
 use snekdown::elements::Document;
 use snekdown::format::html::html_writer::HTMLWriter;
 use snekdown::format::html::to_html::ToHtml;
+use snekdown::lint::links::UrlChecker;
+use snekdown::lint::{lint_document, lint_links, LintIssue};
 use snekdown::parser::ParserOptions;
-use snekdown::settings::Settings;
+use snekdown::settings::validation::validate_config_file;
+use snekdown::settings::{Settings, SettingsOverrides};
 use snekdown::utils::caching::CacheStorage;
 use snekdown::Parser;
 use std::fs::{File, OpenOptions};
 use std::io::{stdout, BufWriter, Write};
+use std::iter;
 use std::path::PathBuf;
 use std::process::exit;
 use std::sync::mpsc::channel;
@@ -43,6 +47,81 @@ enum SubCommand {
 
     /// Clears the cache directory
     ClearCache,
+
+    /// Validates the manifest and additional config files
+    CheckConfig(CheckConfigOptions),
+
+    /// Parses the document and reports issues like undefined references or dead links
+    Lint(LintOptions),
+
+    /// Checks that links point to existing anchors, files and urls
+    CheckLinks(CheckLinksOptions),
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct CheckLinksOptions {
+    /// Also checks if remote urls can be reached.
+    /// Successful checks are cached for 24 hours
+    #[structopt(long)]
+    online: bool,
+
+    /// Checks remote urls again even if they were reached in the last 24 hours
+    #[structopt(long)]
+    no_cache: bool,
+
+    #[structopt(flatten)]
+    lint_options: LintOptions,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct LintOptions {
+    /// Path to the input file
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+
+    /// the output format of the issues (text or json)
+    #[structopt(short, long, default_value = "text")]
+    format: String,
+
+    /// Only use cached versions of remote imports
+    #[structopt(long)]
+    offline: bool,
+
+    /// Defines a value for conditional blocks in the form key=value
+    #[structopt(short = "D", long = "define", parse(try_from_str = parse_define))]
+    defines: Vec<(String, String)>,
+
+    /// Overrides a setting of the manifest in the form key=value.
+    /// Settings can also be overridden with environment variables like SNEKDOWN_STYLE__THEME
+    #[structopt(long = "set", parse(try_from_str = parse_setting))]
+    settings: Vec<(String, String)>,
+
+    /// Additional config files that are merged on top of the manifest
+    #[structopt(long = "config", parse(from_os_str))]
+    config_files: Vec<PathBuf>,
+}
+
+#[derive(StructOpt, Debug, Clone)]
+#[structopt()]
+struct CheckConfigOptions {
+    /// Path to the manifest
+    #[structopt(long, default_value = "Manifest.toml", parse(from_os_str))]
+    manifest: PathBuf,
+
+    /// Additional config files that are merged on top of the manifest
+    #[structopt(long = "config", parse(from_os_str))]
+    config_files: Vec<PathBuf>,
+
+    /// Overrides a setting of the manifest in the form key=value.
+    /// Settings can also be overridden with environment variables like SNEKDOWN_STYLE__THEME
+    #[structopt(long = "set", parse(try_from_str = parse_setting))]
+    settings: Vec<(String, String)>,
+
+    /// Prints the effective configuration as toml
+    #[structopt(long)]
+    print: bool,
 }
 
 #[derive(StructOpt, Debug, Clone)]
@@ -63,6 +142,32 @@ struct RenderOptions {
     /// the output format
     #[structopt(short, long, default_value = "html")]
     format: String,
+
+    /// Only use cached versions of remote imports
+    #[structopt(long)]
+    offline: bool,
+
+    /// Defines a value for conditional blocks in the form key=value
+    #[structopt(short = "D", long = "define", parse(try_from_str = parse_define))]
+    defines: Vec<(String, String)>,
+
+    /// Overrides a setting of the manifest in the form key=value.
+    /// Settings can also be overridden with environment variables like SNEKDOWN_STYLE__THEME
+    #[structopt(long = "set", parse(try_from_str = parse_setting))]
+    settings: Vec<(String, String)>,
+
+    /// Additional config files that are merged on top of the manifest
+    #[structopt(long = "config", parse(from_os_str))]
+    config_files: Vec<PathBuf>,
+
+    /// Renders identical output for identical inputs
+    /// using the timestamp from SOURCE_DATE_EPOCH or the unix epoch
+    #[structopt(long)]
+    reproducible: bool,
+
+    /// Lints the document before rendering and aborts if any errors are found
+    #[structopt(long)]
+    strict: bool,
 }
 
 #[derive(StructOpt, Debug, Clone)]
@@ -101,7 +206,10 @@ fn main() {
 
     match &opt.sub_command {
         SubCommand::Render(opt) => {
-            let _ = render(&opt);
+            let (_, success) = render(&opt);
+            if !success {
+                exit(1)
+            }
         }
         SubCommand::Watch(opt) => watch(&opt),
         SubCommand::ClearCache => {
@@ -109,6 +217,9 @@ fn main() {
             cache.clear().expect("Failed to clear cache");
         }
         SubCommand::Init => init(),
+        SubCommand::CheckConfig(opt) => check_config(&opt),
+        SubCommand::Lint(opt) => lint(&opt),
+        SubCommand::CheckLinks(opt) => check_links(&opt),
     };
 }
 
@@ -151,40 +262,229 @@ fn init() {
     }
 }
 
+/// Validates the config files and optionally prints the merged configuration
+fn check_config(opt: &CheckConfigOptions) {
+    let mut is_valid = true;
+
+    for path in iter::once(&opt.manifest).chain(opt.config_files.iter()) {
+        if !path.exists() {
+            log::error!("The config file {:?} could not be found", path);
+            is_valid = false;
+            continue;
+        }
+        for issue in validate_config_file(path) {
+            issue.log();
+            is_valid &= !issue.is_error();
+        }
+    }
+    if !is_valid {
+        exit(1)
+    }
+    let mut settings = Settings::default();
+    let overrides = SettingsOverrides {
+        files: opt.config_files.clone(),
+        values: opt.settings.clone(),
+    };
+    if let Err(e) = settings
+        .merge(opt.manifest.clone())
+        .and_then(|_| settings.apply_overrides(&overrides))
+    {
+        log::error!("{}", e);
+        exit(1)
+    }
+    log::info!("The configuration is valid");
+
+    if opt.print {
+        println!("{}", toml::to_string_pretty(&settings).unwrap());
+    }
+}
+
 /// Watches a file with all of its imports and renders on change
 fn watch(opt: &WatchOptions) {
-    let parser = render(&opt.render_options);
+    let (parser, _) = render(&opt.render_options);
     let (tx, rx) = channel();
     let mut watcher = watcher(tx, Duration::from_millis(opt.debounce)).unwrap();
 
-    for path in parser.get_paths() {
+    for path in parser.get_paths().into_iter().filter(|p| p.exists()) {
         watcher.watch(path, RecursiveMode::NonRecursive).unwrap();
     }
     while let Ok(_) = rx.recv() {
         println!("---");
-        let parser = render(&opt.render_options);
-        for path in parser.get_paths() {
+        let (parser, _) = render(&opt.render_options);
+        for path in parser.get_paths().into_iter().filter(|p| p.exists()) {
             watcher.watch(path, RecursiveMode::NonRecursive).unwrap();
         }
     }
 }
 
-/// Renders the document to the output path
-fn render(opt: &RenderOptions) -> Parser {
-    if !opt.input.exists() {
+/// Parses a define argument in the form key=value.
+/// A key without a value is defined as true
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    let mut parts = define.splitn(2, '=');
+    let key = parts.next().unwrap_or_default().trim();
+
+    if key.is_empty() {
+        return Err(format!("Invalid define \"{}\"", define));
+    }
+    let value = parts.next().unwrap_or("true").trim();
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parses a setting override in the form key=value
+fn parse_setting(setting: &str) -> Result<(String, String), String> {
+    let mut parts = setting.splitn(2, '=');
+    let key = parts.next().unwrap_or_default().trim();
+    let value = parts
+        .next()
+        .ok_or_else(|| format!("Missing value for setting \"{}\"", setting))?;
+
+    if key.is_empty() {
+        return Err(format!("Invalid setting \"{}\"", setting));
+    }
+
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+/// Lints the document and exits with an error if any errors were found
+fn lint(opt: &LintOptions) {
+    let document = parse_lint_input(opt);
+    report_lint_issues(opt, &lint_document(&document));
+}
+
+/// Checks the links of the document and exits with an error if any links are broken
+fn check_links(opt: &CheckLinksOptions) {
+    let document = parse_lint_input(&opt.lint_options);
+    let checker = if opt.online {
+        let cache = if opt.no_cache {
+            None
+        } else {
+            Some(CacheStorage::new())
+        };
+        match UrlChecker::with_timeout(cache) {
+            Ok(checker) => Some(checker),
+            Err(e) => {
+                log::error!("Failed to create http client: {}", e);
+                exit(1)
+            }
+        }
+    } else {
+        None
+    };
+    report_lint_issues(&opt.lint_options, &lint_links(&document, checker.as_ref()));
+}
+
+/// Parses the input document of the linter
+fn parse_lint_input(opt: &LintOptions) -> Document {
+    check_input(&opt.input);
+    let options = get_parser_options(
+        &opt.input,
+        opt.offline,
+        &opt.defines,
+        &opt.config_files,
+        &opt.settings,
+    );
+
+    Parser::with_defaults(options).parse()
+}
+
+/// Outputs the issues in the requested format and exits if any errors were found
+fn report_lint_issues(opt: &LintOptions, issues: &[LintIssue]) {
+    match opt.format.as_str() {
+        "text" => log_lint_issues(issues),
+        "json" => println!("{}", serde_json::to_string_pretty(&issues).unwrap()),
+        _ => {
+            log::error!("Unknown format {}", opt.format);
+            exit(1)
+        }
+    }
+
+    if issues.iter().any(|i| i.is_error()) {
+        exit(1)
+    }
+}
+
+/// Logs the issues and a summary of them
+fn log_lint_issues(issues: &[LintIssue]) {
+    for issue in issues {
+        issue.log();
+    }
+    let errors = issues.iter().filter(|i| i.is_error()).count();
+    log::info!(
+        "Found {} errors and {} warnings",
+        errors,
+        issues.len() - errors
+    );
+}
+
+/// Exits if the input file does not exist
+fn check_input(input: &PathBuf) {
+    if !input.exists() {
         log::error!(
             "The input file {} could not be found",
-            opt.input.to_str().unwrap()
+            input.to_str().unwrap()
         );
 
         exit(1)
     }
+}
+
+/// Returns the options for parsing the input file
+fn get_parser_options(
+    input: &PathBuf,
+    offline: bool,
+    defines: &[(String, String)],
+    config_files: &[PathBuf],
+    settings: &[(String, String)],
+) -> ParserOptions {
+    let mut options = ParserOptions::default()
+        .add_path(input.clone())
+        .set_offline(offline);
+    for (key, value) in defines.iter().cloned() {
+        options = options.add_define(key, value);
+    }
+    for path in config_files.iter().cloned() {
+        options = options.add_config_file(path);
+    }
+    for (key, value) in settings.iter().cloned() {
+        options = options.add_setting_override(key, value);
+    }
+
+    options
+}
 
+/// Renders the document to the output path.
+/// Returns the parser and if the document was rendered
+fn render(opt: &RenderOptions) -> (Parser, bool) {
+    check_input(&opt.input);
     let start = Instant::now();
 
-    let mut parser = Parser::with_defaults(ParserOptions::default().add_path(opt.input.clone()));
+    let mut settings = opt.settings.clone();
+    if opt.reproducible {
+        settings.insert(0, ("features.reproducible".to_string(), "true".to_string()));
+    }
+    let options = get_parser_options(
+        &opt.input,
+        opt.offline,
+        &opt.defines,
+        &opt.config_files,
+        &settings,
+    );
+    let mut parser = Parser::with_defaults(options);
     let document = parser.parse();
 
+    if opt.strict {
+        let issues = lint_document(&document);
+        if !issues.is_empty() {
+            log_lint_issues(&issues);
+        }
+
+        if issues.iter().any(LintIssue::is_error) {
+            log::error!("The document was not rendered because of lint errors");
+
+            return (parser, false);
+        }
+    }
     log::info!("Parsing + Processing took: {:?}", start.elapsed());
     let start_render = Instant::now();
 
@@ -209,7 +509,7 @@ fn render(opt: &RenderOptions) -> Parser {
     log::info!("Rendering took: {:?}", start_render.elapsed());
     log::info!("Total: {:?}", start.elapsed());
 
-    parser
+    (parser, true)
 }
 
 #[cfg(not(feature = "pdf"))]
@@ -243,3 +543,48 @@ fn render_pdf<W: Write + 'static>(document: Document, mut writer: W) {
     writer.write_all(&result).unwrap();
     writer.flush().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Renders the given text with `--strict` and returns if it was rendered
+    /// together with the written output
+    fn render_strict(name: &str, text: &str) -> (bool, Option<String>) {
+        let dir = std::env::temp_dir().join(format!("snekdown-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("main.sd");
+        let output = dir.join("main.html");
+        fs::write(&input, text).unwrap();
+
+        let opt = RenderOptions::from_iter(&[
+            "render".as_ref(),
+            input.as_os_str(),
+            output.as_os_str(),
+            "--strict".as_ref(),
+        ]);
+        let (_, rendered) = render(&opt);
+        let html = fs::read_to_string(&output).ok();
+        let _ = fs::remove_dir_all(&dir);
+
+        (rendered, html)
+    }
+
+    #[test]
+    fn it_renders_strict_documents_with_warnings() {
+        let (rendered, html) = render_strict("strict-warnings", "# Empty\n# Title\n\nText\n");
+
+        assert!(rendered);
+        assert!(html.unwrap().contains("Text"));
+    }
+
+    #[test]
+    fn it_does_not_render_strict_documents_with_errors() {
+        let (rendered, html) = render_strict("strict-errors", "# Title\n\nText [^missing]\n");
+
+        assert!(!rendered);
+        assert!(html.is_none());
+    }
+}
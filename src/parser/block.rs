@@ -25,6 +25,9 @@ pub(crate) trait ParseBlock {
     fn parse_list(&mut self) -> ParseResult<List>;
     fn parse_table(&mut self) -> ParseResult<Table>;
     fn parse_import(&mut self) -> ParseResult<Option<Import>>;
+    fn parse_conditional(&mut self) -> ParseResult<()>;
+    fn skip_conditional_branch(&mut self, stop_at_else: bool) -> ParseResult<bool>;
+    fn check_conditional_marker(&mut self) -> bool;
 }
 
 impl ParseBlock for Parser {
@@ -37,6 +40,10 @@ impl ParseBlock for Parser {
                 self.section_return = None;
             }
         }
+        if self.parse_conditional().is_ok() {
+            log::trace!("Block::Null (Conditional)");
+            return Ok(Block::Null);
+        }
         let token = if let Ok(section) = self.parse_section() {
             log::trace!("Block::Section");
             Block::Section(section)
@@ -104,8 +111,10 @@ impl ParseBlock for Parser {
                 return Err(self.ctm.rewind_with_error(start_index).into());
             }
             self.ctm.seek_any(&INLINE_WHITESPACE)?;
+            let header_index = self.ctm.get_index();
             let mut header = self.parse_header()?;
-            header.size = size;
+            header.size = self.get_shifted_size(size);
+            header.position = Some(self.get_position_string_for_index(header_index));
             self.section_nesting = size;
             self.sections.push(size);
             self.section_anchors.push(header.anchor.clone());
@@ -216,6 +225,7 @@ impl ParseBlock for Parser {
 
             if self.ctm.check_any_sequence(&BLOCK_SPECIAL_CHARS)
                 || self.ctm.check_any(&self.block_break_at)
+                || self.check_conditional_marker()
             {
                 self.ctm.rewind(start_index);
                 break;
@@ -343,10 +353,6 @@ impl ParseBlock for Parser {
         }
         // parser success
 
-        if self.section_nesting > 0 {
-            self.section_return = Some(0);
-            return Err(self.ctm.rewind_with_error(start_index).into());
-        }
         let metadata = self
             .parse_inline_metadata()
             .ok()
@@ -361,4 +367,93 @@ impl ParseBlock for Parser {
             _ => Err(self.ctm.err().into()),
         }
     }
+
+    /// Parses the markers of a conditional block and skips
+    /// the content of branches whose condition isn't met
+    fn parse_conditional(&mut self) -> ParseResult<()> {
+        let start_index = self.ctm.get_index();
+        self.ctm.seek_whitespace();
+
+        if self.ctm.check_sequence(SQ_COND_IF) {
+            self.ctm.seek_one()?;
+            let condition = self.parse_inline_metadata().ok();
+            let is_met = if let Some(condition) = &condition {
+                self.evaluate_condition(condition)
+            } else {
+                log::warn!(
+                    "Conditional block without condition\n\t--> {}\n",
+                    self.get_position_string()
+                );
+                false
+            };
+
+            if is_met || self.skip_conditional_branch(true)? {
+                self.conditional_depth += 1;
+            }
+            Ok(())
+        } else if self.conditional_depth > 0 && self.ctm.check_sequence(SQ_COND_ELSE) {
+            self.ctm.seek_one()?;
+            self.skip_conditional_branch(false)?;
+            self.conditional_depth -= 1;
+
+            Ok(())
+        } else if self.conditional_depth > 0 && self.ctm.check_sequence(SQ_COND_END) {
+            self.ctm.seek_one()?;
+            self.conditional_depth -= 1;
+
+            Ok(())
+        } else {
+            Err(self.ctm.rewind_with_error(start_index).into())
+        }
+    }
+
+    /// Skips the content of a conditional branch until the matching [[endif]]
+    /// or optionally [[else]] marker. Returns true if it stopped at an [[else]]
+    fn skip_conditional_branch(&mut self, stop_at_else: bool) -> ParseResult<bool> {
+        let start_index = self.ctm.get_index();
+        let mut depth = 0;
+
+        loop {
+            let mut reached_line_start = false;
+
+            while !reached_line_start {
+                reached_line_start = self.ctm.check_char(&LB);
+
+                if self.ctm.seek_one().is_err() {
+                    log::error!(
+                        "Unterminated conditional block\n\t--> {}\n",
+                        self.get_position_string_for_index(start_index)
+                    );
+                    return Err(self.ctm.err().into());
+                }
+            }
+            self.ctm.seek_any(&INLINE_WHITESPACE)?;
+
+            if self.ctm.check_sequence(SQ_COND_IF) {
+                depth += 1;
+            } else if self.ctm.check_sequence(SQ_COND_END) {
+                if depth == 0 {
+                    self.ctm.seek_one()?;
+                    return Ok(false);
+                }
+                depth -= 1;
+            } else if depth == 0 && stop_at_else && self.ctm.check_sequence(SQ_COND_ELSE) {
+                self.ctm.seek_one()?;
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Checks if the line at the current position starts with a conditional marker
+    /// so that paragraphs end before the marker instead of parsing it as text
+    fn check_conditional_marker(&mut self) -> bool {
+        let start_index = self.ctm.get_index();
+        let _ = self.ctm.seek_any(&INLINE_WHITESPACE);
+        let is_marker = self
+            .ctm
+            .check_any_sequence(&[SQ_COND_IF, SQ_COND_ELSE, SQ_COND_END]);
+        self.ctm.rewind(start_index);
+
+        is_marker
+    }
 }
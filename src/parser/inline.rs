@@ -6,17 +6,20 @@
 
 use super::{ParseError, ParseResult};
 use crate::elements::tokens::*;
-use crate::elements::BibReference;
 use crate::elements::*;
 use crate::parser::block::ParseBlock;
 use crate::references::glossary::GlossaryDisplay;
+use crate::references::glossary::GlossaryEntry;
 use crate::references::glossary::GlossaryReference;
+use crate::references::glossary::GLOSSARY_ENTRY_KEYS;
+use crate::references::index::{split_term, IndexCrossReference, IndexEntry};
 use crate::references::templates::{GetTemplateVariables, Template, TemplateVariable};
-use crate::utils::parsing::remove_single_backlslash;
+use crate::utils::localisation::L_CITATION_NEEDED;
+use crate::utils::parsing::{get_local_path, remove_single_backlslash};
 use crate::Parser;
 use bibliographix::references::bib_reference::BibRef;
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 
@@ -35,7 +38,7 @@ pub(crate) trait ParseInline {
     fn parse_superscript(&mut self) -> ParseResult<SuperscriptText>;
     fn parse_emoji(&mut self) -> ParseResult<Emoji>;
     fn parse_colored(&mut self) -> ParseResult<Colored>;
-    fn parse_bibref(&mut self) -> ParseResult<Arc<RwLock<BibReference>>>;
+    fn parse_bibref(&mut self) -> ParseResult<Arc<RwLock<BibCitation>>>;
     fn parse_template_variable(&mut self) -> ParseResult<Arc<RwLock<TemplateVariable>>>;
     fn parse_glossary_reference(&mut self) -> ParseResult<Arc<Mutex<GlossaryReference>>>;
     fn parse_plain(&mut self) -> ParseResult<PlainText>;
@@ -46,6 +49,7 @@ pub(crate) trait ParseInline {
     fn parse_character_code(&mut self) -> ParseResult<CharacterCode>;
     fn parse_arrow(&mut self) -> ParseResult<Arrow>;
     fn parse_anchor(&mut self) -> ParseResult<Anchor>;
+    fn parse_index_entry(&mut self) -> ParseResult<Arc<RwLock<IndexEntry>>>;
 }
 
 impl ParseInline for Parser {
@@ -122,9 +126,9 @@ impl ParseInline for Parser {
         } else if let Ok(colored) = self.parse_colored() {
             log::trace!("Inline::Colored");
             Ok(Inline::Colored(colored))
-        } else if let Ok(bibref) = self.parse_bibref() {
-            log::trace!("Inline::BibReference {:?}", bibref);
-            Ok(Inline::BibReference(bibref))
+        } else if let Ok(citation) = self.parse_bibref() {
+            log::trace!("Inline::BibCitation {:?}", citation);
+            Ok(Inline::BibCitation(citation))
         } else if let Ok(math) = self.parse_math() {
             log::trace!("Inline::Math");
             Ok(Inline::Math(math))
@@ -137,6 +141,9 @@ impl ParseInline for Parser {
         } else if let Ok(anchor) = self.parse_anchor() {
             log::trace!("Inline::Anchor {:?}", anchor);
             Ok(Inline::Anchor(anchor))
+        } else if let Ok(entry) = self.parse_index_entry() {
+            log::trace!("Inline::IndexEntry {:?}", entry);
+            Ok(Inline::IndexEntry(entry))
         } else {
             let plain = self.parse_plain()?;
             log::trace!("Inline::Plain {}", plain.value);
@@ -208,15 +215,20 @@ impl ParseInline for Parser {
 
         self.ctm.seek_one()?;
         let url_path = self.transform_path(url.clone());
+        let local_path = get_local_path(&url).map(|p| self.transform_path(p.to_string()));
         if url_path.exists() {
             url = url_path.to_str().unwrap().to_string();
         }
 
-        if description.len() > 0 {
-            Ok(Url::new(Some(description), url))
+        let mut url = if !description.is_empty() {
+            Url::new(Some(description), url)
         } else {
-            Ok(Url::new(None, url))
-        }
+            Url::new(None, url)
+        };
+        url.position = Some(self.get_position_string_for_index(start_index));
+        url.local_path = local_path;
+
+        Ok(url)
     }
 
     /// parses a markdown checkbox
@@ -368,37 +380,60 @@ impl ParseInline for Parser {
         })
     }
 
-    fn parse_bibref(&mut self) -> ParseResult<Arc<RwLock<BibReference>>> {
+    /// parses a citation `[^prefix key, locator; key]` with an optional `@`
+    /// before the first key for textual citations
+    fn parse_bibref(&mut self) -> ParseResult<Arc<RwLock<BibCitation>>> {
         let start_index = self.ctm.get_index();
         self.ctm
             .assert_sequence(&SQ_BIBREF_START, Some(start_index))?;
         self.ctm.seek_one()?;
-        let key =
+        let content =
             self.ctm
-                .get_string_until_any_or_rewind(&[BIBREF_CLOSE], &[SPACE, LB], start_index)?;
-        self.ctm.seek_one()?;
-        let bib_ref = BibRef::new(key.clone());
-        let ref_entry = Arc::new(RwLock::new(BibReference::new(
-            key,
-            Some(
-                self.options
-                    .document
-                    .config
-                    .lock()
-                    .style
-                    .bib_ref_display
-                    .clone(),
-            ),
-            bib_ref.anchor(),
-        )));
-        self.options
-            .document
-            .bibliography
-            .root_ref_anchor()
-            .lock()
-            .insert(bib_ref);
+                .get_string_until_any_or_rewind(&[BIBREF_CLOSE], &[LB], start_index)?;
+        self.ctm.seek_one()?;
+        let (display, missing_text, style) = {
+            let config = self.options.document.config.lock();
+            (
+                config.style.get_bib_ref_display(),
+                config.get_localisation().get(L_CITATION_NEEDED),
+                config.style.citation_style.clone(),
+            )
+        };
+        let position = self.get_position_string_for_index(start_index);
+        let mut references = Vec::new();
+        let mut textual = false;
+
+        for (index, part) in content.split(BIBREF_SEPARATOR).enumerate() {
+            let (head, locator) = match part.split_once(BIBREF_LOCATOR) {
+                Some((head, locator)) => (head, Some(locator.trim().to_string())),
+                None => (part, None),
+            };
+            let mut words = head.split_whitespace().collect::<Vec<&str>>();
+            let mut key = words.pop().unwrap_or_default();
+
+            if let Some(stripped) = key.strip_prefix(BIBREF_TEXTUAL) {
+                key = stripped;
+                textual = true;
+            }
+            if key.is_empty() {
+                return Err(self.ctm.rewind_with_error(start_index).into());
+            }
+            let bib_ref = BibRef::new(key.to_string());
+            let mut reference =
+                BibReference::new(key.to_string(), Some(display.clone()), bib_ref.anchor());
+            reference.missing_text = missing_text.clone();
+            reference.locator = locator.filter(|l| !l.is_empty());
+            reference.position = Some(position.clone());
+            if !words.is_empty() {
+                reference.prefix = Some(words.join(" "));
+            }
+            references.push(reference);
+            self.pending_bib_refs.insert((start_index, index), bib_ref);
+        }
 
-        Ok(ref_entry)
+        Ok(Arc::new(RwLock::new(BibCitation::new(
+            references, style, textual,
+        ))))
     }
 
     /// parses a template variable {prefix{name}suffix}
@@ -426,7 +461,14 @@ impl ParseInline for Parser {
         })))
     }
 
-    /// Parses a reference to a glossary entry
+    /// Parses a reference to a glossary entry.
+    /// Plurals are referenced by appending an `s` to the key (`~~APIs`) and keys
+    /// are matched case insensitive. The display can be forced with a modifier
+    /// (`~~API:long`, `~~API:short`, `~~API:full` or `~~API:first`) that
+    /// capitalizes the text when it starts with an uppercase letter (`~~API:Long`).
+    /// Keys end before a `[`. A following bracket defines the entry inline
+    /// (`~~API[long="Application Programming Interface"]`) if it contains
+    /// any of the entry fields and is kept as text otherwise (`~~API[1]`)
     fn parse_glossary_reference(&mut self) -> ParseResult<Arc<Mutex<GlossaryReference>>> {
         let start_index = self.ctm.get_index();
         self.ctm
@@ -437,11 +479,13 @@ impl ParseInline for Parser {
             self.ctm.seek_one()?;
             GlossaryDisplay::Long
         } else {
-            GlossaryDisplay::Short
+            GlossaryDisplay::Auto
         };
-        let mut key =
-            self.ctm
-                .get_string_until_any_or_rewind(&WHITESPACE, &[TILDE], start_index)?;
+        let mut key = self.ctm.get_string_until_any_or_rewind(
+            &[WHITESPACE, &[GLOSSARY_DEF_OPEN]].concat(),
+            &[TILDE],
+            start_index,
+        )?;
         if key.is_empty() {
             return Err(self.ctm.rewind_with_error(start_index).into());
         }
@@ -449,7 +493,52 @@ impl ParseInline for Parser {
             self.ctm.rewind(self.ctm.get_index() - 1);
             key = key[..key.len() - 1].to_string();
         }
-        let reference = GlossaryReference::with_display(key, display);
+        let mut reference = GlossaryReference::with_display(key.clone(), display);
+        if let Some((name, modifier)) = key.split_once(GLOSSARY_REF_DISPLAY) {
+            if let Some((display, capitalized)) = GlossaryDisplay::from_modifier(modifier) {
+                reference.short = name.to_string();
+                reference.display = display;
+                reference.capitalized = capitalized;
+            }
+        }
+        if self.ctm.check_char(&GLOSSARY_DEF_OPEN) {
+            let bracket_index = self.ctm.get_index();
+            let meta = self
+                .parse_inline_metadata()
+                .ok()
+                .map(|meta| meta.get_string_map())
+                .filter(|map| GLOSSARY_ENTRY_KEYS.iter().any(|k| map.contains_key(*k)));
+            if let Some(map) = meta {
+                let position = self.get_position_string_for_index(start_index);
+                match GlossaryEntry::from_string_map(
+                    reference.short.clone(),
+                    &map,
+                    position.clone(),
+                ) {
+                    Ok(entry) => {
+                        self.options.document.glossary.lock().add_entry(entry);
+                    }
+                    Err(msg) => {
+                        log::error!(
+                            "Failed to parse glossary entry '{}': {}\n\t--> {}\n",
+                            reference.short,
+                            msg,
+                            position
+                        );
+                    }
+                }
+            } else {
+                self.ctm.rewind(bracket_index);
+            }
+        }
+        reference.position = Some(self.get_position_string_for_index(start_index));
+        reference.first_use_display = self
+            .options
+            .document
+            .config
+            .lock()
+            .style
+            .get_glossary_first_use();
 
         Ok(self
             .options
@@ -497,7 +586,7 @@ impl ParseInline for Parser {
         self.ctm.assert_char(&META_OPEN, Some(start_index))?;
         self.ctm.seek_one()?;
 
-        let mut values = HashMap::new();
+        let mut values = BTreeMap::new();
         while let Ok((key, value)) = self.parse_metadata_pair() {
             values.insert(key, value);
             if self.ctm.check_char(&META_CLOSE) || self.ctm.check_char(&LB) {
@@ -522,7 +611,7 @@ impl ParseInline for Parser {
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
         let name = self
             .ctm
-            .get_string_until_any(&[META_CLOSE, EQ, SPACE, LB], &[])?;
+            .get_string_until_any(&[META_CLOSE, EQ, SPACE, LB, COMMA], &[])?;
 
         self.ctm.seek_any(&INLINE_WHITESPACE)?;
         let mut value = MetadataValue::Bool(true);
@@ -542,7 +631,7 @@ impl ParseInline for Parser {
                     self.ctm.seek_one()?;
                     vec![quote_start, META_CLOSE, LB]
                 } else {
-                    vec![META_CLOSE, LB, SPACE]
+                    vec![META_CLOSE, LB, SPACE, COMMA]
                 };
 
                 let raw_value = self.ctm.get_string_until_any(&parse_until, &[])?;
@@ -570,6 +659,8 @@ impl ParseInline for Parser {
                     MetadataValue::String(raw_value)
                 }
             }
+        } else if self.ctm.check_char(&COMMA) {
+            self.ctm.seek_one()?;
         }
 
         Ok((name, value))
@@ -595,7 +686,9 @@ impl ParseInline for Parser {
 
         let metadata = self.parse_inline_metadata().ok();
 
-        let placeholder = Arc::new(RwLock::new(Placeholder::new(name, metadata)));
+        let mut placeholder = Placeholder::new(name, metadata);
+        placeholder.position = Some(self.get_position_string_for_index(start_index));
+        let placeholder = Arc::new(RwLock::new(placeholder));
         self.options
             .document
             .add_placeholder(Arc::clone(&placeholder));
@@ -708,4 +801,37 @@ impl ParseInline for Parser {
             key,
         })
     }
+
+    /// parses an index term `[!term!sub term|see also other term]`
+    /// with an optional sub term and cross reference
+    fn parse_index_entry(&mut self) -> ParseResult<Arc<RwLock<IndexEntry>>> {
+        let start_index = self.ctm.get_index();
+        self.ctm.assert_sequence(INDEX_START, Some(start_index))?;
+        self.ctm.seek_one()?;
+        let content = self
+            .ctm
+            .get_string_until_any_or_rewind(&[INDEX_STOP], &[LB], start_index)?;
+        self.ctm.try_seek();
+
+        let (term, cross_reference) = match content.split_once(INDEX_CROSS_REF) {
+            Some((term, cross_reference)) => (
+                term,
+                Some(
+                    IndexCrossReference::from_string(cross_reference)
+                        .ok_or_else(|| self.ctm.rewind_with_error(start_index))?,
+                ),
+            ),
+            None => (content.as_str(), None),
+        };
+        let (term, sub_term) = split_term(term);
+        if term.is_empty() || sub_term == Some("") {
+            return Err(self.ctm.rewind_with_error(start_index).into());
+        }
+
+        Ok(Arc::new(RwLock::new(IndexEntry::new(
+            term.to_string(),
+            sub_term.map(|s| s.to_string()),
+            cross_reference,
+        ))))
+    }
 }
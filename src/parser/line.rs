@@ -8,14 +8,23 @@ use super::ParseResult;
 use crate::elements::tokens::*;
 use crate::elements::Inline::LineBreak;
 use crate::elements::{BibEntry, Metadata};
-use crate::elements::{Cell, Centered, Header, Line, ListItem, Row, Ruler, TextLine};
+use crate::elements::{
+    Cell, Centered, Header, Inline, Line, ListItem, PlainText, Row, Ruler, TextLine,
+};
 use crate::parser::inline::ParseInline;
+use crate::utils::parsing::slugify;
 use crate::Parser;
 use bibliographix::bibliography::bibliography_entry::BibliographyEntry;
 use bibliographix::bibliography::keys::{K_KEY, K_TYPE, K_URL, T_WEBSITE};
 use bibliographix::bibliography::FromHashMap;
+use regex::Regex;
 use std::collections::HashMap;
 
+use crate::plain_text;
+
+/// The anchor of headers that don't contain any text that can be used for an anchor
+const DEFAULT_ANCHOR: &str = "section";
+
 pub(crate) trait ParseLine {
     fn parse_line(&mut self) -> ParseResult<Line>;
     fn parse_header(&mut self) -> ParseResult<Header>;
@@ -56,20 +65,52 @@ impl ParseLine for Parser {
         }
     }
 
-    /// parses the header of a section
+    /// parses the header of a section.
+    /// The anchor of the header is the slug of its text
+    /// unless an explicit anchor is given with `{#anchor}` at the end of the line
     fn parse_header(&mut self) -> ParseResult<Header> {
         let start_index = self.ctm.get_index();
-        let line = self.parse_line()?;
-        let mut anchor = String::new();
-        self.ctm.get_text()[start_index..self.ctm.get_index()]
-            .iter()
-            .for_each(|e| anchor.push(*e));
-        if let Some(last) = self.section_anchors.last() {
-            anchor = format!("{}-{}", last, anchor);
-        }
-        anchor.retain(|c| !c.is_whitespace());
+        let mut line = self.parse_line()?;
+        let custom_anchor = take_custom_anchor(&mut line);
+        let legacy_anchors = self
+            .options
+            .document
+            .config
+            .lock()
+            .features
+            .legacy_header_anchors;
+
+        let anchor = if let Some(anchor) = &custom_anchor {
+            anchor.clone()
+        } else if legacy_anchors {
+            let mut anchor = String::new();
+            self.ctm.get_text()[start_index..self.ctm.get_index()]
+                .iter()
+                .for_each(|e| anchor.push(*e));
+            if let Some(last) = self.section_anchors.last() {
+                anchor = format!("{}-{}", last, anchor);
+            }
+            anchor.retain(|c| !c.is_whitespace());
+            anchor
+        } else {
+            let text = line
+                .as_raw_text()
+                .subtext
+                .iter()
+                .map(|i| i.as_plain_text().value)
+                .collect::<String>();
+            let slug = slugify(&text);
+            if slug.is_empty() {
+                DEFAULT_ANCHOR.to_string()
+            } else {
+                slug
+            }
+        };
         log::trace!("Line::Header");
-        Ok(Header::new(line, anchor))
+        let mut header = Header::new(line, anchor);
+        header.custom_anchor = custom_anchor.is_some();
+
+        Ok(header)
     }
 
     /// parses a single list item defined with -
@@ -275,6 +316,11 @@ impl ParseLine for Parser {
             .entry_dictionary()
             .lock()
             .insert(entry);
+        self.options
+            .document
+            .bib_entry_positions
+            .lock()
+            .add(key.clone(), self.get_position_string_for_index(start_index));
 
         Ok(BibEntry {
             entry: self
@@ -289,3 +335,46 @@ impl ParseLine for Parser {
         })
     }
 }
+
+/// Removes an explicit anchor in the form `{#anchor}` from the end of the line
+/// and returns the anchor
+fn take_custom_anchor(line: &mut Line) -> Option<String> {
+    lazy_static::lazy_static! {
+        static ref R_ANCHOR: Regex = Regex::new(r"^(?s)(.*?)\s*\{#([^\s{}]+)\}\s*$").unwrap();
+    }
+    let text = match line {
+        Line::Text(text) => text,
+        _ => return None,
+    };
+    // the anchor may be split into multiple plain texts by the parser
+    // and is followed by the line break of the header line
+    let end = text.subtext.len()
+        - text
+            .subtext
+            .iter()
+            .rev()
+            .take_while(|i| matches!(i, LineBreak))
+            .count();
+    let start = end
+        - text.subtext[..end]
+            .iter()
+            .rev()
+            .take_while(|i| matches!(i, Inline::Plain(_)))
+            .count();
+    let tail = text.subtext[start..end]
+        .iter()
+        .map(|i| i.as_plain_text().value)
+        .collect::<String>();
+    let captures = R_ANCHOR.captures(&tail)?;
+    let anchor = captures[2].to_string();
+    let rest = captures[1].to_string();
+    let line_breaks = text.subtext.split_off(end);
+    text.subtext.truncate(start);
+
+    if !rest.is_empty() {
+        text.subtext.push(plain_text!(rest));
+    }
+    text.subtext.extend(line_breaks);
+
+    Some(anchor)
+}
@@ -9,17 +9,29 @@ pub(crate) mod inline;
 pub(crate) mod line;
 
 use self::block::ParseBlock;
-use crate::elements::tokens::LB;
-use crate::elements::{Document, ImportAnchor};
-use crate::settings::SettingsError;
+use crate::elements::tokens::{
+    LB, SQ_FRONT_MATTER_TOML, SQ_FRONT_MATTER_TOML_END, SQ_FRONT_MATTER_YAML,
+    SQ_FRONT_MATTER_YAML_END,
+};
+use crate::elements::{Block, Document, Import, ImportAnchor, InlineMetadata, MetadataValue};
+use crate::references::bibtex::BibTexParser;
+use crate::references::glossary_import::{
+    read_glossary, GlossaryColumns, GlossaryFormat, GlossaryIssue, A_DELIMITER, A_FORMAT,
+};
+use crate::settings::validation::{validate_config_file, validate_config_value, ConfigIssue};
+use crate::settings::{SettingsError, SettingsOverrides};
+use crate::utils::caching::CacheStorage;
+use crate::utils::downloads::{download_cached, is_remote};
+use crate::utils::parsing::get_toml_line;
+use bibliographix::references::bib_reference::BibRef;
 use charred::tapemachine::{CharTapeMachine, TapeError};
 use crossbeam_utils::sync::WaitGroup;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
-use std::fs::{read_to_string, File};
-use std::io::{self, BufReader};
-use std::path::PathBuf;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
@@ -64,8 +76,14 @@ impl From<io::Error> for ParseError {
 pub struct ParserOptions {
     pub path: Option<PathBuf>,
     pub paths: Arc<Mutex<Vec<PathBuf>>>,
+    pub import_stack: Vec<PathBuf>,
+    pub header_shift: i8,
     pub document: Document,
     pub is_child: bool,
+    pub offline: bool,
+    pub defines: HashMap<String, String>,
+    pub settings_overrides: SettingsOverrides,
+    pub cache: CacheStorage,
 }
 
 impl Default for ParserOptions {
@@ -73,8 +91,14 @@ impl Default for ParserOptions {
         Self {
             path: None,
             paths: Arc::new(Mutex::new(Vec::new())),
+            import_stack: Vec::new(),
+            header_shift: 0,
             document: Document::new(),
             is_child: false,
+            offline: false,
+            defines: HashMap::new(),
+            settings_overrides: SettingsOverrides::default(),
+            cache: CacheStorage::new(),
         }
     }
 }
@@ -83,7 +107,50 @@ impl ParserOptions {
     /// Adds a path to the parser options
     pub fn add_path(mut self, path: PathBuf) -> Self {
         self.path = Some(path.clone());
-        self.paths.lock().unwrap().push(path);
+        self.import_stack.push(path.clone());
+        {
+            let mut paths = self.paths.lock().unwrap();
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+
+        self
+    }
+
+    /// Adds a value that can be used in conditions
+    pub fn add_define(mut self, key: String, value: String) -> Self {
+        self.defines.insert(key, value);
+
+        self
+    }
+
+    /// Adds a config file that is merged on top of the manifest
+    pub fn add_config_file(mut self, path: PathBuf) -> Self {
+        self.settings_overrides.files.push(path);
+
+        self
+    }
+
+    /// Adds a setting that overrides the value from the manifest
+    pub fn add_setting_override(mut self, key: String, value: String) -> Self {
+        self.settings_overrides.values.push((key, value));
+
+        self
+    }
+
+    /// Sets if remote imports should only be read from the cache
+    pub fn set_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self.document.downloads.lock().set_offline(offline);
+
+        self
+    }
+
+    /// Sets the cache remote imports and downloads are stored in
+    pub fn set_cache(mut self, cache: CacheStorage) -> Self {
+        self.document.downloads.lock().set_cache(cache.clone());
+        self.cache = cache;
 
         self
     }
@@ -96,7 +163,9 @@ pub struct Parser {
     sections: Vec<u8>,
     section_anchors: Vec<String>,
     section_return: Option<u8>,
+    conditional_depth: usize,
     wg: WaitGroup,
+    pending_bib_refs: BTreeMap<(usize, usize), BibRef>,
     pub(crate) block_break_at: Vec<char>,
     pub(crate) inline_break_at: Vec<char>,
     pub(crate) parse_variables: bool,
@@ -106,7 +175,12 @@ impl Parser {
     /// Creates a new parser with the default values given
     pub fn with_defaults(options: ParserOptions) -> Self {
         let text = if let Some(path) = &options.path {
-            let mut text = read_to_string(&path).unwrap();
+            let mut text = read_import_file(path, &options.cache, options.offline)
+                .map(|t| String::from_utf8_lossy(&t).to_string())
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to read {}: {}", path.to_string_lossy(), e);
+                    String::new()
+                });
             text = text.replace("\r\n", "\n");
             if text.chars().last() != Some('\n') {
                 text.push('\n');
@@ -122,7 +196,9 @@ impl Parser {
             section_anchors: Vec::new(),
             section_nesting: 0,
             section_return: None,
+            conditional_depth: 0,
             wg: WaitGroup::new(),
+            pending_bib_refs: BTreeMap::new(),
             ctm: CharTapeMachine::new(text.chars().collect()),
             inline_break_at: Vec::new(),
             block_break_at: Vec::new(),
@@ -130,16 +206,72 @@ impl Parser {
         }
     }
 
+    /// Registers the references of citations that were parsed before the given index.
+    /// References are collected first because backtracking can parse the same citation
+    /// multiple times and only the last parse ends up in the document.
+    fn register_bib_refs(&mut self, until: usize) {
+        let pending = self.pending_bib_refs.split_off(&(until, 0));
+        let anchor = self.options.document.bibliography.root_ref_anchor();
+        let mut anchor = anchor.lock();
+
+        for (_, bib_ref) in std::mem::replace(&mut self.pending_bib_refs, pending) {
+            anchor.insert(bib_ref);
+        }
+    }
+
     /// Creates a new child parser
-    fn create_child(&self, path: PathBuf) -> Self {
+    fn create_child(&mut self, path: PathBuf, args: &HashMap<String, String>) -> Self {
+        self.register_bib_refs(self.ctm.get_index());
         let mut options = self.options.clone().add_path(path.clone());
         options.document = self.options.document.create_child();
         options.document.path = Some(path.to_str().unwrap().to_string());
+        options.header_shift = self.get_import_header_shift(args);
+        for (key, value) in args {
+            if !IMPORT_OPTIONS.contains(&key.as_str()) {
+                options
+                    .document
+                    .variables
+                    .insert(key.to_lowercase(), value.clone());
+            }
+        }
         options.is_child = true;
 
         Self::with_defaults(options)
     }
 
+    /// Returns the header shift for an imported document.
+    /// The shift is either relative to the current shift or
+    /// set to the level of the enclosing section with `shift=auto`
+    fn get_import_header_shift(&self, args: &HashMap<String, String>) -> i8 {
+        match args.get("shift").map(|s| s.as_str()) {
+            Some("auto") => self
+                .sections
+                .last()
+                .map(|s| self.get_shifted_size(*s) as i8)
+                .unwrap_or(self.options.header_shift),
+            Some(shift) => {
+                if let Ok(shift) = shift.parse::<i8>() {
+                    self.options.header_shift.saturating_add(shift)
+                } else {
+                    log::warn!(
+                        "Invalid header shift \"{}\" for import.\n\t--> {}\n",
+                        shift,
+                        self.get_position_string()
+                    );
+                    self.options.header_shift
+                }
+            }
+            None => self.options.header_shift,
+        }
+    }
+
+    /// Returns the header size shifted by the header shift of the parser
+    pub(crate) fn get_shifted_size(&self, size: u8) -> u8 {
+        (size as i8)
+            .saturating_add(self.options.header_shift)
+            .clamp(1, 6) as u8
+    }
+
     /// Returns a string of the current position in the file
     pub(crate) fn get_position_string(&self) -> String {
         let char_index = self.ctm.get_index();
@@ -166,6 +298,16 @@ impl Parser {
 
     /// transform an import path to be relative to the current parsers file
     fn transform_path(&mut self, path: String) -> PathBuf {
+        if is_remote(&path) {
+            return PathBuf::from(path);
+        }
+        if let Some(selfpath) = self.options.path.as_ref().and_then(|p| p.to_str()) {
+            if is_remote(selfpath) && !path.starts_with('/') {
+                if let Some((base, _)) = selfpath.rsplit_once('/') {
+                    return PathBuf::from(format!("{}/{}", base, path));
+                }
+            }
+        }
         let mut path = PathBuf::from(path);
 
         if !path.is_absolute() {
@@ -180,8 +322,12 @@ impl Parser {
     }
 
     /// starts up a new thread to parse the imported document
-    fn import_document(&mut self, path: PathBuf) -> ParseResult<Arc<RwLock<ImportAnchor>>> {
-        if !path.exists() || !path.is_file() {
+    fn import_document(
+        &mut self,
+        path: PathBuf,
+        args: &HashMap<String, String>,
+    ) -> ParseResult<Arc<RwLock<ImportAnchor>>> {
+        if !is_remote(&path.to_string_lossy()) && (!path.exists() || !path.is_file()) {
             log::error!(
                 "Import of \"{}\" failed: The file doesn't exist.\n\t--> {}\n",
                 path.to_str().unwrap(),
@@ -192,7 +338,7 @@ impl Parser {
         let anchor = Arc::new(RwLock::new(ImportAnchor::new()));
         let anchor_clone = Arc::clone(&anchor);
         let wg = self.wg.clone();
-        let mut child_parser = self.create_child(path.clone());
+        let mut child_parser = self.create_child(path.clone(), args);
 
         let _ = thread::spawn(move || {
             let document = child_parser.parse();
@@ -204,21 +350,52 @@ impl Parser {
         Ok(anchor)
     }
 
-    /// Imports a bibliography toml file
+    /// Imports a bibliography toml or BibTeX file
     fn import_bib(&mut self, path: PathBuf) -> ParseResult<()> {
-        let f = File::open(path).map_err(|_| self.ctm.err())?;
-        self.options
-            .document
-            .bibliography
-            .read_bib_file(&mut BufReader::new(f))
+        let data = read_import_file(&path, &self.options.cache, self.options.offline)
             .map_err(|_| self.ctm.err())?;
 
+        let source = path.to_string_lossy();
+        let contents = String::from_utf8_lossy(&data);
+        let mut positions = self.options.document.bib_entry_positions.lock();
+
+        if is_bibtex_file(&path) {
+            let entries = BibTexParser::new(&contents, &source).parse();
+            let entry_dict = self.options.document.bibliography.entry_dictionary();
+            let mut entry_dict = entry_dict.lock();
+            entries.into_iter().for_each(|(entry, position)| {
+                positions.add(entry.key(), position);
+                entry_dict.insert(entry)
+            });
+        } else {
+            self.options
+                .document
+                .bibliography
+                .read_bib_file(&mut data.as_slice())
+                .map_err(|_| self.ctm.err())?;
+            if let Some(table) = contents
+                .parse::<toml::Value>()
+                .ok()
+                .and_then(|v| v.as_table().cloned())
+            {
+                for key in table.keys() {
+                    let position = match get_toml_line(&contents, key) {
+                        Some(line) => format!("{}:{}", source, line),
+                        None => source.to_string(),
+                    };
+                    positions.add(key.clone(), position);
+                }
+            }
+        }
+
         Ok(())
     }
 
     /// Returns the text of an imported text file
     fn import_text_file(&self, path: PathBuf) -> ParseResult<String> {
-        read_to_string(path).map_err(ParseError::from)
+        let data = read_import_file(&path, &self.options.cache, self.options.offline)?;
+
+        String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e).into())
     }
 
     fn import_stylesheet(&mut self, path: PathBuf) -> ParseResult<()> {
@@ -234,26 +411,49 @@ impl Parser {
     }
 
     fn import_manifest(&mut self, path: PathBuf) -> ParseResult<()> {
-        self.options
-            .document
-            .config
-            .lock()
-            .merge(path)
-            .map_err(ParseError::from)
-    }
-
-    /// Imports a glossary
-    fn import_glossary(&self, path: PathBuf) -> ParseResult<()> {
-        let contents = self.import_text_file(path)?;
-        let value = contents
-            .parse::<toml::Value>()
-            .map_err(|_| self.ctm.err())?;
-        self.options
-            .document
-            .glossary
-            .lock()
-            .assign_from_toml(value)
-            .unwrap_or_else(|e| log::error!("{}", e));
+        validate_config_file(&path)
+            .iter()
+            .for_each(ConfigIssue::log);
+        let mut config = self.options.document.config.lock();
+        config.merge(path)?;
+        config.apply_overrides(&self.options.settings_overrides)?;
+
+        Ok(())
+    }
+
+    /// Imports a glossary toml, csv or json file.
+    /// The columns of the glossary fields can be changed in the manifest
+    /// and with the import arguments named after the fields
+    fn import_glossary(&self, path: PathBuf, args: &HashMap<String, String>) -> ParseResult<()> {
+        let contents = self.import_text_file(path.clone())?;
+        let format = GlossaryFormat::from_path(&path, args.get(A_FORMAT).map(|f| f.as_str()));
+        let mut columns = GlossaryColumns::from_map(
+            &self.options.document.config.lock().imports.glossary_columns,
+        );
+        columns.update(args);
+        let delimiter = match args.get(A_DELIMITER).map(|d| d.as_bytes()) {
+            Some([delimiter]) => *delimiter,
+            Some(_) => {
+                log::warn!(
+                    "The csv delimiter must be a single character\n\t--> {}\n",
+                    self.get_position_string()
+                );
+                b','
+            }
+            None => b',',
+        };
+        let (entries, issues) = read_glossary(
+            &contents,
+            &path.to_string_lossy(),
+            &format,
+            &columns,
+            delimiter,
+        );
+        issues.iter().for_each(GlossaryIssue::log);
+        let mut glossary = self.options.document.glossary.lock();
+        entries.into_iter().for_each(|e| {
+            glossary.add_entry(e);
+        });
 
         Ok(())
     }
@@ -266,7 +466,16 @@ impl Parser {
             self.get_position_string()
         );
         let path = self.transform_path(path);
-        if !path.exists() {
+        if !is_remote(&path.to_string_lossy()) && (path.is_dir() || is_glob_pattern(&path)) {
+            return self.import_glob(path, args);
+        }
+
+        self.import_path(path, args)
+    }
+
+    /// Imports a path that has already been transformed
+    fn import_path(&mut self, path: PathBuf, args: &HashMap<String, String>) -> ImportType {
+        if !is_remote(&path.to_string_lossy()) && !path.exists() {
             log::error!(
                 "Import of \"{}\" failed: The file doesn't exist.\n\t--> {}\n",
                 path.to_str().unwrap(),
@@ -283,9 +492,30 @@ impl Parser {
                 return ImportType::None;
             }
         }
+        let import_type = get_import_type(&path, args);
         {
             let mut paths = self.options.paths.lock().unwrap();
-            if paths.iter().find(|item| **item == path).is_some() {
+            let already_imported = paths.contains(&path);
+
+            if import_type == I_DOCUMENT {
+                if let Some(chain) = self.get_import_cycle(&path) {
+                    log::error!(
+                        "Import of \"{}\" failed: Import cycle detected: {}\n\t--> {}\n",
+                        path.to_str().unwrap(),
+                        chain,
+                        self.get_position_string(),
+                    );
+                    return ImportType::None;
+                }
+                if already_imported && self.get_import_flag(args, "once") {
+                    log::debug!(
+                        "Skipping import of \"{}\": Already imported.\n\t--> {}\n",
+                        path.to_str().unwrap(),
+                        self.get_position_string(),
+                    );
+                    return ImportType::None;
+                }
+            } else if already_imported {
                 log::warn!(
                     "Import of \"{}\" failed: Already imported.\n\t--> {}\n",
                     path.to_str().unwrap(),
@@ -293,49 +523,171 @@ impl Parser {
                 );
                 return ImportType::None;
             }
-            paths.push(path.clone());
-        }
-        match args.get("type").cloned() {
-            Some(s) if s == "stylesheet".to_string() => {
-                ImportType::Stylesheet(self.import_stylesheet(path))
+            if !already_imported {
+                paths.push(path.clone());
             }
-            Some(s) if s == "document".to_string() => {
-                ImportType::Document(self.import_document(path))
-            }
-            Some(s) if s == "bibliography".to_string() => {
-                ImportType::Bibliography(self.import_bib(path))
+        }
+        match import_type {
+            I_STYLESHEET => ImportType::Stylesheet(self.import_stylesheet(path)),
+            I_BIBLIOGRAPHY => ImportType::Bibliography(self.import_bib(path)),
+            I_MANIFEST => ImportType::Manifest(self.import_manifest(path)),
+            I_GLOSSARY => ImportType::Glossary(self.import_glossary(path, args)),
+            _ => ImportType::Document(self.import_document(path, args)),
+        }
+    }
+
+    /// Returns the value of a boolean import option.
+    /// Bare flags (`[once]`) are true and unrecognised values are false
+    fn get_import_flag(&self, args: &HashMap<String, String>, key: &str) -> bool {
+        match args.get(key).map(|value| value.to_lowercase()).as_deref() {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(value) => {
+                log::warn!(
+                    "Invalid value \"{}\" for the import option \"{}\". Expected true or false.\n\t--> {}\n",
+                    value,
+                    key,
+                    self.get_position_string(),
+                );
+                false
             }
-            Some(s) if s == "manifest".to_string() || s == "config" => {
-                ImportType::Manifest(self.import_manifest(path))
+        }
+    }
+
+    /// Imports all files matching a glob pattern or all snekdown files of a directory.
+    /// The matches are sorted naturally with the files listed in the `order` option first.
+    /// The imported documents are wrapped in a single document import.
+    fn import_glob(&mut self, path: PathBuf, args: &HashMap<String, String>) -> ImportType {
+        let (pattern, watch_dir) = if path.is_dir() {
+            (path.join("*.sd"), path.clone())
+        } else {
+            let dir = path
+                .ancestors()
+                .find(|a| !is_glob_pattern(a))
+                .map(|a| a.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from("."));
+            (path.clone(), dir)
+        };
+        {
+            // the directory gets watched so that files added later get picked up
+            let mut paths = self.options.paths.lock().unwrap();
+            if watch_dir.is_dir() && !paths.contains(&watch_dir) {
+                paths.push(watch_dir);
             }
-            Some(s) if s == "glossary".to_string() => {
-                ImportType::Glossary(self.import_glossary(path))
+        }
+        let mut matches: Vec<PathBuf> = match glob::glob(pattern.to_str().unwrap()) {
+            Ok(paths) => paths
+                .filter_map(|p| p.ok())
+                .filter(|p| p.is_file() && Some(p) != self.options.path.as_ref())
+                .collect(),
+            Err(e) => {
+                log::error!(
+                    "Import of \"{}\" failed: {}\n\t--> {}\n",
+                    pattern.to_str().unwrap(),
+                    e,
+                    self.get_position_string(),
+                );
+                return ImportType::None;
             }
-            _ => {
-                lazy_static::lazy_static! {
-                    static ref BIB_NAME: Regex = Regex::new(r".*\.bib\.toml$").unwrap();
-                }
-                if let Some(fname) = path.file_name().and_then(|f| Some(f.to_str().unwrap())) {
-                    if BIB_NAME.is_match(fname) {
-                        return ImportType::Bibliography(self.import_bib(path));
-                    }
-                }
-                match path.extension().map(|e| e.to_str().unwrap().to_lowercase()) {
-                    Some(e) if e == "css" => ImportType::Stylesheet(self.import_stylesheet(path)),
-                    Some(e) if e == "toml" => ImportType::Manifest(self.import_manifest(path)),
-                    _ => ImportType::Document(self.import_document(path)),
-                }
+        };
+        if matches.is_empty() {
+            log::warn!(
+                "Import of \"{}\" matched no files.\n\t--> {}\n",
+                pattern.to_str().unwrap(),
+                self.get_position_string(),
+            );
+        }
+        let order: Vec<String> = args
+            .get("order")
+            .map(|o| o.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_default();
+        matches.sort_by(|a, b| {
+            let a_name = a.file_name().unwrap().to_string_lossy().to_string();
+            let b_name = b.file_name().unwrap().to_string_lossy().to_string();
+            let a_pos = order
+                .iter()
+                .position(|o| *o == a_name)
+                .unwrap_or(order.len());
+            let b_pos = order
+                .iter()
+                .position(|o| *o == b_name)
+                .unwrap_or(order.len());
+
+            a_pos
+                .cmp(&b_pos)
+                .then_with(|| natord::compare(&a.to_string_lossy(), &b.to_string_lossy()))
+        });
+
+        self.register_bib_refs(self.ctm.get_index());
+        let mut document = self.options.document.create_child();
+        document.path = Some(pattern.to_string_lossy().to_string());
+
+        for path in matches {
+            if let ImportType::Document(Ok(anchor)) = self.import_path(path.clone(), args) {
+                document.add_element(Block::Import(Import {
+                    path: path.to_string_lossy().to_string(),
+                    anchor,
+                }));
             }
         }
+        let mut anchor = ImportAnchor::new();
+        anchor.set_document(document);
+
+        ImportType::Document(Ok(Arc::new(RwLock::new(anchor))))
+    }
+
+    /// Returns the chain of imports as a string if importing the given
+    /// path would result in an import cycle
+    fn get_import_cycle(&self, path: &Path) -> Option<String> {
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let position = self
+            .options
+            .import_stack
+            .iter()
+            .position(|p| p.canonicalize().unwrap_or_else(|_| p.clone()) == canonical)?;
+        let chain = self.options.import_stack[position..]
+            .iter()
+            .chain(std::iter::once(&path.to_path_buf()))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<String>>()
+            .join(" → ");
+
+        Some(chain)
     }
 
     /// parses the given text into a document
     pub fn parse(&mut self) -> Document {
-        self.options.document.path = if let Some(path) = &self.options.path {
-            Some(path.canonicalize().unwrap().to_str().unwrap().to_string())
-        } else {
-            None
-        };
+        self.options.document.path = self.options.path.as_ref().map(|path| {
+            path.canonicalize()
+                .unwrap_or_else(|_| path.clone())
+                .to_str()
+                .unwrap()
+                .to_string()
+        });
+
+        if !self.options.is_child {
+            self.options
+                .settings_overrides
+                .files
+                .iter()
+                .flat_map(|path| validate_config_file(path))
+                .for_each(|issue| issue.log());
+            if let Err(e) = self
+                .options
+                .document
+                .config
+                .lock()
+                .apply_overrides(&self.options.settings_overrides)
+            {
+                log::error!("Failed to apply config overrides: {}", e);
+            }
+            self.import(
+                "Manifest.toml".to_string(),
+                &maplit::hashmap! {"type".to_string() => "manifest".to_string()},
+            );
+        }
+
+        self.parse_front_matter();
 
         while !self.ctm.check_eof() {
             match self.parse_block() {
@@ -361,14 +713,9 @@ impl Parser {
             }
         }
 
+        self.register_bib_refs(usize::MAX);
         let wg = self.wg.clone();
         self.wg = WaitGroup::new();
-        if !self.options.is_child {
-            self.import(
-                "Manifest.toml".to_string(),
-                &maplit::hashmap! {"type".to_string() => "manifest".to_string()},
-            );
-        }
         wg.wait();
         if !self.options.is_child {
             self.import_from_config();
@@ -379,6 +726,126 @@ impl Parser {
         document
     }
 
+    /// Parses an optional front matter block at the start of the document.
+    /// The block contains either toml surrounded by `+++` or yaml surrounded by `---`.
+    /// It is merged into the settings for the root document and
+    /// stored as document variables for imported documents.
+    fn parse_front_matter(&mut self) {
+        let start_index = self.ctm.get_index();
+        let (end_sequence, is_yaml) = if self.ctm.check_sequence(SQ_FRONT_MATTER_TOML) {
+            (SQ_FRONT_MATTER_TOML_END, false)
+        } else if self.ctm.check_sequence(SQ_FRONT_MATTER_YAML) {
+            (SQ_FRONT_MATTER_YAML_END, true)
+        } else {
+            return;
+        };
+        let contents = self
+            .ctm
+            .get_string_until_sequence(&[end_sequence], &[])
+            .unwrap_or_default();
+
+        if self.ctm.check_eof() {
+            // not a front matter block if it isn't closed
+            self.ctm.rewind(start_index);
+            return;
+        }
+        let _ = self.ctm.seek_one();
+
+        // the end sequence has to be followed by a line break or the end of the document
+        if !self.ctm.check_eof() && !self.ctm.check_char(&LB) {
+            self.ctm.rewind(start_index);
+            return;
+        }
+        let _ = self.ctm.seek_one();
+
+        let value = if is_yaml {
+            serde_yaml::from_str::<toml::Value>(&contents).map_err(|e| e.to_string())
+        } else {
+            contents.parse::<toml::Value>().map_err(|e| e.to_string())
+        };
+        let value = match value {
+            Ok(value) => value,
+            Err(e) => {
+                log::error!(
+                    "Invalid front matter: {}\n\t--> {}\n",
+                    e,
+                    self.get_position_string_for_index(start_index)
+                );
+                return;
+            }
+        };
+
+        if self.options.is_child {
+            flatten_front_matter(&value, None, &mut self.options.document.variables);
+        } else {
+            let file = self.options.document.path.clone().unwrap_or_default();
+            // the lines of converted yaml don't match the document
+            let line_contents = if is_yaml { "" } else { contents.as_str() };
+            validate_config_value(&value, line_contents, &file)
+                .iter()
+                .for_each(ConfigIssue::log);
+            let mut config = self.options.document.config.lock();
+
+            if let Err(e) = config
+                .merge_value(&value)
+                .and_then(|_| config.apply_overrides(&self.options.settings_overrides))
+            {
+                log::error!(
+                    "Failed to apply front matter: {}\n\t--> {}\n",
+                    e,
+                    self.get_position_string_for_index(start_index)
+                );
+            }
+        }
+    }
+
+    /// Evaluates a condition of a conditional block.
+    /// Every key has to match the defined value or be set when no value is given.
+    /// Keys prefixed with an exclamation mark are negated.
+    pub(crate) fn evaluate_condition(&self, condition: &InlineMetadata) -> bool {
+        condition.data.iter().all(|(key, value)| {
+            let (negated, key) = if let Some(key) = key.strip_prefix('!') {
+                (true, key)
+            } else {
+                (false, key.as_str())
+            };
+            let actual = self.get_condition_value(key);
+            let matches = match value {
+                MetadataValue::Bool(true) => actual
+                    .map(|v| !v.is_empty() && v != "false")
+                    .unwrap_or(false),
+                value => actual == Some(value.to_string()),
+            };
+
+            matches != negated
+        })
+    }
+
+    /// Returns the value for a condition key either from the defined values,
+    /// the document variables, the custom attributes or the settings
+    fn get_condition_value(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.options.defines.get(key) {
+            return Some(value.clone());
+        }
+        if let Some(value) = self.options.document.variables.get(key) {
+            return Some(value.clone());
+        }
+        let config = self.options.document.config.lock();
+        if let Some(value) = config.custom_attributes.get(key) {
+            return Some(value.clone());
+        }
+        let mut value = toml::Value::try_from(&*config).ok()?;
+        for part in key.split('.') {
+            value = value.get(part)?.clone();
+        }
+
+        match value {
+            toml::Value::String(s) => Some(s),
+            toml::Value::Table(_) => None,
+            v => Some(v.to_string()),
+        }
+    }
+
     pub fn get_paths(&self) -> Vec<PathBuf> {
         self.options.paths.lock().unwrap().clone()
     }
@@ -408,6 +875,109 @@ impl Parser {
     }
 }
 
+const I_STYLESHEET: &str = "stylesheet";
+const I_DOCUMENT: &str = "document";
+const I_BIBLIOGRAPHY: &str = "bibliography";
+const I_MANIFEST: &str = "manifest";
+const I_GLOSSARY: &str = "glossary";
+
+/// Import metadata keys that configure the import itself
+/// and aren't passed to the imported document as variables
+const IMPORT_OPTIONS: &[&str] = &["type", "once", "shift", "order"];
+
+/// Reads the contents of a local file or downloads a remote file
+fn read_import_file(path: &Path, cache: &CacheStorage, offline: bool) -> io::Result<Vec<u8>> {
+    let path_str = path.to_string_lossy();
+
+    if is_remote(&path_str) {
+        download_cached(&path_str, cache, offline).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Failed to download {}", path_str),
+            )
+        })
+    } else {
+        fs::read(path)
+    }
+}
+
+/// Stores the values of a front matter block as variables with their dotted keys.
+/// Metadata and custom attributes are additionally stored with just their names.
+fn flatten_front_matter(
+    value: &toml::Value,
+    prefix: Option<&str>,
+    variables: &mut HashMap<String, String>,
+) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = key.to_lowercase();
+                let path = prefix
+                    .map(|p| format!("{}.{}", p, key))
+                    .unwrap_or_else(|| key.clone());
+                flatten_front_matter(value, Some(&path), variables);
+
+                if let Some("metadata") | Some("custom_attributes") = prefix {
+                    flatten_front_matter(value, Some(&key), variables);
+                }
+            }
+        }
+        toml::Value::String(s) => {
+            if let Some(prefix) = prefix {
+                variables.insert(prefix.to_string(), s.clone());
+            }
+        }
+        value => {
+            if let Some(prefix) = prefix {
+                variables.insert(prefix.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// Returns if the given path contains glob wildcards
+fn is_glob_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(&['*', '?'][..])
+}
+
+/// Returns if the file is a BibTeX or BibLaTeX bibliography
+fn is_bibtex_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("bib"))
+        .unwrap_or(false)
+}
+
+/// Returns the type of an import either from the given type argument
+/// or guessed from the file name
+fn get_import_type(path: &Path, args: &HashMap<String, String>) -> &'static str {
+    lazy_static::lazy_static! {
+        static ref BIB_NAME: Regex = Regex::new(r".*\.bib\.toml$").unwrap();
+    }
+    match args.get("type").map(|t| t.as_str()) {
+        Some(I_STYLESHEET) => I_STYLESHEET,
+        Some(I_DOCUMENT) => I_DOCUMENT,
+        Some(I_BIBLIOGRAPHY) => I_BIBLIOGRAPHY,
+        Some(I_MANIFEST) | Some("config") => I_MANIFEST,
+        Some(I_GLOSSARY) => I_GLOSSARY,
+        _ => {
+            if let Some(fname) = path.file_name().and_then(|f| f.to_str()) {
+                if BIB_NAME.is_match(fname) {
+                    return I_BIBLIOGRAPHY;
+                }
+            }
+            if is_bibtex_file(path) {
+                return I_BIBLIOGRAPHY;
+            }
+            match path.extension().map(|e| e.to_str().unwrap().to_lowercase()) {
+                Some(e) if e == "css" => I_STYLESHEET,
+                Some(e) if e == "toml" => I_MANIFEST,
+                _ => I_DOCUMENT,
+            }
+        }
+    }
+}
+
 pub(crate) enum ImportType {
     Document(ParseResult<Arc<RwLock<ImportAnchor>>>),
     Stylesheet(ParseResult<()>),
@@ -416,3 +986,679 @@ pub(crate) enum ImportType {
     Glossary(ParseResult<()>),
     None,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::elements::{Block, Inline, Line, Section};
+    use crate::parser::{Parser, ParserOptions};
+    use crate::utils::testing::{
+        parse_test_files, parse_test_files_with_options, render_html, write_test_files,
+    };
+    use std::fs;
+
+    fn get_sections(elements: &[Block]) -> Vec<&Section> {
+        elements
+            .iter()
+            .filter_map(|b| match b {
+                Block::Section(s) => Some(s),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_closes_sections_for_same_level_imports() {
+        let document = parse_test_files(
+            "same-level-import",
+            &[
+                ("main.sd", "# Title\n\nIntro\n\n<[ch1.sd]\n<[ch2.sd]\n"),
+                ("ch1.sd", "# Chapter 1\n\n## Part\n\nText\n"),
+                (
+                    "ch2.sd",
+                    "```\n# not a header\n```\n\n# Chapter 2\n\nText\n",
+                ),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(sections.len(), 3);
+        assert!(sections.iter().all(|s| s.header.size == 1));
+        assert_eq!(get_sections(&sections[0].elements).len(), 0);
+        assert_eq!(get_sections(&sections[1].elements).len(), 1);
+    }
+
+    #[test]
+    fn it_nests_deeper_imports() {
+        let document = parse_test_files(
+            "nested-import",
+            &[
+                ("main.sd", "# Title\n\n<[ch1.sd]\n"),
+                ("ch1.sd", "## Chapter 1\n\nText\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(get_sections(&sections[0].elements).len(), 1);
+    }
+
+    #[test]
+    fn it_keeps_content_after_imported_sections() {
+        let document = parse_test_files(
+            "import-content-after",
+            &[
+                ("main.sd", "# Title\n\n<[ch1.sd]\nAfter\n\n# Next\n"),
+                ("ch1.sd", "## Chapter 1\n\n### Part\n\nText\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(sections.len(), 2);
+        let chapter = get_sections(&sections[0].elements)[0];
+        let part = get_sections(&chapter.elements)[0];
+        assert_eq!(part.elements.len(), 2);
+        assert_eq!(get_sections(&sections[1].elements).len(), 0);
+    }
+
+    #[test]
+    fn it_shifts_imported_headers() {
+        let document = parse_test_files(
+            "import-shift",
+            &[
+                ("main.sd", "# Title\n\n<[ch1.sd][shift=1]\n\n## Chapter 2\n"),
+                ("ch1.sd", "# Chapter 1\n\n## Part\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(sections.len(), 1);
+        let chapters = get_sections(&sections[0].elements);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].header.size, 2);
+        assert_eq!(get_sections(&chapters[0].elements)[0].header.size, 3);
+    }
+
+    #[test]
+    fn it_shifts_imported_headers_relative_to_the_section() {
+        let document = parse_test_files(
+            "import-shift-auto",
+            &[
+                (
+                    "main.sd",
+                    "# Title\n\n## Chapter\n\n<[part.sd][shift=auto]\n",
+                ),
+                ("part.sd", "# Part\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        let chapter = get_sections(&sections[0].elements)[0];
+        let parts = get_sections(&chapter.elements);
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].header.size, 3);
+    }
+
+    fn get_titles(sections: &[&Section]) -> Vec<String> {
+        sections
+            .iter()
+            .map(|sec| match &sec.header.line {
+                Line::Text(text) => text
+                    .subtext
+                    .iter()
+                    .map(|inline| match inline {
+                        Inline::Plain(plain) => plain.value.clone(),
+                        _ => String::new(),
+                    })
+                    .collect(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_imports_directories_in_natural_order() {
+        let document = parse_test_files(
+            "import-directory",
+            &[
+                ("main.sd", "<[chapters]\n"),
+                ("chapters/ch10.sd", "# Ten\n"),
+                ("chapters/ch2.sd", "# Two\n"),
+                ("chapters/ch1.sd", "# One\n"),
+                ("chapters/notes.txt", "# Notes\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(get_titles(&sections), vec!["One", "Two", "Ten"]);
+    }
+
+    #[test]
+    fn it_imports_glob_matches_in_the_given_order() {
+        let document = parse_test_files(
+            "import-glob-order",
+            &[
+                ("main.sd", "<[chapters/ch*.sd][order=\"ch10.sd, ch2.sd\"]\n"),
+                ("chapters/ch1.sd", "# One\n"),
+                ("chapters/ch2.sd", "# Two\n"),
+                ("chapters/ch10.sd", "# Ten\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(get_titles(&sections), vec!["Ten", "Two", "One"]);
+    }
+
+    #[test]
+    fn it_skips_the_importing_document_in_glob_matches() {
+        let document = parse_test_files(
+            "import-glob-self",
+            &[
+                ("main.sd", "<[*.sd]\n"),
+                ("b.sd", "# B\n"),
+                ("a.sd", "# A\n"),
+            ],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(get_titles(&sections), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn it_merges_toml_front_matter_into_the_settings() {
+        let document = parse_test_files(
+            "front-matter-toml",
+            &[(
+                "main.sd",
+                "+++\n[metadata]\ntitle = \"Front\"\n\n[custom_attributes]\nedition = \"student\"\n+++\n\n# Text\n",
+            )],
+        );
+        let config = document.config.lock();
+        assert_eq!(config.metadata.title.as_deref(), Some("Front"));
+        assert_eq!(config.custom_attributes["edition"], "student");
+        assert_eq!(get_titles(&get_sections(&document.elements)), vec!["Text"]);
+    }
+
+    #[test]
+    fn it_merges_yaml_front_matter_into_the_settings() {
+        let document = parse_test_files(
+            "front-matter-yaml",
+            &[(
+                "main.sd",
+                "---\nmetadata:\n  title: Yaml\n  author: Someone\n---\n\n# Text\n",
+            )],
+        );
+        let config = document.config.lock();
+        assert_eq!(config.metadata.title.as_deref(), Some("Yaml"));
+        assert_eq!(config.metadata.author.as_deref(), Some("Someone"));
+        assert_eq!(get_titles(&get_sections(&document.elements)), vec!["Text"]);
+    }
+
+    #[test]
+    fn it_applies_front_matter_between_the_manifest_and_overrides() {
+        let files = [
+            (
+                "main.sd",
+                "+++\n[metadata]\ntitle = \"Front\"\nauthor = \"Front\"\n+++\n",
+            ),
+            (
+                "Manifest.toml",
+                "[metadata]\ntitle = \"Manifest\"\nauthor = \"Manifest\"\ndescription = \"Manifest\"\n",
+            ),
+        ];
+        let document =
+            parse_test_files_with_options("front-matter-precedence", &files, |options| {
+                options.add_setting_override("metadata.author".to_string(), "Set".to_string())
+            });
+        let config = document.config.lock();
+        assert_eq!(config.metadata.description.as_deref(), Some("Manifest"));
+        assert_eq!(config.metadata.title.as_deref(), Some("Front"));
+        assert_eq!(config.metadata.author.as_deref(), Some("Set"));
+    }
+
+    #[test]
+    fn it_stores_the_front_matter_of_imports_as_variables() {
+        let document = parse_test_files(
+            "front-matter-import",
+            &[
+                ("main.sd", "<[chapter.sd]\n"),
+                (
+                    "chapter.sd",
+                    "+++\n[metadata]\ntitle = \"Chapter\"\n+++\n\
+                    [[if]][title=\"Chapter\", metadata.title=\"Chapter\"]\n# Included\n[[endif]]\n",
+                ),
+            ],
+        );
+        assert_eq!(document.config.lock().metadata.title, None);
+        assert_eq!(
+            get_titles(&get_sections(&document.elements)),
+            vec!["Included"]
+        );
+    }
+
+    #[test]
+    fn it_ignores_front_matter_that_is_not_at_the_start() {
+        let document = parse_test_files(
+            "front-matter-position",
+            &[
+                (
+                    "main.sd",
+                    "# Text\n\n+++\n[metadata]\ntitle = \"Front\"\n+++\n",
+                ),
+                ("unclosed.sd", ""),
+            ],
+        );
+        assert_eq!(document.config.lock().metadata.title, None);
+
+        let document = parse_test_files(
+            "front-matter-unclosed",
+            &[("main.sd", "+++\n[metadata]\ntitle = \"Front\"\n")],
+        );
+        assert_eq!(document.config.lock().metadata.title, None);
+    }
+
+    fn get_anchors(sections: &[&Section]) -> Vec<String> {
+        sections
+            .iter()
+            .flat_map(|s| {
+                let mut anchors = vec![s.header.anchor.clone()];
+                anchors.append(&mut get_anchors(&get_sections(&s.elements)));
+                anchors
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_uses_slugified_header_anchors() {
+        let document = parse_test_files(
+            "header-anchors",
+            &[(
+                "main.sd",
+                "# Hello, World!\n\n## Sub Section_Name\n\n# Äpfel und  Birnen\n\n# ???\n",
+            )],
+        );
+        assert_eq!(
+            get_anchors(&get_sections(&document.elements)),
+            vec![
+                "hello-world",
+                "sub-section-name",
+                "äpfel-und-birnen",
+                "section"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_uses_explicit_header_anchors() {
+        let document = parse_test_files(
+            "header-anchors-explicit",
+            &[(
+                "main.sd",
+                "# Introduction {#start}\n\n# Usage { #not-an-anchor }\n",
+            )],
+        );
+        let sections = get_sections(&document.elements);
+        assert_eq!(get_anchors(&sections), vec!["start", "usage-not-an-anchor"]);
+        assert_eq!(
+            get_titles(&sections),
+            vec!["Introduction", "Usage { #not-an-anchor }"]
+        );
+        assert!(sections[0].header.custom_anchor);
+        assert!(!sections[1].header.custom_anchor);
+    }
+
+    #[test]
+    fn it_makes_generated_header_anchors_unique() {
+        let document = parse_test_files(
+            "header-anchors-unique",
+            &[(
+                "main.sd",
+                "# Setup\n\n## Usage\n\n# Usage\n\n# Setup\n\n# Other {#usage-1}\n",
+            )],
+        );
+        assert_eq!(
+            get_anchors(&get_sections(&document.elements)),
+            vec!["setup", "usage", "usage-2", "setup-1", "usage-1"]
+        );
+    }
+
+    #[test]
+    fn it_uses_legacy_header_anchors_when_enabled() {
+        let document = parse_test_files_with_options(
+            "header-anchors-legacy",
+            &[(
+                "main.sd",
+                "# Setup Guide\n\n## First Steps\n\n# Setup Guide\n",
+            )],
+            |options| {
+                options.add_setting_override(
+                    "features.legacy_header_anchors".to_string(),
+                    "true".to_string(),
+                )
+            },
+        );
+        let anchors = get_anchors(&get_sections(&document.elements));
+        assert_eq!(anchors[0], anchors[2]);
+        assert!(anchors[1].starts_with(&anchors[0]));
+        assert!(anchors.iter().all(|a| !a.contains(' ')));
+    }
+
+    fn get_numbers(sections: &[&Section]) -> Vec<Option<String>> {
+        sections
+            .iter()
+            .flat_map(|s| {
+                let mut numbers = vec![s.header.number.clone()];
+                numbers.append(&mut get_numbers(&get_sections(&s.elements)));
+                numbers
+            })
+            .collect()
+    }
+
+    fn get_plain_text(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Plain(plain) => plain.value.clone(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_numbers_headings_when_enabled() {
+        let document = parse_test_files_with_options(
+            "numbered-headings",
+            &[(
+                "main.sd",
+                "# Intro\n\n## Setup\n\n## Usage\n\n#[toc-hidden] Hidden\n\n# End\n\n## Summary\n",
+            )],
+            |options| {
+                options.add_setting_override(
+                    "features.numbered_headings".to_string(),
+                    "true".to_string(),
+                )
+            },
+        );
+        let numbers = get_numbers(&get_sections(&document.elements));
+        let numbers: Vec<Option<&str>> = numbers.iter().map(|n| n.as_deref()).collect();
+        assert_eq!(
+            numbers,
+            vec![
+                Some("1"),
+                Some("1.1"),
+                Some("1.2"),
+                None,
+                Some("2"),
+                Some("2.1")
+            ]
+        );
+    }
+
+    #[test]
+    fn it_numbers_sections_with_the_numbered_metadata() {
+        let document = parse_test_files(
+            "numbered-headings-metadata",
+            &[(
+                "main.sd",
+                "# Preface\n\n#[numbered] Appendix\n\n## Details\n\n###[numbered=false] Notes\n",
+            )],
+        );
+        let numbers = get_numbers(&get_sections(&document.elements));
+        let numbers: Vec<Option<&str>> = numbers.iter().map(|n| n.as_deref()).collect();
+        assert_eq!(numbers, vec![None, Some("1"), Some("1.1"), None]);
+    }
+
+    #[test]
+    fn it_shows_section_numbers_in_the_toc_and_links() {
+        let document = parse_test_files_with_options(
+            "numbered-headings-links",
+            &[(
+                "main.sd",
+                "# Intro\n\n## Setup\n\nSee [](#setup) or [the setup](#setup).\n",
+            )],
+            |options| {
+                options.add_setting_override(
+                    "features.numbered_headings".to_string(),
+                    "true".to_string(),
+                )
+            },
+        );
+        let intro = get_sections(&document.elements)[0];
+        let setup = get_sections(&intro.elements)[0];
+        assert_eq!(
+            get_plain_text(&setup.header.get_anchor().description.subtext),
+            "1.1 Setup"
+        );
+
+        let descriptions: Vec<String> = setup
+            .elements
+            .iter()
+            .filter_map(|b| match b {
+                Block::Paragraph(par) => Some(&par.elements),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|l| match l {
+                Line::Text(text) => Some(&text.subtext),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|i| match i {
+                Inline::Url(url) => url.description.as_deref().map(get_plain_text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(descriptions, vec!["1.1 Setup", "the setup"]);
+    }
+
+    /// Returns the text of all paragraphs in the given blocks
+    fn get_text(elements: &[Block]) -> String {
+        elements
+            .iter()
+            .map(|block| match block {
+                Block::Section(sec) => get_text(&sec.elements),
+                Block::Paragraph(par) => par
+                    .elements
+                    .iter()
+                    .map(|line| match line {
+                        Line::Text(text) => text
+                            .subtext
+                            .iter()
+                            .map(|inline| match inline {
+                                Inline::Plain(plain) => plain.value.clone(),
+                                _ => " ".to_string(),
+                            })
+                            .collect(),
+                        _ => String::new(),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                _ => String::new(),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn it_removes_excluded_conditional_content() {
+        let document = parse_test_files(
+            "conditional-text",
+            &[(
+                "main.sd",
+                "Intro\n  [[if]][solutions]\nSolution\n  [[endif]]\nOutro\n",
+            )],
+        );
+        let text = get_text(&document.elements);
+        assert!(text.contains("Intro"));
+        assert!(text.contains("Outro"));
+        assert!(!text.contains("Solution"));
+        assert!(!text.contains("[["));
+    }
+
+    #[test]
+    fn it_evaluates_else_and_nested_conditionals() {
+        let document = parse_test_files_with_options(
+            "conditional-nested",
+            &[(
+                "main.sd",
+                "[[if]][edition=\"student\"]\nStudent\n[[if]][solutions]\nSolution\n[[endif]]\n\
+                [[else]]\nInstructor\n[[if]][solutions]\nAnswer\n[[else]]\nNoAnswer\n[[endif]]\n\
+                [[endif]]\nEnd\n",
+            )],
+            |options| {
+                options
+                    .add_define("edition".to_string(), "instructor".to_string())
+                    .add_define("solutions".to_string(), "true".to_string())
+            },
+        );
+        let text = get_text(&document.elements);
+        assert!(!text.contains("Student"));
+        assert!(!text.contains("Solution"));
+        assert!(text.contains("Instructor"));
+        assert!(text.contains("Answer"));
+        assert!(!text.contains("NoAnswer"));
+        assert!(text.contains("End"));
+    }
+
+    #[test]
+    fn it_drops_the_rest_of_unterminated_conditionals() {
+        let document = parse_test_files(
+            "conditional-unterminated",
+            &[("main.sd", "Intro\n[[if]][missing]\nHidden\n")],
+        );
+        let text = get_text(&document.elements);
+        assert!(text.contains("Intro"));
+        assert!(!text.contains("Hidden"));
+    }
+
+    #[test]
+    fn it_prefers_defines_over_the_manifest() {
+        let files = [
+            (
+                "main.sd",
+                "[[if]][edition=\"student\"]\nStudent\n[[else]]\nInstructor\n[[endif]]\n",
+            ),
+            (
+                "Manifest.toml",
+                "[custom_attributes]\nedition = \"student\"\n",
+            ),
+        ];
+        let text = get_text(&parse_test_files("conditional-manifest", &files).elements);
+        assert!(text.contains("Student"));
+        assert!(!text.contains("Instructor"));
+
+        let document = parse_test_files_with_options("conditional-define", &files, |options| {
+            options.add_define("edition".to_string(), "instructor".to_string())
+        });
+        let text = get_text(&document.elements);
+        assert!(!text.contains("Student"));
+        assert!(text.contains("Instructor"));
+    }
+
+    #[test]
+    fn it_imports_documents_multiple_times() {
+        let document = parse_test_files(
+            "import-twice",
+            &[
+                ("main.sd", "<[part.sd]\n\n<[part.sd]\n"),
+                ("part.sd", "Part\n"),
+            ],
+        );
+        assert_eq!(get_text(&document.elements).matches("Part").count(), 2);
+    }
+
+    #[test]
+    fn it_imports_documents_once() {
+        for options in &[
+            "[once]",
+            "[once, shift=1]",
+            "[once=True]",
+            "[once=\"true\"]",
+        ] {
+            let main = format!("<[part.sd]\n\n<[part.sd]{}\n", options);
+            let document = parse_test_files(
+                "import-once",
+                &[("main.sd", main.as_str()), ("part.sd", "Part\n")],
+            );
+            assert_eq!(
+                get_text(&document.elements).matches("Part").count(),
+                1,
+                "{}",
+                options
+            );
+        }
+        let document = parse_test_files(
+            "import-once-invalid",
+            &[
+                ("main.sd", "<[part.sd]\n\n<[part.sd][once=maybe]\n"),
+                ("part.sd", "Part\n"),
+            ],
+        );
+        assert_eq!(get_text(&document.elements).matches("Part").count(), 2);
+    }
+
+    #[test]
+    fn it_detects_import_cycles() {
+        let document = parse_test_files(
+            "import-cycle",
+            &[
+                ("main.sd", "Main\n\n<[a.sd]\n"),
+                ("a.sd", "A\n\n<[b.sd]\n"),
+                ("b.sd", "B\n\n<[a.sd]\n\n<[main.sd]\n"),
+            ],
+        );
+        let text = get_text(&document.elements);
+        assert_eq!(text.matches("Main").count(), 1);
+        assert_eq!(text.matches('A').count(), 1);
+        assert_eq!(text.matches('B').count(), 1);
+    }
+
+    #[test]
+    fn it_prefers_import_variables_over_placeholder_values() {
+        let document = parse_test_files(
+            "import-variables",
+            &[
+                (
+                    "main.sd",
+                    "Book: [[title]]\n\n<[chapter.sd][title=\"One\"]\n\n<[chapter.sd][title=\"Two\"]\n",
+                ),
+                ("chapter.sd", "Chapter: [[title]] by [[author]]\n"),
+                (
+                    "Manifest.toml",
+                    "[metadata]\ntitle = \"Manual\"\nauthor = \"Someone\"\n",
+                ),
+            ],
+        );
+        let html = render_html(&document);
+        assert!(html.contains("Book: Manual"));
+        assert!(html.contains("Chapter: One by Someone"));
+        assert!(html.contains("Chapter: Two by Someone"));
+    }
+
+    #[test]
+    fn it_applies_settings_in_order_of_precedence() {
+        let dir = write_test_files(
+            "settings-precedence",
+            &[
+                ("main.sd", "Text\n"),
+                (
+                    "Manifest.toml",
+                    "[custom_attributes]\nprec_a = \"manifest\"\nprec_b = \"manifest\"\n\
+                    prec_c = \"manifest\"\nprec_d = \"manifest\"\n",
+                ),
+                (
+                    "config.toml",
+                    "[custom_attributes]\nprec_b = \"config\"\nprec_c = \"config\"\nprec_d = \"config\"\n",
+                ),
+            ],
+        );
+        std::env::set_var("SNEKDOWN_CUSTOM_ATTRIBUTES__PREC_C", "env");
+        std::env::set_var("SNEKDOWN_CUSTOM_ATTRIBUTES__PREC_D", "env");
+        let options = ParserOptions::default()
+            .add_path(dir.join("main.sd"))
+            .add_config_file(dir.join("config.toml"))
+            .add_setting_override("custom_attributes.prec_d".to_string(), "set".to_string());
+        let document = Parser::with_defaults(options).parse();
+        std::env::remove_var("SNEKDOWN_CUSTOM_ATTRIBUTES__PREC_C");
+        std::env::remove_var("SNEKDOWN_CUSTOM_ATTRIBUTES__PREC_D");
+        let _ = fs::remove_dir_all(&dir);
+
+        let config = document.config.lock();
+        let attributes = &config.custom_attributes;
+        assert_eq!(attributes["prec_a"], "manifest");
+        assert_eq!(attributes["prec_b"], "config");
+        assert_eq!(attributes["prec_c"], "env");
+        assert_eq!(attributes["prec_d"], "set");
+    }
+}
@@ -6,6 +6,10 @@
 
 use crate::elements::{Anchor, BoldText, ItalicText, Line, List, ListItem, PlainText, TextLine};
 use crate::elements::{Inline, Url};
+use crate::references::citation_styles::{get_styled_item, sort_bib_entries, BibFields};
+use crate::settings::style_settings::{CitationStyle, StyleSettings};
+use crate::utils::dates::DateFormatter;
+use crate::utils::localisation::*;
 use bibliographix::bibliography::bib_types::article::Article;
 use bibliographix::bibliography::bib_types::book::Book;
 use bibliographix::bibliography::bib_types::booklet::Booklet;
@@ -22,78 +26,185 @@ use bibliographix::bibliography::bib_types::BibliographyType;
 use bibliographix::bibliography::bibliography_entry::{
     BibliographyEntry, BibliographyEntryReference,
 };
+use std::collections::{BTreeMap, HashMap};
 
-const DATE_FORMAT: &str = "%d.%m.%Y";
 use crate::bold_text;
 use crate::italic_text;
 use crate::list_item;
 use crate::plain_text;
 use crate::url_text;
 
+/// The positions bibliography entries are defined at
+#[derive(Clone, Debug, Default)]
+pub struct BibEntryPositions {
+    positions: BTreeMap<String, Vec<String>>,
+}
+
+impl BibEntryPositions {
+    /// Adds a position the entry with the given key is defined at.
+    /// Positions that have already been added are ignored
+    /// as the parser can read the same definition again when it backtracks
+    pub fn add(&mut self, key: String, position: String) {
+        let positions = self.positions.entry(key).or_default();
+
+        if !positions.contains(&position) {
+            positions.push(position);
+        }
+    }
+
+    /// Returns the keys and positions of all defined entries
+    pub fn get_entries(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.positions
+    }
+}
+
+/// Sorts the entries and assigns the number, the short author and the year
+/// used by references in the text
+pub fn assign_citation_fields(
+    mut entries: Vec<BibliographyEntryReference>,
+    style: &StyleSettings,
+    l10n: &Localisation,
+) {
+    sort_bib_entries(&mut entries, &style.get_bib_sorting());
+
+    for (index, entry) in entries.iter().enumerate() {
+        let mut entry = entry.lock();
+        let fields = BibFields::from_entry(&entry);
+        entry
+            .raw_fields
+            .insert("ord".to_string(), (index + 1).to_string());
+        entry
+            .raw_fields
+            .insert("cite_author".to_string(), fields.get_cite_author(l10n));
+        entry
+            .raw_fields
+            .insert("year".to_string(), fields.get_year(l10n));
+    }
+}
+
 /// Creates a list from a list of bib items
-pub fn create_bib_list(entries: Vec<BibliographyEntryReference>) -> List {
+pub fn create_bib_list(
+    mut entries: Vec<BibliographyEntryReference>,
+    style: &StyleSettings,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> List {
     let mut list = List::new();
-    list.ordered = true;
+    list.ordered = style.citation_style.is_numbered();
+    sort_bib_entries(&mut entries, &style.get_bib_sorting());
 
-    let mut count = 1;
     for entry in entries {
-        entry
-            .lock()
-            .raw_fields
-            .insert("ord".to_string(), count.to_string());
-        list.add_item(get_item_for_entry(entry));
-        count += 1;
+        list.add_item(get_item_for_entry(
+            entry,
+            &style.citation_style,
+            dates,
+            l10n,
+        ));
     }
 
     list
 }
 
+/// Prefixes the anchors of the list items with the given scope and
+/// appends links to the citations of every entry that has back references
+pub fn add_bib_list_links(
+    list: &mut List,
+    scope: Option<&str>,
+    back_references: &HashMap<String, Vec<String>>,
+    l10n: &Localisation,
+) {
+    for item in &mut list.items {
+        if let Line::Anchor(anchor) = &mut item.text {
+            if let (Some(ids), Line::Text(text)) =
+                (back_references.get(&anchor.key), &mut *anchor.inner)
+            {
+                text.subtext
+                    .push(plain_text!(format!(" {}: ", l10n.get(L_BIB_CITED_IN))));
+                for (index, id) in ids.iter().enumerate() {
+                    if index > 0 {
+                        text.subtext.push(plain_text!(", ".to_string()));
+                    }
+                    text.subtext.push(Inline::Url(Url::new(
+                        Some(vec![plain_text!(format!("↑{}", index + 1))]),
+                        format!("#{}", id),
+                    )));
+                }
+            }
+            if let Some(scope) = scope {
+                anchor.key = format!("{}-{}", scope, anchor.key);
+            }
+        }
+    }
+}
+
 /// Returns the list item for a bib entry
-fn get_item_for_entry(entry: BibliographyEntryReference) -> ListItem {
+fn get_item_for_entry(
+    entry: BibliographyEntryReference,
+    style: &CitationStyle,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let entry = entry.lock();
 
+    if *style != CitationStyle::Numeric {
+        return get_styled_item(&entry, style, dates, l10n);
+    }
+
     match &entry.bib_type {
-        BibliographyType::Article(a) => get_item_for_article(&*entry, a),
-        BibliographyType::Book(b) => get_item_for_book(&*entry, b),
-        BibliographyType::Booklet(b) => get_item_for_booklet(&*entry, b),
-        BibliographyType::InBook(ib) => get_item_for_in_book(&*entry, ib),
-        BibliographyType::InCollection(ic) => get_item_for_in_collection(&*entry, ic),
-        BibliographyType::Manual(m) => get_item_for_manual(&*entry, m),
-        BibliographyType::Misc(m) => get_item_for_misc(&*entry, m),
-        BibliographyType::Repository(r) => get_item_for_repository(&*entry, r),
-        BibliographyType::TechReport(tr) => get_item_for_tech_report(&*entry, tr),
-        BibliographyType::Thesis(t) => get_item_for_thesis(&*entry, t),
-        BibliographyType::Unpublished(u) => get_item_for_unpublished(&*entry, u),
-        BibliographyType::Website(w) => get_item_for_website(&*entry, w),
+        BibliographyType::Article(a) => get_item_for_article(&entry, a, dates, l10n),
+        BibliographyType::Book(b) => get_item_for_book(&entry, b, dates, l10n),
+        BibliographyType::Booklet(b) => get_item_for_booklet(&entry, b, dates, l10n),
+        BibliographyType::InBook(ib) => get_item_for_in_book(&entry, ib, l10n),
+        BibliographyType::InCollection(ic) => get_item_for_in_collection(&entry, ic, l10n),
+        BibliographyType::Manual(m) => get_item_for_manual(&entry, m, dates, l10n),
+        BibliographyType::Misc(m) => get_item_for_misc(&entry, m, dates, l10n),
+        BibliographyType::Repository(r) => get_item_for_repository(&entry, r, dates, l10n),
+        BibliographyType::TechReport(tr) => get_item_for_tech_report(&entry, tr, dates, l10n),
+        BibliographyType::Thesis(t) => get_item_for_thesis(&entry, t, dates, l10n),
+        BibliographyType::Unpublished(u) => get_item_for_unpublished(&entry, u, dates, l10n),
+        BibliographyType::Website(w) => get_item_for_website(&entry, w, dates, l10n),
     }
 }
 
 /// Returns the formatted article bib entry
-fn get_item_for_article(entry: &BibliographyEntry, a: &Article) -> ListItem {
+fn get_item_for_article(
+    entry: &BibliographyEntry,
+    a: &Article,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
     text.subtext
         .push(plain_text!(format!("{}.", a.author.clone())));
     text.subtext
         .push(plain_text!(format!("\"{}\"", a.title.clone())));
-    text.subtext.push(plain_text!("In: ".to_string()));
+    text.subtext
+        .push(plain_text!(format!("{}: ", l10n.get(L_BIB_IN))));
     text.subtext.push(italic_text!(a.journal.clone()));
 
     if let Some(volume) = a.volume.clone() {
         text.subtext.push(italic_text!(format!(", {}", volume)))
     }
     if let Some(number) = a.number.clone() {
-        text.subtext
-            .push(plain_text!(format!(", Number: {}", number)));
+        text.subtext.push(plain_text!(format!(
+            ", {}: {}",
+            l10n.get(L_BIB_NUMBER),
+            number
+        )));
     }
     text.subtext
-        .push(plain_text!(format!(", {}", a.date.format(DATE_FORMAT))));
+        .push(plain_text!(format!(", {}", dates.format_date(&a.date))));
 
     if let Some(pages) = a.pages.clone() {
-        text.subtext
-            .push(plain_text!(format!(", Pages: {}", pages)));
+        text.subtext.push(plain_text!(format!(
+            ", {}: {}",
+            l10n.get(L_BIB_PAGES),
+            pages
+        )));
     }
     if let Some(url) = a.url.clone() {
-        text.subtext.push(plain_text!(", URL: ".to_string()));
+        text.subtext
+            .push(plain_text!(format!(", {}: ", l10n.get(L_BIB_URL))));
         text.subtext.push(url_text!(url));
     }
     if let Some(notes) = entry.note.clone() {
@@ -104,7 +215,12 @@ fn get_item_for_article(entry: &BibliographyEntry, a: &Article) -> ListItem {
 }
 
 /// Returns a list item for a book entry
-fn get_item_for_book(entry: &BibliographyEntry, b: &Book) -> ListItem {
+fn get_item_for_book(
+    entry: &BibliographyEntry,
+    b: &Book,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
     text.subtext
         .push(plain_text!(format!("{}.", b.author.clone())));
@@ -118,18 +234,23 @@ fn get_item_for_book(entry: &BibliographyEntry, b: &Book) -> ListItem {
         text.subtext.push(plain_text!(format!(", {}", edition)))
     }
     if let Some(series) = b.series.clone() {
-        text.subtext.push(plain_text!(format!("In: ")));
+        text.subtext
+            .push(plain_text!(format!("{}: ", l10n.get(L_BIB_IN))));
         text.subtext.push(italic_text!(series))
     }
     text.subtext.push(plain_text!(format!(
-        "Published By: {}",
+        "{}: {}",
+        l10n.get(L_BIB_PUBLISHED_BY),
         b.publisher.clone()
-    )
-    .to_string()));
-    text.subtext
-        .push(plain_text!(format!("on {}", b.date.format(DATE_FORMAT))));
+    )));
+    text.subtext.push(plain_text!(format!(
+        "{} {}",
+        l10n.get(L_BIB_ON),
+        dates.format_date(&b.date)
+    )));
     if let Some(url) = b.url.clone() {
-        text.subtext.push(plain_text!(", URL: ".to_string()));
+        text.subtext
+            .push(plain_text!(format!(", {}: ", l10n.get(L_BIB_URL))));
         text.subtext.push(url_text!(url));
     }
     if let Some(notes) = entry.note.clone() {
@@ -140,19 +261,34 @@ fn get_item_for_book(entry: &BibliographyEntry, b: &Book) -> ListItem {
 }
 
 /// Returns the list item for a booklet
-fn get_item_for_booklet(entry: &BibliographyEntry, b: &Booklet) -> ListItem {
+fn get_item_for_booklet(
+    entry: &BibliographyEntry,
+    b: &Booklet,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
     if let Some(author) = b.author.clone() {
         text.subtext.push(plain_text!(format!("{}. ", author)))
     }
-    text.subtext
-        .push(plain_text!(format!("\"{}\", Published ", b.title.clone())));
+    text.subtext.push(plain_text!(format!(
+        "\"{}\", {} ",
+        b.title.clone(),
+        l10n.get(L_BIB_PUBLISHED)
+    )));
     if let Some(how_pub) = b.how_published.clone() {
-        text.subtext.push(plain_text!(format!("as {} ", how_pub)))
+        text.subtext.push(plain_text!(format!(
+            "{} {} ",
+            l10n.get(L_BIB_PUBLISHED_AS),
+            how_pub
+        )))
     }
     if let Some(date) = b.date {
-        text.subtext
-            .push(plain_text!(format!("on {}", date.format(DATE_FORMAT))))
+        text.subtext.push(plain_text!(format!(
+            "{} {}",
+            l10n.get(L_BIB_ON),
+            dates.format_date(&date)
+        )))
     }
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
@@ -162,7 +298,7 @@ fn get_item_for_booklet(entry: &BibliographyEntry, b: &Booklet) -> ListItem {
 }
 
 /// Returns the list item for an in book bib entry
-fn get_item_for_in_book(entry: &BibliographyEntry, ib: &InBook) -> ListItem {
+fn get_item_for_in_book(entry: &BibliographyEntry, ib: &InBook, l10n: &Localisation) -> ListItem {
     let mut text = TextLine::new();
     text.subtext
         .push(plain_text!(format!("{}. ", ib.author.clone())));
@@ -178,11 +314,13 @@ fn get_item_for_in_book(entry: &BibliographyEntry, ib: &InBook) -> ListItem {
         text.subtext.push(plain_text!(format!(", {}", edition)))
     }
     if let Some(series) = ib.series.clone() {
-        text.subtext.push(plain_text!("In: ".to_string()));
+        text.subtext
+            .push(plain_text!(format!("{}: ", l10n.get(L_BIB_IN))));
         text.subtext.push(italic_text!(series))
     }
     text.subtext.push(plain_text!(format!(
-        ", Published By: {}",
+        ", {}: {}",
+        l10n.get(L_BIB_PUBLISHED_BY),
         ib.publisher.clone()
     )));
     if let Some(notes) = entry.note.clone() {
@@ -193,14 +331,21 @@ fn get_item_for_in_book(entry: &BibliographyEntry, ib: &InBook) -> ListItem {
 }
 
 /// Returns the list item for an InCollection bib entry
-fn get_item_for_in_collection(entry: &BibliographyEntry, ic: &InCollection) -> ListItem {
+fn get_item_for_in_collection(
+    entry: &BibliographyEntry,
+    ic: &InCollection,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
     text.subtext
         .push(plain_text!(format!("{}. ", ic.author.clone())));
 
     if let Some(editor) = ic.editor.clone() {
-        text.subtext
-            .push(plain_text!(format!("(Editor: {})", editor)))
+        text.subtext.push(plain_text!(format!(
+            "({}: {})",
+            l10n.get(L_BIB_EDITOR),
+            editor
+        )))
     }
     text.subtext
         .push(plain_text!(format!("\"{}\"", ic.title.clone())));
@@ -216,7 +361,8 @@ fn get_item_for_in_collection(entry: &BibliographyEntry, ic: &InCollection) -> L
         text.subtext.push(plain_text!(format!(", {}", edition)))
     }
     if let Some(series) = ic.series.clone() {
-        text.subtext.push(plain_text!("In: ".to_string()));
+        text.subtext
+            .push(plain_text!(format!("{}: ", l10n.get(L_BIB_IN))));
         text.subtext.push(italic_text!(series))
     }
     if let Some(notes) = entry.note.clone() {
@@ -227,7 +373,12 @@ fn get_item_for_in_collection(entry: &BibliographyEntry, ic: &InCollection) -> L
 }
 
 /// Returns the list item for a manual
-fn get_item_for_manual(entry: &BibliographyEntry, m: &Manual) -> ListItem {
+fn get_item_for_manual(
+    entry: &BibliographyEntry,
+    m: &Manual,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     if let Some(author) = m.author.clone() {
@@ -240,12 +391,18 @@ fn get_item_for_manual(entry: &BibliographyEntry, m: &Manual) -> ListItem {
         text.subtext.push(plain_text!(format!(", {}", edition)));
     }
     if let Some(organization) = m.organization.clone() {
-        text.subtext
-            .push(plain_text!(format!(", by {}", organization)))
+        text.subtext.push(plain_text!(format!(
+            ", {} {}",
+            l10n.get(L_BIB_BY),
+            organization
+        )))
     }
     if let Some(date) = m.date {
-        text.subtext
-            .push(plain_text!(format!(" on {}", date.format(DATE_FORMAT))))
+        text.subtext.push(plain_text!(format!(
+            " {} {}",
+            l10n.get(L_BIB_ON),
+            dates.format_date(&date)
+        )))
     }
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
@@ -255,7 +412,12 @@ fn get_item_for_manual(entry: &BibliographyEntry, m: &Manual) -> ListItem {
 }
 
 /// Returns the list item for a misc bib entry
-fn get_item_for_misc(entry: &BibliographyEntry, m: &Misc) -> ListItem {
+fn get_item_for_misc(
+    entry: &BibliographyEntry,
+    m: &Misc,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     if let Some(author) = m.author.clone() {
@@ -265,14 +427,22 @@ fn get_item_for_misc(entry: &BibliographyEntry, m: &Misc) -> ListItem {
         text.subtext.push(plain_text!(format!("\"{}\"", title)));
     }
     if let Some(how_pub) = m.how_published.clone() {
-        text.subtext.push(plain_text!(format!("as {} ", how_pub)))
+        text.subtext.push(plain_text!(format!(
+            "{} {} ",
+            l10n.get(L_BIB_PUBLISHED_AS),
+            how_pub
+        )))
     }
     if let Some(date) = m.date {
-        text.subtext
-            .push(plain_text!(format!("on {}", date.format(DATE_FORMAT))))
+        text.subtext.push(plain_text!(format!(
+            "{} {}",
+            l10n.get(L_BIB_ON),
+            dates.format_date(&date)
+        )))
     }
     if let Some(url) = m.url.clone() {
-        text.subtext.push(plain_text!(format!(", URL: {}", url)));
+        text.subtext
+            .push(plain_text!(format!(", {}: {}", l10n.get(L_BIB_URL), url)));
     }
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
@@ -282,26 +452,39 @@ fn get_item_for_misc(entry: &BibliographyEntry, m: &Misc) -> ListItem {
 }
 
 /// Returns a list item for a repository bib entry
-fn get_item_for_repository(entry: &BibliographyEntry, r: &Repository) -> ListItem {
+fn get_item_for_repository(
+    entry: &BibliographyEntry,
+    r: &Repository,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     text.subtext.push(italic_text!(r.title.clone()));
-    text.subtext
-        .push(plain_text!(format!(" by {}", r.author.clone())));
+    text.subtext.push(plain_text!(format!(
+        " {} {}",
+        l10n.get(L_BIB_BY),
+        r.author.clone()
+    )));
 
     if let Some(url) = r.url.clone() {
-        text.subtext.push(plain_text!(", URL: ".to_string()));
+        text.subtext
+            .push(plain_text!(format!(", {}: ", l10n.get(L_BIB_URL))));
         text.subtext.push(url_text!(url));
     }
     if let Some(accessed) = r.accessed_at.clone() {
         text.subtext.push(plain_text!(format!(
-            "(accessed: {})",
-            accessed.format(DATE_FORMAT)
+            "({}: {})",
+            l10n.get(L_BIB_ACCESSED),
+            dates.format_date(&accessed)
         )))
     }
     if let Some(license) = r.license.clone() {
-        text.subtext
-            .push(plain_text!(format!(", License: {}", license)))
+        text.subtext.push(plain_text!(format!(
+            ", {}: {}",
+            l10n.get(L_BIB_LICENSE),
+            license
+        )))
     }
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
@@ -311,17 +494,28 @@ fn get_item_for_repository(entry: &BibliographyEntry, r: &Repository) -> ListIte
 }
 
 /// Returns the list item for the tech report type
-fn get_item_for_tech_report(entry: &BibliographyEntry, tr: &TechReport) -> ListItem {
+fn get_item_for_tech_report(
+    entry: &BibliographyEntry,
+    tr: &TechReport,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     text.subtext
         .push(plain_text!(format!("{}. ", tr.author.clone())));
     text.subtext
         .push(plain_text!(format!("\"{}\"", tr.title.clone())));
-    text.subtext
-        .push(plain_text!(format!(" by {}", tr.institution.clone())));
-    text.subtext
-        .push(plain_text!(format!(" on {}", tr.date.format(DATE_FORMAT))));
+    text.subtext.push(plain_text!(format!(
+        " {} {}",
+        l10n.get(L_BIB_BY),
+        tr.institution.clone()
+    )));
+    text.subtext.push(plain_text!(format!(
+        " {} {}",
+        l10n.get(L_BIB_ON),
+        dates.format_date(&tr.date)
+    )));
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
     }
@@ -330,17 +524,28 @@ fn get_item_for_tech_report(entry: &BibliographyEntry, tr: &TechReport) -> ListI
 }
 
 /// Returns a list item for a thesis
-fn get_item_for_thesis(entry: &BibliographyEntry, t: &Thesis) -> ListItem {
+fn get_item_for_thesis(
+    entry: &BibliographyEntry,
+    t: &Thesis,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     text.subtext
         .push(plain_text!(format!("{}. ", t.author.clone())));
     text.subtext
         .push(plain_text!(format!("\"{}\" ", t.title.clone())));
-    text.subtext
-        .push(plain_text!(format!("at {}", t.school.clone())));
-    text.subtext
-        .push(plain_text!(format!(" on {}", t.date.format(DATE_FORMAT))));
+    text.subtext.push(plain_text!(format!(
+        "{} {}",
+        l10n.get(L_BIB_AT),
+        t.school.clone()
+    )));
+    text.subtext.push(plain_text!(format!(
+        " {} {}",
+        l10n.get(L_BIB_ON),
+        dates.format_date(&t.date)
+    )));
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
     }
@@ -349,7 +554,12 @@ fn get_item_for_thesis(entry: &BibliographyEntry, t: &Thesis) -> ListItem {
 }
 
 /// Returns the list item for an unpublished bib type
-fn get_item_for_unpublished(entry: &BibliographyEntry, u: &Unpublished) -> ListItem {
+fn get_item_for_unpublished(
+    entry: &BibliographyEntry,
+    u: &Unpublished,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     text.subtext
@@ -357,8 +567,11 @@ fn get_item_for_unpublished(entry: &BibliographyEntry, u: &Unpublished) -> ListI
     text.subtext
         .push(plain_text!(format!("\"{}\"", u.title.clone())));
     if let Some(date) = u.date.clone() {
-        text.subtext
-            .push(plain_text!(format!(" on {}", date.format(DATE_FORMAT))));
+        text.subtext.push(plain_text!(format!(
+            " {} {}",
+            l10n.get(L_BIB_ON),
+            dates.format_date(&date)
+        )));
     }
     if let Some(notes) = entry.note.clone() {
         text.subtext.push(plain_text!(notes))
@@ -367,7 +580,12 @@ fn get_item_for_unpublished(entry: &BibliographyEntry, u: &Unpublished) -> ListI
     list_item!(text, entry.key())
 }
 
-fn get_item_for_website(entry: &BibliographyEntry, w: &Website) -> ListItem {
+fn get_item_for_website(
+    entry: &BibliographyEntry,
+    w: &Website,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
     let mut text = TextLine::new();
 
     if let Some(title) = w.title.clone() {
@@ -375,18 +593,21 @@ fn get_item_for_website(entry: &BibliographyEntry, w: &Website) -> ListItem {
     }
     text.subtext.push(url_text!(w.url.clone()));
     if let Some(author) = w.author.clone() {
-        text.subtext.push(bold_text!(format!(" by {}", author)))
+        text.subtext
+            .push(bold_text!(format!(" {} {}", l10n.get(L_BIB_BY), author)))
     }
     if let Some(accessed) = w.accessed_at.clone() {
         text.subtext.push(plain_text!(format!(
-            "(accessed: {})",
-            accessed.format(DATE_FORMAT)
+            "({}: {})",
+            l10n.get(L_BIB_ACCESSED),
+            dates.format_date(&accessed)
         )))
     }
     if let Some(date) = w.date.clone() {
         text.subtext.push(plain_text!(format!(
-            ", Published On: {}",
-            date.format(DATE_FORMAT)
+            ", {}: {}",
+            l10n.get(L_BIB_PUBLISHED_ON),
+            dates.format_date(&date)
         )))
     }
     if let Some(notes) = entry.note.clone() {
@@ -0,0 +1,726 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use bibliographix::bibliography::bibliography_entry::BibliographyEntry;
+use bibliographix::bibliography::keys::*;
+use bibliographix::bibliography::FromHashMap;
+use std::collections::HashMap;
+
+/// Fields that are copied to the entry with the same name
+const COPIED_FIELDS: &[&str] = &[
+    K_AUTHOR,
+    K_TITLE,
+    K_PUBLISHER,
+    K_VOLUME,
+    K_URL,
+    K_SERIES,
+    K_ADDRESS,
+    K_JOURNAL,
+    K_EDITION,
+    K_NUMBER,
+    K_PAGES,
+    K_NOTE,
+    K_EDITOR,
+    K_ORGANIZATION,
+    K_INSTITUTION,
+    K_SCHOOL,
+    K_DATE,
+];
+
+/// Fields that are only used to build other fields
+const DATE_FIELDS: &[&str] = &["year", "month", "day"];
+
+const MONTHS: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// A single entry of a BibTeX file
+#[derive(Clone, Debug)]
+struct BibTexEntry {
+    entry_type: String,
+    key: String,
+    fields: Vec<(String, String, usize)>,
+    index: usize,
+}
+
+/// Parses BibTeX and BibLaTeX bibliography files
+pub struct BibTexParser {
+    text: Vec<char>,
+    index: usize,
+    file: String,
+    strings: HashMap<String, String>,
+}
+
+impl BibTexParser {
+    pub fn new(contents: &str, file: &str) -> Self {
+        let strings = MONTHS
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.to_string(), (i + 1).to_string()))
+            .collect();
+
+        Self {
+            text: contents.chars().collect(),
+            index: 0,
+            file: file.to_string(),
+            strings,
+        }
+    }
+
+    /// Parses the file and returns the bibliography entries with their positions.
+    /// Entries that can't be parsed and unsupported fields are reported as warnings.
+    pub fn parse(&mut self) -> Vec<(BibliographyEntry, String)> {
+        let mut entries = Vec::new();
+
+        while self.seek_to('@') {
+            let start_index = self.index;
+            self.index += 1;
+
+            match self.parse_item() {
+                Ok(Some(entry)) => {
+                    let key = entry.key.clone();
+                    let index = entry.index;
+
+                    if let Some(map) = self.convert_entry(entry) {
+                        match BibliographyEntry::from_hash_map(&map) {
+                            Ok(entry) => entries.push((*entry, self.get_position_string(index))),
+                            Err(e) => {
+                                log::warn!(
+                                    "Failed to import BibTeX entry \"{}\": {}\n\t--> {}\n",
+                                    key,
+                                    e,
+                                    self.get_position_string(index)
+                                );
+                            }
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(message) => {
+                    log::warn!(
+                        "Failed to parse BibTeX entry: {}\n\t--> {}\n",
+                        message,
+                        self.get_position_string(self.index)
+                    );
+                    self.index = start_index + 1;
+                }
+            }
+        }
+
+        entries
+    }
+
+    /// Parses an entry, string definition, comment or preamble after the `@`
+    fn parse_item(&mut self) -> Result<Option<BibTexEntry>, String> {
+        let index = self.index - 1;
+        let entry_type = self.parse_identifier().to_lowercase();
+        self.skip_whitespace();
+        let close = match self.current() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(format!("Expected '{{' after @{}", entry_type)),
+        };
+        self.index += 1;
+
+        match entry_type.as_str() {
+            "comment" | "preamble" => {
+                self.index -= 1;
+                self.parse_braced(close)?;
+                Ok(None)
+            }
+            "string" => {
+                self.skip_whitespace();
+                let (name, value, _) = self.parse_field()?;
+                self.strings.insert(name, value);
+                self.skip_whitespace();
+                self.expect(close)?;
+                Ok(None)
+            }
+            _ => {
+                self.skip_whitespace();
+                let key = self.parse_until(&[',', close]).trim().to_string();
+                let mut fields = Vec::new();
+
+                loop {
+                    self.skip_whitespace();
+                    match self.current() {
+                        Some(',') => self.index += 1,
+                        Some(c) if c == close => {
+                            self.index += 1;
+                            break;
+                        }
+                        Some(_) => fields.push(self.parse_field()?),
+                        None => return Err(format!("Unterminated entry \"{}\"", key)),
+                    }
+                }
+
+                Ok(Some(BibTexEntry {
+                    entry_type,
+                    key,
+                    fields,
+                    index,
+                }))
+            }
+        }
+    }
+
+    /// Parses a field in the form `name = value # value`
+    fn parse_field(&mut self) -> Result<(String, String, usize), String> {
+        let index = self.index;
+        let name = self.parse_identifier().to_lowercase();
+        if name.is_empty() {
+            return Err("Expected a field name".to_string());
+        }
+        self.skip_whitespace();
+        self.expect('=')?;
+        let mut value = String::new();
+
+        loop {
+            self.skip_whitespace();
+            match self.current() {
+                Some('{') => value.push_str(&self.parse_braced('}')?),
+                Some('"') => value.push_str(&self.parse_quoted()?),
+                Some(c) if c.is_ascii_digit() => value.push_str(&self.parse_identifier()),
+                Some(_) => {
+                    let macro_name = self.parse_identifier().to_lowercase();
+                    if macro_name.is_empty() {
+                        return Err(format!("Expected a value for the field \"{}\"", name));
+                    }
+                    if let Some(string) = self.strings.get(&macro_name) {
+                        value.push_str(string);
+                    } else {
+                        log::warn!(
+                            "Unknown string macro \"{}\"\n\t--> {}\n",
+                            macro_name,
+                            self.get_position_string(self.index)
+                        );
+                    }
+                }
+                None => return Err(format!("Expected a value for the field \"{}\"", name)),
+            }
+            self.skip_whitespace();
+            if self.current() == Some('#') {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+
+        Ok((name, decode_latex(&value), index))
+    }
+
+    /// Parses a value enclosed in braces with nested braces
+    fn parse_braced(&mut self, close: char) -> Result<String, String> {
+        let open = self.current().unwrap_or('{');
+        self.index += 1;
+        let mut depth = 0;
+        let mut value = String::new();
+
+        while let Some(c) = self.current() {
+            self.index += 1;
+            if c == close && depth == 0 {
+                return Ok(value);
+            }
+            if c == '{' || (c == open && open != close) {
+                depth += 1;
+            } else if c == '}' || c == close {
+                depth -= 1;
+            }
+            value.push(c);
+        }
+
+        Err("Unterminated value".to_string())
+    }
+
+    /// Parses a value enclosed in quotes which may contain braces
+    fn parse_quoted(&mut self) -> Result<String, String> {
+        self.index += 1;
+        let mut depth = 0;
+        let mut value = String::new();
+
+        while let Some(c) = self.current() {
+            self.index += 1;
+            match c {
+                '"' if depth == 0 => return Ok(value),
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            value.push(c);
+        }
+
+        Err("Unterminated value".to_string())
+    }
+
+    /// Parses a name of an entry type, field, string macro or a number
+    fn parse_identifier(&mut self) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.current() {
+            if c.is_whitespace() || "{}(),=#\"@".contains(c) {
+                break;
+            }
+            value.push(c);
+            self.index += 1;
+        }
+
+        value
+    }
+
+    /// Returns the text until one of the given characters
+    fn parse_until(&mut self, chars: &[char]) -> String {
+        let mut value = String::new();
+
+        while let Some(c) = self.current() {
+            if chars.contains(&c) {
+                break;
+            }
+            value.push(c);
+            self.index += 1;
+        }
+
+        value
+    }
+
+    /// Converts a parsed entry into a map of bibliography fields
+    fn convert_entry(&self, entry: BibTexEntry) -> Option<HashMap<String, String>> {
+        let bib_type = match get_bib_type(&entry.entry_type) {
+            Some(t) => t,
+            None => {
+                log::warn!(
+                    "Unsupported BibTeX entry type \"{}\"\n\t--> {}\n",
+                    entry.entry_type,
+                    self.get_position_string(entry.index)
+                );
+                return None;
+            }
+        };
+        let mut map = HashMap::new();
+        map.insert(K_KEY.to_string(), entry.key.clone());
+        map.insert(K_TYPE.to_string(), bib_type.to_string());
+        let mut date_parts = HashMap::new();
+
+        for (name, value, index) in entry.fields {
+            let name = match name.as_str() {
+                "journaltitle" => K_JOURNAL,
+                "howpublished" => K_HOW_PUBLISHED,
+                "urldate" => K_ACCESSED_AT,
+                "booktitle" if bib_type == T_IN_COLLECTION => K_SERIES,
+                "chapter" if bib_type == T_IN_BOOK => K_POSITION,
+                "location" => K_ADDRESS,
+                "institution" if bib_type == T_THESIS => K_SCHOOL,
+                "type" => continue,
+                "doi" => {
+                    map.entry(K_URL.to_string())
+                        .or_insert_with(|| format!("https://doi.org/{}", value));
+                    continue;
+                }
+                n if DATE_FIELDS.contains(&n) => {
+                    date_parts.insert(name, value);
+                    continue;
+                }
+                n if COPIED_FIELDS.contains(&n) => n,
+                _ => {
+                    log::warn!(
+                        "Unsupported BibTeX field \"{}\" in entry \"{}\"\n\t--> {}\n",
+                        name,
+                        entry.key,
+                        self.get_position_string(index)
+                    );
+                    continue;
+                }
+            };
+            map.insert(name.to_string(), value);
+        }
+        if !map.contains_key(K_DATE) {
+            if let Some(date) = get_date(&date_parts) {
+                map.insert(K_DATE.to_string(), date);
+            }
+        }
+        if bib_type == T_IN_BOOK && !map.contains_key(K_POSITION) {
+            if let Some(pages) = map.get(K_PAGES).cloned() {
+                map.insert(K_POSITION.to_string(), pages);
+            }
+        }
+        if bib_type == T_THESIS && !map.contains_key(K_SCHOOL) {
+            if let Some(institution) = map.get(K_INSTITUTION).cloned() {
+                map.insert(K_SCHOOL.to_string(), institution);
+            }
+        }
+
+        Some(map)
+    }
+
+    /// Returns the position of the given index as `file:line:column`
+    pub fn get_position_string(&self, index: usize) -> String {
+        let before = &self.text[..index.min(self.text.len())];
+        let line = before.iter().filter(|c| **c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|c| **c != '\n').count();
+
+        format!("{}:{}:{}", self.file, line, column)
+    }
+
+    fn current(&self) -> Option<char> {
+        self.text.get(self.index).cloned()
+    }
+
+    /// Moves to the next occurrence of the given char and returns if it was found
+    fn seek_to(&mut self, c: char) -> bool {
+        while let Some(current) = self.current() {
+            if current == c {
+                return true;
+            }
+            self.index += 1;
+        }
+
+        false
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.current().map(|c| c.is_whitespace()).unwrap_or(false) {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.current() == Some(c) {
+            self.index += 1;
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", c))
+        }
+    }
+}
+
+/// Returns the bibliography type for a BibTeX or BibLaTeX entry type
+fn get_bib_type(entry_type: &str) -> Option<&'static str> {
+    let bib_type = match entry_type {
+        "article" => T_ARTICLE,
+        "book" | "mvbook" | "proceedings" | "collection" => T_BOOK,
+        "booklet" => T_BOOKLET,
+        "inbook" => T_IN_BOOK,
+        "incollection" | "inproceedings" | "conference" => T_IN_COLLECTION,
+        "manual" => T_MANUAL,
+        "misc" => T_MISC,
+        "software" => T_REPOSITORY,
+        "techreport" | "report" => T_TECH_REPORT,
+        "thesis" | "mastersthesis" | "phdthesis" => T_THESIS,
+        "unpublished" => T_UNPUBLISHED,
+        "online" | "electronic" | "www" => T_WEBSITE,
+        _ => return None,
+    };
+
+    Some(bib_type)
+}
+
+/// Builds a date in the form `YYYY-MM-DD` from the year, month and day fields
+fn get_date(parts: &HashMap<String, String>) -> Option<String> {
+    let year = parts.get("year")?.trim();
+    let month = parts
+        .get("month")
+        .map(|m| m.trim().to_lowercase())
+        .and_then(|m| {
+            m.parse::<u32>().ok().or_else(|| {
+                MONTHS
+                    .iter()
+                    .position(|n| m.starts_with(n))
+                    .map(|i| i as u32 + 1)
+            })
+        })
+        .unwrap_or(1);
+    let day = parts
+        .get("day")
+        .and_then(|d| d.trim().parse::<u32>().ok())
+        .unwrap_or(1);
+
+    Some(format!("{}-{:02}-{:02}", year, month, day))
+}
+
+/// Replaces LaTeX escapes and commands with the characters they represent
+/// and removes the braces used for grouping
+pub fn decode_latex(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let mut result = String::with_capacity(value.len());
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+
+        match c {
+            '{' | '}' => {}
+            '~' => result.push('\u{00A0}'),
+            '-' if chars.get(index) == Some(&'-') => {
+                if chars.get(index + 1) == Some(&'-') {
+                    result.push('—');
+                    index += 2;
+                } else {
+                    result.push('–');
+                    index += 1;
+                }
+            }
+            '\\' => {
+                let command: String = if matches!(chars.get(index), Some(c) if c.is_alphabetic()) {
+                    chars[index..]
+                        .iter()
+                        .take_while(|c| c.is_alphabetic())
+                        .collect()
+                } else {
+                    chars.get(index).map(|c| c.to_string()).unwrap_or_default()
+                };
+                index += command.chars().count();
+
+                if let Some(symbol) = get_latex_symbol(&command) {
+                    result.push_str(symbol);
+                    if command.chars().all(char::is_alphabetic) {
+                        // a space after a command only terminates the command
+                        while chars.get(index) == Some(&' ') {
+                            index += 1;
+                        }
+                    }
+                } else if let Some(accent) = get_latex_accent(&command) {
+                    // the accented letter is either in braces or follows directly
+                    while chars.get(index) == Some(&' ') {
+                        index += 1;
+                    }
+                    let braced = chars.get(index) == Some(&'{');
+                    if braced {
+                        index += 1;
+                    }
+                    let mut letter = match chars.get(index) {
+                        Some(c) if *c != '}' => *c,
+                        // an accent without a letter is dropped
+                        _ => continue,
+                    };
+                    if letter == '\\' && chars.get(index + 1) == Some(&'i') {
+                        // dotless i
+                        letter = 'i';
+                        index += 1;
+                    }
+                    index += 1;
+                    if braced && chars.get(index) == Some(&'}') {
+                        index += 1;
+                    }
+                    result.push_str(&combine_accent(letter, accent));
+                }
+                // other commands like \textit are dropped and their argument is kept
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Returns the text for LaTeX commands that produce symbols
+fn get_latex_symbol(command: &str) -> Option<&'static str> {
+    let symbol = match command {
+        "&" => "&",
+        "%" => "%",
+        "$" => "$",
+        "_" => "_",
+        "#" => "#",
+        "{" => "{",
+        "}" => "}",
+        " " => " ",
+        "ss" => "ß",
+        "ae" => "æ",
+        "AE" => "Æ",
+        "oe" => "œ",
+        "OE" => "Œ",
+        "aa" => "å",
+        "AA" => "Å",
+        "o" => "ø",
+        "O" => "Ø",
+        "l" => "ł",
+        "L" => "Ł",
+        "i" => "ı",
+        "textendash" => "–",
+        "textemdash" => "—",
+        "textquoteleft" => "‘",
+        "textquoteright" => "’",
+        "LaTeX" => "LaTeX",
+        "TeX" => "TeX",
+        _ => return None,
+    };
+
+    Some(symbol)
+}
+
+/// Returns the combining character for LaTeX accent commands
+fn get_latex_accent(command: &str) -> Option<char> {
+    let accent = match command {
+        "\"" => '\u{0308}',
+        "'" => '\u{0301}',
+        "`" => '\u{0300}',
+        "^" => '\u{0302}',
+        "~" => '\u{0303}',
+        "=" => '\u{0304}',
+        "." => '\u{0307}',
+        "c" => '\u{0327}',
+        "v" => '\u{030C}',
+        "u" => '\u{0306}',
+        "H" => '\u{030B}',
+        "k" => '\u{0328}',
+        "r" => '\u{030A}',
+        _ => return None,
+    };
+
+    Some(accent)
+}
+
+/// Combines a letter with an accent into a precomposed character where one exists
+fn combine_accent(letter: char, accent: char) -> String {
+    const PRECOMPOSED: &[(char, char, char)] = &[
+        ('a', '\u{0308}', 'ä'),
+        ('o', '\u{0308}', 'ö'),
+        ('u', '\u{0308}', 'ü'),
+        ('e', '\u{0308}', 'ë'),
+        ('i', '\u{0308}', 'ï'),
+        ('y', '\u{0308}', 'ÿ'),
+        ('A', '\u{0308}', 'Ä'),
+        ('O', '\u{0308}', 'Ö'),
+        ('U', '\u{0308}', 'Ü'),
+        ('E', '\u{0308}', 'Ë'),
+        ('I', '\u{0308}', 'Ï'),
+        ('a', '\u{0301}', 'á'),
+        ('e', '\u{0301}', 'é'),
+        ('i', '\u{0301}', 'í'),
+        ('o', '\u{0301}', 'ó'),
+        ('u', '\u{0301}', 'ú'),
+        ('y', '\u{0301}', 'ý'),
+        ('c', '\u{0301}', 'ć'),
+        ('n', '\u{0301}', 'ń'),
+        ('s', '\u{0301}', 'ś'),
+        ('z', '\u{0301}', 'ź'),
+        ('A', '\u{0301}', 'Á'),
+        ('E', '\u{0301}', 'É'),
+        ('I', '\u{0301}', 'Í'),
+        ('O', '\u{0301}', 'Ó'),
+        ('U', '\u{0301}', 'Ú'),
+        ('a', '\u{0300}', 'à'),
+        ('e', '\u{0300}', 'è'),
+        ('i', '\u{0300}', 'ì'),
+        ('o', '\u{0300}', 'ò'),
+        ('u', '\u{0300}', 'ù'),
+        ('A', '\u{0300}', 'À'),
+        ('E', '\u{0300}', 'È'),
+        ('a', '\u{0302}', 'â'),
+        ('e', '\u{0302}', 'ê'),
+        ('i', '\u{0302}', 'î'),
+        ('o', '\u{0302}', 'ô'),
+        ('u', '\u{0302}', 'û'),
+        ('a', '\u{0303}', 'ã'),
+        ('n', '\u{0303}', 'ñ'),
+        ('o', '\u{0303}', 'õ'),
+        ('N', '\u{0303}', 'Ñ'),
+        ('c', '\u{0327}', 'ç'),
+        ('C', '\u{0327}', 'Ç'),
+        ('s', '\u{0327}', 'ş'),
+        ('c', '\u{030C}', 'č'),
+        ('s', '\u{030C}', 'š'),
+        ('z', '\u{030C}', 'ž'),
+        ('r', '\u{030C}', 'ř'),
+        ('e', '\u{030C}', 'ě'),
+        ('C', '\u{030C}', 'Č'),
+        ('S', '\u{030C}', 'Š'),
+        ('Z', '\u{030C}', 'Ž'),
+        ('a', '\u{030A}', 'å'),
+        ('u', '\u{030A}', 'ů'),
+        ('A', '\u{030A}', 'Å'),
+        ('o', '\u{030B}', 'ő'),
+        ('u', '\u{030B}', 'ű'),
+        ('a', '\u{0328}', 'ą'),
+        ('e', '\u{0328}', 'ę'),
+        ('z', '\u{0307}', 'ż'),
+        ('g', '\u{0306}', 'ğ'),
+    ];
+
+    PRECOMPOSED
+        .iter()
+        .find(|(l, a, _)| *l == letter && *a == accent)
+        .map(|(_, _, c)| c.to_string())
+        .unwrap_or_else(|| format!("{}{}", letter, accent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses the given BibTeX text and returns the field maps and positions of the entries
+    fn parse_entries(text: &str) -> Vec<(HashMap<String, String>, String)> {
+        let mut parser = BibTexParser::new(text, "test.bib");
+        let mut entries = Vec::new();
+
+        while parser.seek_to('@') {
+            parser.index += 1;
+            if let Ok(Some(entry)) = parser.parse_item() {
+                let position = parser.get_position_string(entry.index);
+                if let Some(map) = parser.convert_entry(entry) {
+                    entries.push((map, position));
+                }
+            }
+        }
+
+        entries
+    }
+
+    #[test]
+    fn it_expands_string_macros_and_concatenations() {
+        let entries = parse_entries(
+            "@string{pub = \"Snek\" # \" Press\"}\n\
+            @book{key, title = \"Part \" # 2, publisher = pub # {, Inc.}, year = 2020, month = mar}",
+        );
+        let (map, _) = &entries[0];
+        assert_eq!(map[K_TITLE], "Part 2");
+        assert_eq!(map[K_PUBLISHER], "Snek Press, Inc.");
+        assert_eq!(map[K_DATE], "2020-03-01");
+        assert_eq!(map[K_TYPE], T_BOOK);
+    }
+
+    #[test]
+    fn it_keeps_nested_braces() {
+        let entries = parse_entries(
+            "@article(key, title = {The {RUST} {Book {of} Snek}}, journal = \"A {\"}Quote\")",
+        );
+        let (map, _) = &entries[0];
+        assert_eq!(map[K_TITLE], "The RUST Book of Snek");
+        assert_eq!(map[K_JOURNAL], "A \"Quote");
+    }
+
+    #[test]
+    fn it_decodes_latex() {
+        assert_eq!(decode_latex("M\\\"{u}ller"), "Müller");
+        assert_eq!(decode_latex("Caf\\'e"), "Café");
+        assert_eq!(decode_latex("Ni\\~no \\c{c}a"), "Niño ça");
+        assert_eq!(decode_latex("\\'{\\i}"), "í");
+        assert_eq!(decode_latex("\\ss{} and \\o"), "ß and ø");
+        assert_eq!(decode_latex("1--2 -- a---b"), "1–2 – a—b");
+        assert_eq!(decode_latex("a~b \\& c"), "a\u{00A0}b & c");
+        assert_eq!(decode_latex("\\textit{text}"), "text");
+    }
+
+    #[test]
+    fn it_drops_accents_without_a_letter() {
+        assert_eq!(decode_latex("end\\'"), "end");
+        assert_eq!(decode_latex("{end\\'}"), "end");
+        assert_eq!(decode_latex("\\'{}e"), "e");
+    }
+
+    #[test]
+    fn it_reports_positions_of_entries_after_errors() {
+        let text = "@misc{broken, title = }\n\n  @misc{valid,\n  title = {Title}}\n";
+        let mut parser = BibTexParser::new(text, "test.bib");
+        let entries = parser.parse();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1, "test.bib:3:2");
+
+        let mut parser = BibTexParser::new(text, "test.bib");
+        parser.index = 1;
+        assert!(parser.parse_item().is_err());
+        assert_eq!(parser.get_position_string(parser.index), "test.bib:1:22");
+    }
+}
@@ -0,0 +1,595 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Anchor, ItalicText, Line, ListItem, PlainText, TextLine};
+use crate::elements::{Inline, Url};
+use crate::settings::style_settings::{BibSorting, CitationStyle};
+use crate::utils::dates::DateFormatter;
+use crate::utils::localisation::*;
+use bibliographix::bibliography::bib_types::BibliographyType;
+use bibliographix::bibliography::bibliography_entry::{
+    BibliographyEntry, BibliographyEntryReference,
+};
+use chrono::{Date, Datelike, Local};
+
+use crate::italic_text;
+use crate::list_item;
+use crate::plain_text;
+use crate::url_text;
+
+/// The fields of a bibliography entry that are used by the citation styles
+#[derive(Clone, Debug, Default)]
+pub struct BibFields {
+    pub author: Option<String>,
+    pub title: Option<String>,
+    pub container: Option<String>,
+    pub volume: Option<String>,
+    pub number: Option<String>,
+    pub pages: Option<String>,
+    pub publisher: Option<String>,
+    pub date: Option<Date<Local>>,
+    pub url: Option<String>,
+    /// If the entry is a work on its own and not part of a container
+    pub standalone: bool,
+}
+
+impl BibFields {
+    /// Returns the common fields of a bibliography entry
+    pub fn from_entry(entry: &BibliographyEntry) -> Self {
+        match &entry.bib_type {
+            BibliographyType::Article(a) => Self {
+                author: Some(a.author.clone()),
+                title: Some(a.title.clone()),
+                container: Some(a.journal.clone()),
+                volume: a.volume.clone(),
+                number: a.number.clone(),
+                pages: a.pages.clone(),
+                date: Some(a.date),
+                url: a.url.clone(),
+                ..Default::default()
+            },
+            BibliographyType::Book(b) => Self {
+                author: Some(b.author.clone()),
+                title: Some(b.title.clone()),
+                container: b.series.clone(),
+                volume: b.volume.clone(),
+                publisher: Some(b.publisher.clone()),
+                date: Some(b.date),
+                url: b.url.clone(),
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Booklet(b) => Self {
+                author: b.author.clone(),
+                title: Some(b.title.clone()),
+                publisher: b.how_published.clone(),
+                date: b.date,
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::InBook(ib) => Self {
+                author: Some(ib.author.clone()),
+                title: Some(ib.title.clone()),
+                container: ib.series.clone(),
+                volume: ib.volume.clone(),
+                pages: Some(ib.position.clone()),
+                publisher: Some(ib.publisher.clone()),
+                date: Some(ib.date),
+                ..Default::default()
+            },
+            BibliographyType::InCollection(ic) => Self {
+                author: Some(ic.author.clone()),
+                title: Some(ic.title.clone()),
+                container: ic.series.clone(),
+                volume: ic.volume.clone(),
+                pages: ic.position.clone(),
+                publisher: Some(ic.publisher.clone()),
+                date: Some(ic.date),
+                ..Default::default()
+            },
+            BibliographyType::Manual(m) => Self {
+                author: m.author.clone(),
+                title: Some(m.title.clone()),
+                publisher: m.organization.clone(),
+                date: m.date,
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Misc(m) => Self {
+                author: m.author.clone(),
+                title: m.title.clone(),
+                publisher: m.how_published.clone(),
+                date: m.date,
+                url: m.url.clone(),
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Repository(r) => Self {
+                author: Some(r.author.clone()),
+                title: Some(r.title.clone()),
+                url: r.url.clone(),
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::TechReport(tr) => Self {
+                author: Some(tr.author.clone()),
+                title: Some(tr.title.clone()),
+                number: tr.number.clone(),
+                publisher: Some(tr.institution.clone()),
+                date: Some(tr.date),
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Thesis(t) => Self {
+                author: Some(t.author.clone()),
+                title: Some(t.title.clone()),
+                publisher: Some(t.school.clone()),
+                date: Some(t.date),
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Unpublished(u) => Self {
+                author: Some(u.author.clone()),
+                title: Some(u.title.clone()),
+                date: u.date,
+                standalone: true,
+                ..Default::default()
+            },
+            BibliographyType::Website(w) => Self {
+                author: w.author.clone(),
+                title: w.title.clone(),
+                date: w.date,
+                url: Some(w.url.clone()),
+                standalone: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Returns the year of the entry or the localised placeholder for entries without a date
+    pub fn get_year(&self, l10n: &Localisation) -> String {
+        self.date
+            .map(|d| d.year().to_string())
+            .unwrap_or_else(|| l10n.get(L_BIB_NO_DATE))
+    }
+
+    /// Returns the short form of the authors used for references in the text.
+    /// Entries without an author use the title instead.
+    pub fn get_cite_author(&self, l10n: &Localisation) -> String {
+        let author = if let Some(author) = &self.author {
+            author
+        } else {
+            return self.title.clone().unwrap_or_default();
+        };
+        let names: Vec<&str> = author
+            .split(" and ")
+            .map(get_last_name)
+            .filter(|n| !n.is_empty())
+            .collect();
+
+        match names.len() {
+            0 => author.clone(),
+            1 => names[0].to_string(),
+            2 => format!("{} {} {}", names[0], l10n.get(L_BIB_AND), names[1]),
+            _ => format!("{} {}", names[0], l10n.get(L_BIB_ET_AL)),
+        }
+    }
+}
+
+/// Returns the last name of an author written as `First Last` or `Last, First`
+fn get_last_name(author: &str) -> &str {
+    let author = author.trim();
+
+    if let Some((last, _)) = author.split_once(',') {
+        last.trim()
+    } else {
+        author.rsplit(' ').next().unwrap_or(author)
+    }
+}
+
+/// Sorts the entries of a bibliography
+pub fn sort_bib_entries(entries: &mut [BibliographyEntryReference], sorting: &BibSorting) {
+    let key = |entry: &BibliographyEntryReference| {
+        let entry = entry.lock();
+        let fields = BibFields::from_entry(&entry);
+        // sorted by the last name of the first author
+        let author = fields
+            .author
+            .as_ref()
+            .and_then(|a| a.split(" and ").next())
+            .map(|a| get_last_name(a).to_string())
+            .or_else(|| fields.title.clone())
+            .unwrap_or_else(|| entry.key())
+            .to_lowercase();
+
+        (author, fields.date, fields.title)
+    };
+
+    match sorting {
+        BibSorting::Occurrence => {}
+        BibSorting::Author => entries.sort_by_cached_key(key),
+        BibSorting::Year => entries.sort_by_cached_key(|e| {
+            let (author, date, title) = key(e);
+            (date, author, title)
+        }),
+    }
+}
+
+/// Returns the list item for an entry formatted with the given citation style.
+/// The numeric style uses the formatting of the bibliography type instead.
+pub fn get_styled_item(
+    entry: &BibliographyEntry,
+    style: &CitationStyle,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) -> ListItem {
+    let fields = BibFields::from_entry(entry);
+    let mut text = TextLine::new();
+
+    match style {
+        CitationStyle::Apa => add_apa_fields(&mut text, &fields, l10n),
+        CitationStyle::Chicago => add_chicago_fields(&mut text, &fields, l10n),
+        CitationStyle::Ieee => add_ieee_fields(&mut text, &fields, dates, l10n),
+        _ => add_author_year_fields(&mut text, &fields, l10n),
+    }
+    if let Some(Inline::Plain(last)) = text.subtext.last_mut() {
+        last.value = last.value.trim_end().to_string();
+    }
+    if let Some(url) = fields.url {
+        text.subtext.push(plain_text!(" ".to_string()));
+        text.subtext.push(url_text!(url));
+    }
+    if let Some(notes) = entry.note.clone() {
+        text.subtext.push(plain_text!(format!(" {}", notes)))
+    }
+
+    list_item!(text, entry.key())
+}
+
+/// Adds the fields in the form `Author. (Year). Title. Container, Volume(Number), Pages. Publisher.`
+fn add_apa_fields(text: &mut TextLine, fields: &BibFields, l10n: &Localisation) {
+    if let Some(author) = &fields.author {
+        text.subtext
+            .push(plain_text!(format!("{} ", with_period(author))));
+    }
+    text.subtext
+        .push(plain_text!(format!("({}). ", fields.get_year(l10n))));
+    add_title(text, fields, ". ");
+
+    if let Some(container) = &fields.container {
+        text.subtext.push(italic_text!(container.clone()));
+        if let Some(volume) = &fields.volume {
+            text.subtext.push(italic_text!(format!(", {}", volume)));
+        }
+        if let Some(number) = &fields.number {
+            text.subtext.push(plain_text!(format!("({})", number)));
+        }
+        if let Some(pages) = &fields.pages {
+            text.subtext.push(plain_text!(format!(", {}", pages)));
+        }
+        text.subtext.push(plain_text!(". ".to_string()));
+    }
+    if let Some(publisher) = &fields.publisher {
+        text.subtext.push(plain_text!(with_period(publisher)));
+    }
+}
+
+/// Adds the fields in the form `Author. Year. "Title." Container Volume (Number): Pages. Publisher.`
+fn add_chicago_fields(text: &mut TextLine, fields: &BibFields, l10n: &Localisation) {
+    if let Some(author) = &fields.author {
+        text.subtext
+            .push(plain_text!(format!("{} ", with_period(author))));
+    }
+    text.subtext.push(plain_text!(format!(
+        "{} ",
+        with_period(&fields.get_year(l10n))
+    )));
+
+    if let Some(title) = &fields.title {
+        if fields.standalone {
+            text.subtext.push(italic_text!(title.clone()));
+            text.subtext.push(plain_text!(". ".to_string()));
+        } else {
+            text.subtext.push(plain_text!(format!("\"{}.\" ", title)));
+        }
+    }
+    if let Some(container) = &fields.container {
+        text.subtext.push(italic_text!(container.clone()));
+        if let Some(volume) = &fields.volume {
+            text.subtext.push(plain_text!(format!(" {}", volume)));
+        }
+        if let Some(number) = &fields.number {
+            text.subtext.push(plain_text!(format!(" ({})", number)));
+        }
+        if let Some(pages) = &fields.pages {
+            text.subtext.push(plain_text!(format!(": {}", pages)));
+        }
+        text.subtext.push(plain_text!(". ".to_string()));
+    }
+    if let Some(publisher) = &fields.publisher {
+        text.subtext.push(plain_text!(with_period(publisher)));
+    }
+}
+
+/// Adds the fields in the form `Author, "Title," Container, vol. Volume, no. Number, pp. Pages, Publisher, Date.`
+fn add_ieee_fields(
+    text: &mut TextLine,
+    fields: &BibFields,
+    dates: &DateFormatter,
+    l10n: &Localisation,
+) {
+    let mut parts = Vec::new();
+
+    if let Some(volume) = &fields.volume {
+        parts.push(format!("{} {}", l10n.get(L_BIB_VOLUME_SHORT), volume));
+    }
+    if let Some(number) = &fields.number {
+        parts.push(format!("{} {}", l10n.get(L_BIB_NUMBER_SHORT), number));
+    }
+    if let Some(pages) = &fields.pages {
+        parts.push(format!("{} {}", l10n.get(L_BIB_PAGES_SHORT), pages));
+    }
+    if let Some(publisher) = &fields.publisher {
+        parts.push(publisher.clone());
+    }
+    if let Some(date) = &fields.date {
+        parts.push(dates.format_date(date));
+    }
+    let separator = if fields.container.is_some() || !parts.is_empty() {
+        ","
+    } else {
+        "."
+    };
+
+    if let Some(author) = &fields.author {
+        text.subtext.push(plain_text!(format!("{}, ", author)));
+    }
+    if let Some(title) = &fields.title {
+        if fields.standalone {
+            text.subtext.push(italic_text!(title.clone()));
+            text.subtext.push(plain_text!(format!("{} ", separator)));
+        } else {
+            text.subtext
+                .push(plain_text!(format!("\"{}{}\" ", title, separator)));
+        }
+    }
+    if let Some(container) = &fields.container {
+        text.subtext.push(italic_text!(container.clone()));
+        text.subtext.push(plain_text!(if parts.is_empty() {
+            ".".to_string()
+        } else {
+            ", ".to_string()
+        }));
+    }
+    if !parts.is_empty() {
+        text.subtext
+            .push(plain_text!(format!("{}.", parts.join(", "))));
+    }
+}
+
+/// Adds the fields in the form `Author (Year): Title. Container. Publisher.`
+fn add_author_year_fields(text: &mut TextLine, fields: &BibFields, l10n: &Localisation) {
+    if let Some(author) = &fields.author {
+        text.subtext.push(plain_text!(format!(
+            "{} ({}): ",
+            author,
+            fields.get_year(l10n)
+        )));
+    } else {
+        text.subtext
+            .push(plain_text!(format!("({}): ", fields.get_year(l10n))));
+    }
+    add_title(text, fields, ". ");
+
+    if let Some(container) = &fields.container {
+        text.subtext.push(italic_text!(container.clone()));
+        text.subtext.push(plain_text!(". ".to_string()));
+    }
+    if let Some(publisher) = &fields.publisher {
+        text.subtext.push(plain_text!(with_period(publisher)));
+    }
+}
+
+/// Adds the title which is italic for standalone works
+fn add_title(text: &mut TextLine, fields: &BibFields, separator: &str) {
+    if let Some(title) = &fields.title {
+        if fields.standalone {
+            text.subtext.push(italic_text!(title.clone()));
+            text.subtext.push(plain_text!(separator.to_string()));
+        } else {
+            text.subtext
+                .push(plain_text!(format!("{}{}", title, separator)));
+        }
+    }
+}
+
+/// Appends a period to the text if it doesn't already end with one
+fn with_period(text: &str) -> String {
+    if text.ends_with('.') {
+        text.to_string()
+    } else {
+        format!("{}.", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::style_settings::StyleSettings;
+    use bibliographix::bibliography::FromHashMap;
+    use parking_lot::Mutex;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn get_entry(fields: &[(&str, &str)]) -> BibliographyEntry {
+        let map: HashMap<String, String> = fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        *BibliographyEntry::from_hash_map(&map).unwrap()
+    }
+
+    fn get_book(key: &str, author: &str, date: &str) -> BibliographyEntryReference {
+        Arc::new(Mutex::new(get_entry(&[
+            ("key", key),
+            ("type", "book"),
+            ("author", author),
+            ("title", key),
+            ("publisher", "Publisher"),
+            ("date", date),
+        ])))
+    }
+
+    fn get_keys(entries: &[BibliographyEntryReference]) -> Vec<String> {
+        entries.iter().map(|e| e.lock().key()).collect()
+    }
+
+    fn get_text(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Plain(p) => p.value.clone(),
+                Inline::Italic(i) => get_text(&i.value),
+                Inline::Url(u) => u.url.clone(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    fn get_styled_text(entry: &BibliographyEntry, style: CitationStyle) -> String {
+        let item = get_styled_item(
+            entry,
+            &style,
+            &DateFormatter::new("%d.%m.%Y", "en"),
+            &Localisation::new("en", &HashMap::new()),
+        );
+        match item.text {
+            Line::Anchor(Anchor { inner, key }) => {
+                assert_eq!(key, entry.key());
+                match *inner {
+                    Line::Text(text) => get_text(&text.subtext),
+                    _ => panic!("the anchor should contain a text line"),
+                }
+            }
+            _ => panic!("the item should be an anchor"),
+        }
+    }
+
+    #[test]
+    fn it_returns_the_last_names_of_authors() {
+        assert_eq!(get_last_name("Jane Doe"), "Doe");
+        assert_eq!(get_last_name(" Doe, Jane "), "Doe");
+        assert_eq!(get_last_name("Plato"), "Plato");
+    }
+
+    #[test]
+    fn it_shortens_the_authors_for_references() {
+        let l10n = Localisation::new("en", &HashMap::new());
+        let mut fields = BibFields {
+            author: Some("Jane Doe".to_string()),
+            title: Some("Title".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(fields.get_cite_author(&l10n), "Doe");
+
+        fields.author = Some("Jane Doe and Smith, John".to_string());
+        assert_eq!(fields.get_cite_author(&l10n), "Doe and Smith");
+
+        fields.author = Some("Jane Doe and John Smith and Max Mustermann".to_string());
+        assert_eq!(fields.get_cite_author(&l10n), "Doe et al.");
+
+        fields.author = None;
+        assert_eq!(fields.get_cite_author(&l10n), "Title");
+        assert_eq!(fields.get_year(&l10n), "n.d.");
+        assert_eq!(
+            fields.get_year(&Localisation::new("de", &HashMap::new())),
+            "o. J."
+        );
+    }
+
+    #[test]
+    fn it_sorts_the_bibliography() {
+        let entries = vec![
+            get_book("zeta", "Zoe Zeta", "01.01.2001"),
+            get_book("alpha", "Alice Alpha and Zoe Zeta", "01.01.2010"),
+            get_book("mid", "Max Mid", "01.01.1999"),
+        ];
+
+        let mut sorted = entries.clone();
+        sort_bib_entries(&mut sorted, &BibSorting::Occurrence);
+        assert_eq!(get_keys(&sorted), vec!["zeta", "alpha", "mid"]);
+
+        let mut sorted = entries.clone();
+        sort_bib_entries(&mut sorted, &BibSorting::Author);
+        assert_eq!(get_keys(&sorted), vec!["alpha", "mid", "zeta"]);
+
+        let mut sorted = entries;
+        sort_bib_entries(&mut sorted, &BibSorting::Year);
+        assert_eq!(get_keys(&sorted), vec!["mid", "zeta", "alpha"]);
+    }
+
+    #[test]
+    fn it_uses_the_sorting_of_the_citation_style() {
+        let mut style = StyleSettings::default();
+        assert_eq!(style.get_bib_sorting(), BibSorting::Occurrence);
+        assert_eq!(style.get_bib_ref_display(), "{{number}}");
+
+        style.citation_style = CitationStyle::Apa;
+        assert_eq!(style.get_bib_sorting(), BibSorting::Author);
+        assert_eq!(style.get_bib_ref_display(), "{{author}}, {{year}}");
+
+        style.bib_sorting = Some(BibSorting::Year);
+        style.bib_ref_display = Some("[{{key}}]".to_string());
+        assert_eq!(style.get_bib_sorting(), BibSorting::Year);
+        assert_eq!(style.get_bib_ref_display(), "[{{key}}]");
+    }
+
+    #[test]
+    fn it_formats_entries_in_the_citation_style() {
+        let article = get_entry(&[
+            ("key", "article"),
+            ("type", "article"),
+            ("author", "Jane Doe"),
+            ("title", "On Snakes"),
+            ("journal", "Reptiles"),
+            ("volume", "3"),
+            ("number", "2"),
+            ("pages", "10-20"),
+            ("date", "05.01.2020"),
+        ]);
+        assert_eq!(
+            get_styled_text(&article, CitationStyle::Apa),
+            "Jane Doe. (2020). On Snakes. Reptiles, 3(2), 10-20."
+        );
+        assert_eq!(
+            get_styled_text(&article, CitationStyle::Chicago),
+            "Jane Doe. 2020. \"On Snakes.\" Reptiles 3 (2): 10-20."
+        );
+        assert_eq!(
+            get_styled_text(&article, CitationStyle::Ieee),
+            "Jane Doe, \"On Snakes,\" Reptiles, vol. 3, no. 2, pp. 10-20, 01.05.2020."
+        );
+        assert_eq!(
+            get_styled_text(&article, CitationStyle::AuthorYear),
+            "Jane Doe (2020): On Snakes. Reptiles."
+        );
+
+        let book = get_entry(&[
+            ("key", "book"),
+            ("type", "book"),
+            ("author", "Jane Doe"),
+            ("title", "Snakes"),
+            ("publisher", "Snek Press"),
+            ("date", "05.01.2020"),
+            ("url", "https://example.com"),
+        ]);
+        assert_eq!(
+            get_styled_text(&book, CitationStyle::Apa),
+            "Jane Doe. (2020). Snakes. Snek Press. https://example.com"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{BibCitation, Block, Inline, Line, ListItem, Placeholder, TextLine};
+use std::sync::{Arc, RwLock};
+
+type CitationRef = Arc<RwLock<BibCitation>>;
+type PlaceholderRef = Arc<RwLock<Placeholder>>;
+
+/// A citation or placeholder in the order it appears in the document
+#[derive(Clone, Debug)]
+pub enum CitationItem {
+    Citation(Arc<RwLock<BibCitation>>),
+    Placeholder(Arc<RwLock<Placeholder>>),
+}
+
+pub trait GetCitations {
+    fn get_citations(&self) -> Vec<CitationItem>;
+}
+
+impl GetCitations for Inline {
+    fn get_citations(&self) -> Vec<CitationItem> {
+        match self {
+            Inline::BibCitation(citation) => vec![CitationItem::Citation(Arc::clone(citation))],
+            Inline::Placeholder(pholder) => vec![CitationItem::Placeholder(Arc::clone(pholder))],
+            Inline::Bold(bo) => bo.value.iter().flat_map(|i| i.get_citations()).collect(),
+            Inline::Italic(it) => it.value.iter().flat_map(|i| i.get_citations()).collect(),
+            Inline::Underlined(un) => un.value.iter().flat_map(|i| i.get_citations()).collect(),
+            Inline::Striked(st) => st.value.iter().flat_map(|i| i.get_citations()).collect(),
+            Inline::Superscript(sup) => sup.value.iter().flat_map(|i| i.get_citations()).collect(),
+            Inline::Colored(col) => col.value.get_citations(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl GetCitations for TextLine {
+    fn get_citations(&self) -> Vec<CitationItem> {
+        self.subtext
+            .iter()
+            .flat_map(|i| i.get_citations())
+            .collect()
+    }
+}
+
+impl GetCitations for Line {
+    fn get_citations(&self) -> Vec<CitationItem> {
+        match self {
+            Line::Text(text) => text.get_citations(),
+            Line::Centered(center) => center.line.get_citations(),
+            Line::Anchor(anchor) => anchor.inner.get_citations(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl GetCitations for ListItem {
+    fn get_citations(&self) -> Vec<CitationItem> {
+        let mut citations = self.text.get_citations();
+        citations.append(
+            &mut self
+                .children
+                .iter()
+                .flat_map(|child| child.get_citations())
+                .collect(),
+        );
+
+        citations
+    }
+}
+
+impl GetCitations for Block {
+    fn get_citations(&self) -> Vec<CitationItem> {
+        match self {
+            Block::Section(sec) => sec
+                .elements
+                .iter()
+                .flat_map(|b| b.get_citations())
+                .collect(),
+            Block::Paragraph(par) => par
+                .elements
+                .iter()
+                .flat_map(|l| l.get_citations())
+                .collect(),
+            Block::List(list) => list.items.iter().flat_map(|i| i.get_citations()).collect(),
+            Block::Quote(q) => q.text.iter().flat_map(|t| t.get_citations()).collect(),
+            Block::Table(table) => std::iter::once(&table.header)
+                .chain(table.rows.iter())
+                .flat_map(|row| row.cells.iter())
+                .flat_map(|cell| cell.text.get_citations())
+                .collect(),
+            Block::Placeholder(pholder) => {
+                vec![CitationItem::Placeholder(Arc::clone(pholder))]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns all citations of the given blocks in the order they appear in
+pub fn get_citation_list(blocks: &[Block]) -> Vec<Arc<RwLock<BibCitation>>> {
+    blocks
+        .iter()
+        .flat_map(|b| b.get_citations())
+        .filter_map(|item| match item {
+            CitationItem::Citation(citation) => Some(citation),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Assigns the citations of the enclosing section to every placeholder
+/// that accepts a citation scope. Placeholders outside of any section
+/// are assigned the citations of all given blocks if there are any so that
+/// an imported document without citations is scoped by the importing one.
+pub fn assign_citation_scopes<F>(blocks: &[Block], accepts_scope: &F)
+where
+    F: Fn(&Placeholder) -> bool,
+{
+    let (citations, placeholders) = assign_section_scopes(blocks, accepts_scope);
+    if !citations.is_empty() {
+        set_citation_scope(&placeholders, &citations);
+    }
+}
+
+/// Assigns the scopes of the sections in the given blocks and returns
+/// the citations and open placeholders of the blocks
+fn assign_section_scopes<F>(
+    blocks: &[Block],
+    accepts_scope: &F,
+) -> (Vec<CitationRef>, Vec<PlaceholderRef>)
+where
+    F: Fn(&Placeholder) -> bool,
+{
+    let mut citations = Vec::new();
+    let mut placeholders = Vec::new();
+
+    for block in blocks {
+        if let Block::Section(sec) = block {
+            let (mut sec_citations, mut sec_placeholders) =
+                assign_section_scopes(&sec.elements, accepts_scope);
+            // sections without citations like a "References" subsection
+            // list the citations of their parent instead
+            if sec_citations.is_empty() {
+                placeholders.append(&mut sec_placeholders);
+            } else {
+                set_citation_scope(&sec_placeholders, &sec_citations);
+            }
+            citations.append(&mut sec_citations);
+            continue;
+        }
+        for item in block.get_citations() {
+            match item {
+                CitationItem::Citation(citation) => citations.push(citation),
+                CitationItem::Placeholder(pholder) => {
+                    let accepts = {
+                        let pholder = pholder.read().unwrap();
+                        pholder.citation_scope.is_none() && accepts_scope(&pholder)
+                    };
+                    if accepts {
+                        placeholders.push(pholder)
+                    }
+                }
+            }
+        }
+    }
+
+    (citations, placeholders)
+}
+
+fn set_citation_scope(placeholders: &[PlaceholderRef], citations: &[CitationRef]) {
+    for pholder in placeholders {
+        pholder.write().unwrap().citation_scope = Some(citations.to_vec());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elements::CitationPart;
+    use crate::utils::testing::{parse_test_files, render_html};
+
+    const ENTRIES: &str = "[a]: https://a.com\n[b]: https://b.com\n[c]: https://c.com\n\
+        [e]: [type=\"book\", author=\"Smith, John\", title=\"Title\", publisher=\"Pub\", date=\"2020-01-01\"]\n";
+
+    /// Returns the rendered text of every citation in the given document text
+    fn get_citation_texts(name: &str, text: &str) -> Vec<String> {
+        let document = parse_test_files(name, &[("main.sd", &format!("{}\n{}", text, ENTRIES))]);
+
+        get_citation_list(&document.elements)
+            .iter()
+            .map(|citation| {
+                citation
+                    .read()
+                    .unwrap()
+                    .get_parts()
+                    .into_iter()
+                    .map(|part| match part {
+                        CitationPart::Text(text) => text,
+                        CitationPart::Reference { text, .. } => text,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_groups_citations() {
+        let texts = get_citation_texts("citation-group", "Text [^a; b] and [^e; a]\n");
+        assert_eq!(texts, vec!["1, 2", "1, 3"]);
+    }
+
+    #[test]
+    fn it_collapses_ranges() {
+        let texts = get_citation_texts("citation-range", "Text [^c; a; b] and [^b; e; a; c]\n");
+        assert_eq!(texts, vec!["1–3", "1–4"]);
+    }
+
+    #[test]
+    fn it_adds_prefixes_and_locators() {
+        let texts = get_citation_texts("citation-locator", "Text [^see a, p. 3; b, ch. 2]\n");
+        assert_eq!(texts, vec!["see 1, p. 3, 2, ch. 2"]);
+    }
+
+    #[test]
+    fn it_formats_textual_citations() {
+        let texts = get_citation_texts("citation-textual", "[^a] [^@e, p. 5] and [^@b]\n");
+        assert_eq!(texts, vec!["1", "Smith [2, p. 5]", "[3]"]);
+    }
+
+    #[test]
+    fn it_escapes_the_citation_text() {
+        let document = parse_test_files(
+            "citation-escape",
+            &[(
+                "main.sd",
+                &format!("[^see <img src=x> a, p. 3]\n{}", ENTRIES),
+            )],
+        );
+        let citation = get_citation_list(&document.elements).remove(0);
+        let html = render_html(&*citation.read().unwrap());
+        assert!(html.contains("see &lt;img src=x&gt; 1, p. 3"));
+        assert!(!html.contains("<img"));
+    }
+}
@@ -0,0 +1,105 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::{Block, Inline, Line, ListItem, TextLine};
+use std::collections::HashMap;
+
+/// The titles of the sections by their anchor
+type SectionTitles = HashMap<String, Vec<Inline>>;
+
+/// Assigns the title of the referenced section to links without a description
+pub trait AssignLinkTitles {
+    fn assign_link_titles(&mut self, titles: &SectionTitles);
+}
+
+impl AssignLinkTitles for Inline {
+    fn assign_link_titles(&mut self, titles: &SectionTitles) {
+        match self {
+            Inline::Url(url) => {
+                if url.description.is_none() {
+                    if let Some(title) = url.url.strip_prefix('#').and_then(|a| titles.get(a)) {
+                        url.description = Some(title.clone());
+                    }
+                }
+            }
+            Inline::Bold(bo) => bo
+                .value
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Inline::Italic(it) => it
+                .value
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Inline::Underlined(un) => un
+                .value
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Inline::Striked(st) => st
+                .value
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Inline::Superscript(sup) => sup
+                .value
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Inline::Colored(col) => col.value.assign_link_titles(titles),
+            _ => {}
+        }
+    }
+}
+
+impl AssignLinkTitles for TextLine {
+    fn assign_link_titles(&mut self, titles: &SectionTitles) {
+        self.subtext
+            .iter_mut()
+            .for_each(|i| i.assign_link_titles(titles))
+    }
+}
+
+impl AssignLinkTitles for Line {
+    fn assign_link_titles(&mut self, titles: &SectionTitles) {
+        match self {
+            Line::Text(text) => text.assign_link_titles(titles),
+            Line::Centered(center) => center.line.assign_link_titles(titles),
+            Line::Anchor(anchor) => anchor.inner.assign_link_titles(titles),
+            _ => {}
+        }
+    }
+}
+
+impl AssignLinkTitles for ListItem {
+    fn assign_link_titles(&mut self, titles: &SectionTitles) {
+        self.text.assign_link_titles(titles);
+        self.children
+            .iter_mut()
+            .for_each(|child| child.assign_link_titles(titles));
+    }
+}
+
+impl AssignLinkTitles for Block {
+    fn assign_link_titles(&mut self, titles: &SectionTitles) {
+        match self {
+            Block::Section(sec) => sec
+                .elements
+                .iter_mut()
+                .for_each(|b| b.assign_link_titles(titles)),
+            Block::Paragraph(par) => par
+                .elements
+                .iter_mut()
+                .for_each(|l| l.assign_link_titles(titles)),
+            Block::List(list) => list
+                .items
+                .iter_mut()
+                .for_each(|i| i.assign_link_titles(titles)),
+            Block::Quote(q) => q.text.iter_mut().for_each(|t| t.assign_link_titles(titles)),
+            Block::Table(table) => std::iter::once(&mut table.header)
+                .chain(table.rows.iter_mut())
+                .flat_map(|row| row.cells.iter_mut())
+                .for_each(|cell| cell.text.assign_link_titles(titles)),
+            _ => {}
+        }
+    }
+}
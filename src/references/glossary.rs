@@ -5,10 +5,13 @@
  */
 
 use crate::elements::{
-    Anchor, BoldText, Inline, ItalicText, Line, List, ListItem, PlainText, TextLine,
+    BoldText, Definition, DefinitionList, Inline, ItalicText, PlainText, TextLine,
+};
+use crate::format::PlaceholderTemplate;
+use crate::references::glossary_import::{
+    read_glossary, GlossaryColumns, GlossaryFormat, GlossaryIssue,
 };
 use parking_lot::Mutex;
-use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -16,14 +19,24 @@ use crate::bold_text;
 use crate::italic_text;
 use crate::plain_text;
 
-const K_LONG: &str = "long";
-const K_DESCRIPTION: &str = "description";
+pub(crate) const K_SHORT: &str = "short";
+pub(crate) const K_LONG: &str = "long";
+pub(crate) const K_DESCRIPTION: &str = "description";
+pub(crate) const K_PLURAL: &str = "plural";
+pub(crate) const K_LONG_PLURAL: &str = "long_plural";
+
+/// The fields of a glossary entry that can be defined inline
+pub(crate) const GLOSSARY_ENTRY_KEYS: &[&str] = &[K_LONG, K_DESCRIPTION, K_PLURAL, K_LONG_PLURAL];
 
 /// A glossary manager responsible for handling glossary entries and references to those entries
 #[derive(Clone, Debug)]
 pub struct GlossaryManager {
     entries: HashMap<String, Arc<Mutex<GlossaryEntry>>>,
     references: Vec<Arc<Mutex<GlossaryReference>>>,
+    duplicates: Vec<GlossaryEntry>,
+    /// The plural forms and case folded keys of the entries mapped
+    /// to the key of the entry and if the form is a plural
+    lookup: HashMap<String, (String, bool)>,
 }
 
 /// A single glossary entry
@@ -32,6 +45,9 @@ pub struct GlossaryEntry {
     pub short: String,
     pub long: String,
     pub description: String,
+    pub plural: Option<String>,
+    pub long_plural: Option<String>,
+    pub position: Option<String>,
     pub is_assigned: bool,
 }
 
@@ -41,14 +57,23 @@ pub struct GlossaryReference {
     pub short: String,
     pub display: GlossaryDisplay,
     pub entry: Option<Arc<Mutex<GlossaryEntry>>>,
+    pub plural: bool,
+    pub capitalized: bool,
+    pub first_use_display: String,
+    pub position: Option<String>,
 }
 
 /// A glossary display value that determines which value
 /// of a glossary entry will be rendered
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum GlossaryDisplay {
+    /// The first use display for the first reference and short for all others
+    Auto,
     Short,
     Long,
+    /// Long followed by short in parentheses
+    Full,
+    FirstUse,
 }
 
 impl GlossaryManager {
@@ -57,12 +82,35 @@ impl GlossaryManager {
         Self {
             entries: HashMap::new(),
             references: Vec::new(),
+            duplicates: Vec::new(),
+            lookup: HashMap::new(),
         }
     }
 
-    /// Adds a new glossary entry to the manager
+    /// Adds a new glossary entry to the manager.
+    /// If an entry with the same key exists it is replaced by the new definition
     pub fn add_entry(&mut self, entry: GlossaryEntry) -> Arc<Mutex<GlossaryEntry>> {
         let key = entry.short.clone();
+
+        if let Some(existing) = self.entries.get(&key) {
+            // the same definition can be parsed again when the parser backtracks
+            if existing.lock().position == entry.position {
+                return Arc::clone(existing);
+            }
+            let existing = existing.lock().clone();
+            log::warn!(
+                "Glossary entry '{}' is defined multiple times. The last definition is used.\n\t--> {}\n\t--> {} (previous definition)\n",
+                key,
+                entry.get_position(),
+                existing.get_position()
+            );
+            self.lookup.retain(|_, (entry_key, _)| *entry_key != key);
+            self.duplicates.push(existing);
+        }
+        let plural = entry.get_short(true);
+        self.add_lookup_form(key.to_lowercase(), &key, false);
+        self.add_lookup_form(plural.to_lowercase(), &key, true);
+        self.add_lookup_form(plural, &key, true);
         let entry = Arc::new(Mutex::new(entry));
         self.entries.insert(key.clone(), Arc::clone(&entry));
         log::debug!("Added glossary entry {}", key);
@@ -70,6 +118,18 @@ impl GlossaryManager {
         entry
     }
 
+    /// Adds a form of an entry key to the lookup table.
+    /// Singular forms take precedence over plural forms of other entries
+    fn add_lookup_form(&mut self, form: String, key: &str, plural: bool) {
+        match self.lookup.get(&form) {
+            Some((_, false)) => {}
+            Some(_) if plural => {}
+            _ => {
+                self.lookup.insert(form, (key.to_string(), plural));
+            }
+        }
+    }
+
     /// Adds a new glossary reference to the manager
     pub fn add_reference(&mut self, reference: GlossaryReference) -> Arc<Mutex<GlossaryReference>> {
         let reference = Arc::new(Mutex::new(reference));
@@ -78,62 +138,111 @@ impl GlossaryManager {
         reference
     }
 
-    /// Assignes bibliography entries from toml
+    /// Assignes glossary entries from toml
     pub fn assign_from_toml(&mut self, value: toml::Value) -> Result<(), String> {
-        let table = value.as_table().ok_or("Failed to parse toml".to_string())?;
+        if !value.is_table() {
+            return Err("Failed to parse toml".to_string());
+        }
+        let contents = toml::to_string(&value).map_err(|e| e.to_string())?;
 
         log::debug!("Assigning glossary entries from toml...");
-        for (key, value) in table {
-            let long = value.get(K_LONG).and_then(|l| l.as_str());
-            let description = value.get(K_DESCRIPTION).and_then(|d| d.as_str());
-            if let Some(long) = long {
-                if let Some(description) = description {
-                    let entry = GlossaryEntry {
-                        description: description.to_string(),
-                        long: long.to_string(),
-                        short: key.clone(),
-                        is_assigned: false,
-                    };
-                    self.add_entry(entry);
-                } else {
-                    log::warn!(
-                        "Failed to parse glossary entry {}: Missing field '{}'",
-                        key,
-                        K_DESCRIPTION
-                    );
-                }
-            } else {
-                log::warn!(
-                    "Failed to parse glossary entry {}: Missing field '{}'",
-                    key,
-                    K_LONG
-                );
-            }
-        }
+        let (entries, issues) = read_glossary(
+            &contents,
+            "toml",
+            &GlossaryFormat::Toml,
+            &GlossaryColumns::default(),
+            b',',
+        );
+        issues.iter().for_each(GlossaryIssue::log);
+        entries.into_iter().for_each(|e| {
+            self.add_entry(e);
+        });
 
         Ok(())
     }
 
+    /// Returns the entry for the key of a reference and if the key is a plural form.
+    /// The plural of an entry is referenced with its plural form or by appending
+    /// an `s` to the key (`~~APIs`).
+    /// Keys are matched exactly first and case insensitive afterwards (`~~api`).
+    fn find_entry(&self, key: &str) -> Option<(Arc<Mutex<GlossaryEntry>>, bool)> {
+        if let Some(entry) = self.entries.get(key) {
+            return Some((Arc::clone(entry), false));
+        }
+        let lower_key = key.to_lowercase();
+        let (entry_key, plural) = self
+            .lookup
+            .get(key)
+            .or_else(|| self.lookup.get(&lower_key))
+            .cloned()
+            .or_else(|| match self.lookup.get(lower_key.strip_suffix('s')?)? {
+                (entry_key, false) => Some((entry_key.clone(), true)),
+                _ => None,
+            })?;
+
+        self.entries
+            .get(&entry_key)
+            .map(|entry| (Arc::clone(entry), plural))
+    }
+
     /// Assignes entries to references
     pub fn assign_entries_to_references(&self) {
         for reference in &self.references {
             let mut reference = reference.lock();
 
-            if let Some(entry) = self.entries.get(&reference.short) {
-                reference.entry = Some(Arc::clone(entry));
+            if let Some((entry, plural)) = self.find_entry(&reference.short) {
+                reference.entry = Some(Arc::clone(&entry));
+                reference.plural = plural;
                 let mut entry = entry.lock();
 
                 if !entry.is_assigned {
                     entry.is_assigned = true;
-                    reference.display = GlossaryDisplay::Long;
+                    if reference.display == GlossaryDisplay::Auto {
+                        reference.display = GlossaryDisplay::FirstUse;
+                    }
                 }
             }
+            if reference.display == GlossaryDisplay::Auto {
+                reference.display = GlossaryDisplay::Short;
+            }
         }
     }
 
-    /// Creates a sorted glossary list from the glossary entries
-    pub fn create_glossary_list(&self) -> List {
-        let mut list = List::new();
+    /// Returns the references that no entry has been assigned to
+    pub fn get_undefined_references(&self) -> Vec<Arc<Mutex<GlossaryReference>>> {
+        self.references
+            .iter()
+            .filter(|r| r.lock().entry.is_none())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the entries that are never referenced sorted by their key
+    pub fn get_unused_entries(&self) -> Vec<Arc<Mutex<GlossaryEntry>>> {
+        let mut entries = self
+            .entries
+            .values()
+            .filter(|e| !e.lock().is_assigned)
+            .cloned()
+            .collect::<Vec<Arc<Mutex<GlossaryEntry>>>>();
+        entries.sort_by_key(|e| e.lock().short.clone());
+
+        entries
+    }
+
+    /// Returns the entries that were replaced because an entry
+    /// with the same key was defined afterwards together with that entry
+    pub fn get_duplicate_entries(&self) -> Vec<(&GlossaryEntry, Arc<Mutex<GlossaryEntry>>)> {
+        self.duplicates
+            .iter()
+            .filter_map(|d| Some((d, Arc::clone(self.entries.get(&d.short)?))))
+            .collect()
+    }
+
+    /// Creates a definition list of the used glossary entries
+    /// that is sorted and grouped by the first letter
+    pub fn create_glossary_list(&self) -> DefinitionList {
+        let mut list = DefinitionList::new();
         let mut entries = self
             .entries
             .values()
@@ -144,44 +253,98 @@ impl GlossaryManager {
         entries.sort_by(|a, b| {
             let a = a.lock();
             let b = b.lock();
-            if a.short > b.short {
-                Ordering::Greater
-            } else if a.short < b.short {
-                Ordering::Less
-            } else {
-                Ordering::Equal
-            }
+            a.short
+                .to_lowercase()
+                .cmp(&b.short.to_lowercase())
+                .then_with(|| a.short.cmp(&b.short))
+                .then_with(|| a.long.cmp(&b.long))
+                .then_with(|| a.description.cmp(&b.description))
         });
         for entry in &entries {
             let entry = entry.lock();
-            let mut line = TextLine::new();
-            line.subtext.push(bold_text!(entry.short.clone()));
-            line.subtext.push(plain_text!(" - ".to_string()));
-            line.subtext.push(italic_text!(entry.long.clone()));
-            line.subtext.push(plain_text!(" - ".to_string()));
-            line.subtext.push(plain_text!(entry.description.clone()));
-            list.add_item(ListItem::new(
-                Line::Anchor(Anchor {
-                    inner: Box::new(Line::Text(line)),
-                    key: entry.short.clone(),
-                }),
-                0,
-                false,
-            ));
+            let mut term = TextLine::new();
+            term.subtext.push(bold_text!(entry.short.clone()));
+            let mut description = TextLine::new();
+            description.subtext.push(italic_text!(entry.long.clone()));
+            if !entry.description.is_empty() {
+                description.subtext.push(plain_text!(" - ".to_string()));
+                description
+                    .subtext
+                    .push(plain_text!(entry.description.clone()));
+            }
+
+            list.add_definition(
+                entry.get_group_title(),
+                Definition::new(entry.short.clone(), term, description),
+            );
         }
 
         list
     }
 }
 
+impl GlossaryEntry {
+    /// Creates a new entry from a map of string values like the metadata of an inline definition
+    pub fn from_string_map(
+        short: String,
+        map: &HashMap<String, String>,
+        position: String,
+    ) -> Result<Self, String> {
+        let long = map
+            .get(K_LONG)
+            .cloned()
+            .ok_or_else(|| format!("Missing field '{}'", K_LONG))?;
+
+        Ok(Self {
+            short,
+            long,
+            description: map.get(K_DESCRIPTION).cloned().unwrap_or_default(),
+            plural: map.get(K_PLURAL).cloned(),
+            long_plural: map.get(K_LONG_PLURAL).cloned(),
+            position: Some(position),
+            is_assigned: false,
+        })
+    }
+
+    /// Returns the position the entry was defined at
+    pub fn get_position(&self) -> String {
+        self.position
+            .clone()
+            .unwrap_or_else(|| "unknown position".to_string())
+    }
+
+    /// Returns the short form in singular or plural
+    pub fn get_short(&self, plural: bool) -> String {
+        if plural {
+            self.plural
+                .clone()
+                .unwrap_or_else(|| format!("{}s", self.short))
+        } else {
+            self.short.clone()
+        }
+    }
+
+    /// Returns the long form in singular or plural
+    pub fn get_long(&self, plural: bool) -> String {
+        if plural {
+            self.long_plural
+                .clone()
+                .unwrap_or_else(|| format!("{}s", self.long))
+        } else {
+            self.long.clone()
+        }
+    }
+
+    /// Returns the title of the group the entry belongs to in the glossary list
+    fn get_group_title(&self) -> String {
+        get_group_title(&self.short)
+    }
+}
+
 impl GlossaryReference {
     /// Creates a new glossary reference
     pub fn new(key: String) -> Self {
-        Self {
-            short: key,
-            display: GlossaryDisplay::Short,
-            entry: None,
-        }
+        Self::with_display(key, GlossaryDisplay::Auto)
     }
 
     /// Creates a new glossary reference with a given display parameter
@@ -190,6 +353,174 @@ impl GlossaryReference {
             short: key,
             display,
             entry: None,
+            plural: false,
+            capitalized: false,
+            first_use_display: "{{long}}".to_string(),
+            position: None,
         }
     }
+
+    /// Returns the text of the reference if an entry has been assigned to it
+    pub fn get_text(&self) -> Option<String> {
+        let entry = self.entry.as_ref()?.lock();
+        let short = entry.get_short(self.plural);
+        let long = entry.get_long(self.plural);
+
+        let text = match self.display {
+            GlossaryDisplay::Auto | GlossaryDisplay::Short => short,
+            GlossaryDisplay::Long => long,
+            GlossaryDisplay::Full => format!("{} ({})", long, short),
+            GlossaryDisplay::FirstUse => {
+                let mut template = PlaceholderTemplate::new(self.first_use_display.clone());
+                let mut value_map = HashMap::new();
+                value_map.insert("short".to_string(), short);
+                value_map.insert("long".to_string(), long);
+                value_map.insert("description".to_string(), entry.description.clone());
+                template.set_replacements(value_map);
+
+                template.render()
+            }
+        };
+        if !self.capitalized {
+            return Some(text);
+        }
+        let mut chars = text.chars();
+
+        Some(
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl GlossaryDisplay {
+    /// Returns the display for the name used in a reference like `~API:full`
+    /// and if the rendered text should be capitalized
+    pub fn from_modifier(modifier: &str) -> Option<(Self, bool)> {
+        let display = match modifier.to_lowercase().as_str() {
+            "short" => GlossaryDisplay::Short,
+            "long" => GlossaryDisplay::Long,
+            "full" => GlossaryDisplay::Full,
+            "first" => GlossaryDisplay::FirstUse,
+            _ => return None,
+        };
+        let capitalized = modifier.starts_with(char::is_uppercase);
+
+        Some((display, capitalized))
+    }
+}
+
+/// Returns the title of the group a term belongs to in a list
+/// that is grouped by the first letter
+pub(crate) fn get_group_title(term: &str) -> String {
+    match term.chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().to_string(),
+        _ => "#".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{parse_test_files, render_html};
+
+    fn create_manager() -> GlossaryManager {
+        let mut manager = GlossaryManager::new();
+        let value = r#"
+            [API]
+            long = "Application Programming Interface"
+            description = "An interface"
+
+            [Index]
+            long = "Index"
+            plural = "Indices"
+            description = "A list"
+        "#
+        .parse::<toml::Value>()
+        .unwrap();
+        manager.assign_from_toml(value).unwrap();
+
+        manager
+    }
+
+    fn get_match(manager: &GlossaryManager, key: &str) -> Option<(String, bool)> {
+        manager
+            .find_entry(key)
+            .map(|(entry, plural)| (entry.lock().short.clone(), plural))
+    }
+
+    #[test]
+    fn it_assigns_entries_from_toml() {
+        let manager = create_manager();
+        assert_eq!(manager.entries.len(), 2);
+        assert_eq!(
+            manager.entries["API"].lock().long,
+            "Application Programming Interface"
+        );
+        assert!(manager
+            .clone()
+            .assign_from_toml(toml::Value::String("API".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn it_finds_plural_and_case_variants() {
+        let manager = create_manager();
+        assert_eq!(get_match(&manager, "API"), Some(("API".to_string(), false)));
+        assert_eq!(get_match(&manager, "api"), Some(("API".to_string(), false)));
+        assert_eq!(get_match(&manager, "APIs"), Some(("API".to_string(), true)));
+        assert_eq!(get_match(&manager, "apis"), Some(("API".to_string(), true)));
+        assert_eq!(
+            get_match(&manager, "Indices"),
+            Some(("Index".to_string(), true))
+        );
+        assert_eq!(
+            get_match(&manager, "Indexs"),
+            Some(("Index".to_string(), true))
+        );
+        assert_eq!(get_match(&manager, "SDK"), None);
+    }
+
+    #[test]
+    fn it_uses_the_last_duplicate_definition() {
+        let document = parse_test_files(
+            "glossary-duplicates",
+            &[(
+                "main.sd",
+                "~~API[long=\"First\"]\n\n~~API[long=\"Second\"]\n",
+            )],
+        );
+        let glossary = document.glossary.lock();
+        assert_eq!(glossary.entries["API"].lock().long, "Second");
+
+        let duplicates = glossary.get_duplicate_entries();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].0.long, "First");
+        assert_eq!(glossary.find_entry("api").unwrap().0.lock().long, "Second");
+    }
+
+    #[test]
+    fn it_ends_keys_before_brackets() {
+        let document = parse_test_files(
+            "glossary-brackets",
+            &[(
+                "main.sd",
+                "~~API[long=\"Interface\"]\n\nSee ~~API[1] and ~~APIs.\n",
+            )],
+        );
+        let html = render_html(&document);
+        assert!(html.contains("[1] and"), "{}", html);
+
+        let glossary = document.glossary.lock();
+        assert_eq!(glossary.entries.len(), 1);
+        assert!(glossary.get_duplicate_entries().is_empty());
+        assert!(glossary.get_undefined_references().is_empty());
+        assert_eq!(glossary.references.len(), 3);
+        assert!(glossary
+            .references
+            .iter()
+            .all(|r| r.lock().short == "API" || r.lock().short == "APIs"));
+    }
 }
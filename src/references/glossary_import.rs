@@ -0,0 +1,516 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::references::glossary::{
+    GlossaryEntry, K_DESCRIPTION, K_LONG, K_LONG_PLURAL, K_PLURAL, K_SHORT,
+};
+use crate::utils::parsing::get_toml_line;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::path::Path;
+
+/// The import argument that selects the format of a glossary file
+pub(crate) const A_FORMAT: &str = "format";
+/// The import argument that sets the field delimiter of a csv glossary
+pub(crate) const A_DELIMITER: &str = "delimiter";
+
+/// The file format of an imported glossary
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlossaryFormat {
+    Toml,
+    Csv,
+    Json,
+}
+
+/// The names of the columns or keys the glossary fields are read from
+#[derive(Clone, Debug)]
+pub struct GlossaryColumns {
+    pub short: String,
+    pub long: String,
+    pub description: String,
+    pub plural: String,
+    pub long_plural: String,
+}
+
+/// An issue found while importing a glossary file
+#[derive(Clone, Debug)]
+pub struct GlossaryIssue {
+    pub file: String,
+    pub row: Option<usize>,
+    pub message: String,
+}
+
+/// The fields of a glossary entry read from a file with the row they start at
+struct GlossaryRecord {
+    row: Option<usize>,
+    fields: HashMap<String, String>,
+}
+
+impl GlossaryFormat {
+    /// Returns the format from the format argument of the import
+    /// or guessed from the file extension
+    pub fn from_path(path: &Path, format: Option<&str>) -> Self {
+        let format = format
+            .map(|f| f.to_lowercase())
+            .or_else(|| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.to_lowercase())
+            })
+            .unwrap_or_default();
+
+        match format.as_str() {
+            "csv" => GlossaryFormat::Csv,
+            "json" => GlossaryFormat::Json,
+            _ => GlossaryFormat::Toml,
+        }
+    }
+}
+
+impl Default for GlossaryColumns {
+    fn default() -> Self {
+        Self {
+            short: K_SHORT.to_string(),
+            long: K_LONG.to_string(),
+            description: K_DESCRIPTION.to_string(),
+            plural: K_PLURAL.to_string(),
+            long_plural: K_LONG_PLURAL.to_string(),
+        }
+    }
+}
+
+impl GlossaryColumns {
+    /// Creates the column mapping with the values of the given map
+    /// that are keyed by the name of the glossary field
+    pub fn from_map(map: &HashMap<String, String>) -> Self {
+        let mut columns = Self::default();
+        columns.update(map);
+
+        columns
+    }
+
+    /// Overrides the columns of the fields that are keys of the given map
+    pub fn update(&mut self, map: &HashMap<String, String>) {
+        for (field, column) in self.get_columns_mut() {
+            if let Some(value) = map.get(field) {
+                *column = value.clone();
+            }
+        }
+    }
+
+    fn get_columns(&self) -> Vec<(&'static str, &String)> {
+        vec![
+            (K_SHORT, &self.short),
+            (K_LONG, &self.long),
+            (K_DESCRIPTION, &self.description),
+            (K_PLURAL, &self.plural),
+            (K_LONG_PLURAL, &self.long_plural),
+        ]
+    }
+
+    fn get_columns_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        vec![
+            (K_SHORT, &mut self.short),
+            (K_LONG, &mut self.long),
+            (K_DESCRIPTION, &mut self.description),
+            (K_PLURAL, &mut self.plural),
+            (K_LONG_PLURAL, &mut self.long_plural),
+        ]
+    }
+
+    /// Returns the glossary fields of a record whose values are looked up by column name
+    fn map_fields<F>(&self, get_value: F) -> HashMap<String, String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.get_columns()
+            .into_iter()
+            .filter_map(|(field, column)| {
+                get_value(column)
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| (field.to_string(), v))
+            })
+            .collect()
+    }
+}
+
+impl Display for GlossaryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(row) = self.row {
+            write!(f, "{}\n\t--> {}:{}\n", self.message, self.file, row)
+        } else {
+            write!(f, "{}\n\t--> {}\n", self.message, self.file)
+        }
+    }
+}
+
+impl GlossaryIssue {
+    fn new(file: &str, row: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_string(),
+            row,
+            message,
+        }
+    }
+
+    /// Logs the issue as an error
+    pub fn log(&self) {
+        log::error!("{}", self);
+    }
+}
+
+/// Reads the glossary entries of a file.
+/// Entries with missing fields are skipped and returned as issues
+/// together with the row of the file they were found in
+pub fn read_glossary(
+    contents: &str,
+    source: &str,
+    format: &GlossaryFormat,
+    columns: &GlossaryColumns,
+    delimiter: u8,
+) -> (Vec<GlossaryEntry>, Vec<GlossaryIssue>) {
+    let mut issues = Vec::new();
+    let records = match format {
+        GlossaryFormat::Toml => read_toml_records(contents, source, columns),
+        GlossaryFormat::Json => read_json_records(contents, source, columns),
+        GlossaryFormat::Csv => read_csv_records(contents, source, columns, delimiter, &mut issues),
+    };
+    let records = match records {
+        Ok(records) => records,
+        Err(issue) => {
+            issues.push(issue);
+            return (Vec::new(), issues);
+        }
+    };
+    let mut entries = Vec::new();
+
+    for record in records {
+        match create_entry(record, source) {
+            Ok(entry) => entries.push(entry),
+            Err(issue) => issues.push(issue),
+        }
+    }
+
+    (entries, issues)
+}
+
+/// Creates a glossary entry from a record if it contains all required fields
+fn create_entry(record: GlossaryRecord, source: &str) -> Result<GlossaryEntry, GlossaryIssue> {
+    let short = record.fields.get(K_SHORT).cloned().ok_or_else(|| {
+        GlossaryIssue::new(
+            source,
+            record.row,
+            format!(
+                "Failed to import glossary entry: Missing field '{}'",
+                K_SHORT
+            ),
+        )
+    })?;
+    let position = match record.row {
+        Some(row) => format!("{}:{}", source, row),
+        None => source.to_string(),
+    };
+
+    GlossaryEntry::from_string_map(short.clone(), &record.fields, position).map_err(|e| {
+        GlossaryIssue::new(
+            source,
+            record.row,
+            format!("Failed to import glossary entry '{}': {}", short, e),
+        )
+    })
+}
+
+/// Reads the records of a toml glossary that is keyed by the short form of the entries
+fn read_toml_records(
+    contents: &str,
+    source: &str,
+    columns: &GlossaryColumns,
+) -> Result<Vec<GlossaryRecord>, GlossaryIssue> {
+    let value = contents.parse::<toml::Value>().map_err(|e| {
+        GlossaryIssue::new(
+            source,
+            e.line_col().map(|(l, _)| l + 1),
+            format!("Failed to parse glossary: {}", e),
+        )
+    })?;
+    let table = value.as_table().ok_or_else(|| {
+        GlossaryIssue::new(
+            source,
+            None,
+            "Failed to parse glossary: Expected a table".to_string(),
+        )
+    })?;
+
+    Ok(table
+        .iter()
+        .map(|(key, value)| {
+            let mut fields = columns.map_fields(|column| {
+                value
+                    .get(column)
+                    .and_then(|v| v.as_str())
+                    .map(|v| v.to_string())
+            });
+            fields
+                .entry(K_SHORT.to_string())
+                .or_insert_with(|| key.clone());
+
+            GlossaryRecord {
+                row: get_toml_line(contents, key),
+                fields,
+            }
+        })
+        .collect())
+}
+
+/// Reads the records of a json glossary that is either an object keyed by
+/// the short form of the entries or an array of entries.
+/// The json parser doesn't report the positions of values so only syntax errors have a line
+fn read_json_records(
+    contents: &str,
+    source: &str,
+    columns: &GlossaryColumns,
+) -> Result<Vec<GlossaryRecord>, GlossaryIssue> {
+    let value = serde_json::from_str::<serde_json::Value>(contents).map_err(|e| {
+        GlossaryIssue::new(
+            source,
+            Some(e.line()),
+            format!("Failed to parse glossary: {}", e),
+        )
+    })?;
+    let get_fields = |value: &serde_json::Value| {
+        columns.map_fields(|column| match value.get(column) {
+            Some(serde_json::Value::String(s)) => Some(s.clone()),
+            Some(serde_json::Value::Null) | None => None,
+            Some(v) => Some(v.to_string()),
+        })
+    };
+
+    match value {
+        serde_json::Value::Object(map) => Ok(map
+            .iter()
+            .map(|(key, value)| {
+                let mut fields = get_fields(value);
+                fields
+                    .entry(K_SHORT.to_string())
+                    .or_insert_with(|| key.clone());
+
+                GlossaryRecord { row: None, fields }
+            })
+            .collect()),
+        serde_json::Value::Array(values) => Ok(values
+            .iter()
+            .map(|value| GlossaryRecord {
+                row: None,
+                fields: get_fields(value),
+            })
+            .collect()),
+        _ => Err(GlossaryIssue::new(
+            source,
+            None,
+            "Failed to parse glossary: Expected an object or an array".to_string(),
+        )),
+    }
+}
+
+/// Reads the records of a csv glossary with a header row that contains the column names
+fn read_csv_records(
+    contents: &str,
+    source: &str,
+    columns: &GlossaryColumns,
+    delimiter: u8,
+    issues: &mut Vec<GlossaryIssue>,
+) -> Result<Vec<GlossaryRecord>, GlossaryIssue> {
+    let csv_error = |e: csv::Error| {
+        GlossaryIssue::new(
+            source,
+            e.position().map(|p| p.line() as usize),
+            format!("Failed to parse glossary: {}", e),
+        )
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let header_index: HashMap<&str, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, name)| (name.trim(), index))
+        .collect();
+
+    let missing_columns = columns
+        .get_columns()
+        .into_iter()
+        .filter(|(field, column)| {
+            (*field == K_SHORT || *field == K_LONG) && !header_index.contains_key(column.as_str())
+        })
+        .map(|(field, column)| {
+            GlossaryIssue::new(
+                source,
+                Some(1),
+                format!(
+                    "The column '{}' of the glossary field '{}' doesn't exist",
+                    column, field
+                ),
+            )
+        })
+        .collect::<Vec<GlossaryIssue>>();
+    if !missing_columns.is_empty() {
+        issues.extend(missing_columns);
+        return Ok(Vec::new());
+    }
+    let mut records = Vec::new();
+
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let fields = columns.map_fields(|column| {
+            header_index
+                .get(column)
+                .and_then(|index| record.get(*index))
+                .map(|v| v.to_string())
+        });
+        if fields.is_empty() {
+            continue;
+        }
+        records.push(GlossaryRecord {
+            row: record.position().map(|p| p.line() as usize),
+            fields,
+        });
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(
+        contents: &str,
+        format: GlossaryFormat,
+        columns: &GlossaryColumns,
+        delimiter: u8,
+    ) -> (Vec<GlossaryEntry>, Vec<GlossaryIssue>) {
+        read_glossary(contents, "glossary", &format, columns, delimiter)
+    }
+
+    #[test]
+    fn it_imports_csv_glossaries() {
+        let contents = "Abbreviation;Meaning;description\n\
+            API;Application Programming Interface;\"An interface; for programs\"\n\
+            ;;\n\
+            SDK;;A kit\n";
+        let columns = GlossaryColumns::from_map(&maplit::hashmap! {
+            K_SHORT.to_string() => "Abbreviation".to_string(),
+            K_LONG.to_string() => "Meaning".to_string(),
+        });
+        let (entries, issues) = read(contents, GlossaryFormat::Csv, &columns, b';');
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].short, "API");
+        assert_eq!(entries[0].description, "An interface; for programs");
+        assert_eq!(entries[0].get_position(), "glossary:2");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, Some(4));
+        assert!(issues[0].message.contains("'SDK'"));
+    }
+
+    #[test]
+    fn it_reports_missing_csv_columns() {
+        let (entries, issues) = read(
+            "short,meaning\nAPI,Interface\n",
+            GlossaryFormat::Csv,
+            &GlossaryColumns::default(),
+            b',',
+        );
+
+        assert!(entries.is_empty());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, Some(1));
+        assert!(issues[0].message.contains("'long'"));
+    }
+
+    #[test]
+    fn it_imports_json_glossaries() {
+        let object = r#"{
+            "API": {"long": "Application {Programming} Interface", "plural": "APIs"},
+            "SDK": {"description": "A kit"}
+        }"#;
+        let (mut entries, issues) = read(
+            object,
+            GlossaryFormat::Json,
+            &GlossaryColumns::default(),
+            b',',
+        );
+        entries.sort_by(|a, b| a.short.cmp(&b.short));
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].long, "Application {Programming} Interface");
+        assert_eq!(entries[0].plural, Some("APIs".to_string()));
+        assert_eq!(entries[0].get_position(), "glossary");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, None);
+
+        let array = r#"[{"short": "API", "long": "Interface", "description": 1}]"#;
+        let (entries, issues) = read(
+            array,
+            GlossaryFormat::Json,
+            &GlossaryColumns::default(),
+            b',',
+        );
+        assert!(issues.is_empty());
+        assert_eq!(entries[0].short, "API");
+        assert_eq!(entries[0].description, "1");
+    }
+
+    #[test]
+    fn it_reports_json_syntax_errors() {
+        let (entries, issues) = read(
+            "{\n  \"API\": {\"long\": \"Interface\"},\n  \"SDK\": \n}",
+            GlossaryFormat::Json,
+            &GlossaryColumns::default(),
+            b',',
+        );
+
+        assert!(entries.is_empty());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, Some(4));
+    }
+
+    #[test]
+    fn it_imports_toml_glossaries() {
+        let contents = "[API]\nlong = \"Interface\"\n\n[SDK]\ndescription = \"A kit\"\n";
+        let (entries, issues) = read(
+            contents,
+            GlossaryFormat::Toml,
+            &GlossaryColumns::default(),
+            b',',
+        );
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].short, "API");
+        assert_eq!(entries[0].get_position(), "glossary:1");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].row, Some(4));
+    }
+
+    #[test]
+    fn it_guesses_the_format() {
+        assert_eq!(
+            GlossaryFormat::from_path(Path::new("terms.CSV"), None),
+            GlossaryFormat::Csv
+        );
+        assert_eq!(
+            GlossaryFormat::from_path(Path::new("terms.txt"), Some("json")),
+            GlossaryFormat::Json
+        );
+        assert_eq!(
+            GlossaryFormat::from_path(Path::new("terms"), None),
+            GlossaryFormat::Toml
+        );
+    }
+}
@@ -0,0 +1,525 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::tokens::INDEX_SUB;
+use crate::elements::{
+    Block, BoldText, Definition, DefinitionList, Inline, ItalicText, Line, ListItem, PlainText,
+    TextLine, Url,
+};
+use crate::references::glossary::get_group_title;
+use crate::utils::localisation::{Localisation, L_INDEX_SEE, L_INDEX_SEE_ALSO};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::bold_text;
+use crate::italic_text;
+use crate::plain_text;
+
+const K_SEE: &str = "see";
+const K_ALSO: &str = "also";
+
+type IndexEntryRef = Arc<RwLock<IndexEntry>>;
+type SortKey = String;
+
+/// A term that is marked in the text to be listed in the index
+#[derive(Clone, Debug)]
+pub struct IndexEntry {
+    pub(crate) term: String,
+    pub(crate) sub_term: Option<String>,
+    pub(crate) cross_reference: Option<IndexCrossReference>,
+    pub(crate) id: Option<String>,
+}
+
+/// A reference from an index term to another term
+#[derive(Clone, Debug, PartialEq)]
+pub enum IndexCrossReference {
+    See(String),
+    SeeAlso(String),
+}
+
+/// An occurrence of an index term with the title of the enclosing section
+struct IndexOccurrence {
+    entry: IndexEntryRef,
+    section: Option<TextLine>,
+}
+
+/// A term of the index with the places it occurs at
+struct IndexTerm {
+    name: String,
+    occurrences: Vec<(String, Option<TextLine>)>,
+    cross_references: Vec<IndexCrossReference>,
+    sub_terms: BTreeMap<SortKey, IndexTerm>,
+}
+
+impl IndexEntry {
+    pub fn new(
+        term: String,
+        sub_term: Option<String>,
+        cross_reference: Option<IndexCrossReference>,
+    ) -> Self {
+        Self {
+            term,
+            sub_term,
+            cross_reference,
+            id: None,
+        }
+    }
+}
+
+impl IndexCrossReference {
+    /// Parses a cross reference in the form `see <term>` or `see also <term>`
+    pub fn from_string(value: &str) -> Option<Self> {
+        let (keyword, target) = value.trim().split_once(char::is_whitespace)?;
+        if !keyword.eq_ignore_ascii_case(K_SEE) {
+            return None;
+        }
+        let target = target.trim();
+
+        match target.split_once(char::is_whitespace) {
+            Some((also, target)) if also.eq_ignore_ascii_case(K_ALSO) => {
+                Some(IndexCrossReference::SeeAlso(target.trim().to_string()))
+            }
+            _ => Some(IndexCrossReference::See(target.to_string())),
+        }
+    }
+
+    /// Returns the term that is referenced
+    fn get_target(&self) -> &str {
+        match self {
+            IndexCrossReference::See(target) | IndexCrossReference::SeeAlso(target) => target,
+        }
+    }
+}
+
+impl IndexTerm {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            occurrences: Vec::new(),
+            cross_references: Vec::new(),
+            sub_terms: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the definition list entry of the term with its sub terms as children
+    fn get_definition(
+        &self,
+        parent: Option<&str>,
+        keys: &HashSet<String>,
+        l10n: &Localisation,
+    ) -> Definition {
+        let key = match parent {
+            Some(parent) => get_index_key(parent, Some(&self.name)),
+            None => get_index_key(&self.name, None),
+        };
+        let mut term = TextLine::new();
+        if parent.is_some() {
+            term.subtext.push(plain_text!(self.name.clone()));
+        } else {
+            term.subtext.push(bold_text!(self.name.clone()));
+        }
+        let mut description = TextLine::new();
+
+        for (index, (id, section)) in self.occurrences.iter().enumerate() {
+            if index > 0 {
+                description.subtext.push(plain_text!(", ".to_string()));
+            }
+            let text = match section {
+                Some(section) => section.subtext.clone(),
+                None => vec![plain_text!((index + 1).to_string())],
+            };
+            description
+                .subtext
+                .push(Inline::Url(Url::new(Some(text), format!("#{}", id))));
+        }
+        for cross_reference in &self.cross_references {
+            if !description.subtext.is_empty() {
+                description.subtext.push(plain_text!("; ".to_string()));
+            }
+            let keyword = match cross_reference {
+                IndexCrossReference::See(_) => l10n.get(L_INDEX_SEE),
+                IndexCrossReference::SeeAlso(_) => l10n.get(L_INDEX_SEE_ALSO),
+            };
+            description.subtext.push(italic_text!(keyword));
+            description.subtext.push(plain_text!(" ".to_string()));
+
+            let (target, target_sub) = split_term(cross_reference.get_target());
+            let target_key = get_index_key(target, target_sub);
+            let text = match target_sub {
+                Some(sub) => format!("{}, {}", target, sub),
+                None => target.to_string(),
+            };
+            if keys.contains(&target_key) {
+                description.subtext.push(Inline::Url(Url::new(
+                    Some(vec![plain_text!(text)]),
+                    format!("#{}", target_key),
+                )));
+            } else {
+                log::warn!(
+                    "The index term '{}' references the unknown term '{}'",
+                    self.name,
+                    cross_reference.get_target()
+                );
+                description.subtext.push(plain_text!(text));
+            }
+        }
+        let mut definition = Definition::new(key, term, description);
+        definition.children = self
+            .sub_terms
+            .values()
+            .map(|sub| sub.get_definition(Some(&self.name), keys, l10n))
+            .collect();
+
+        definition
+    }
+}
+
+pub trait GetIndexEntries {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef>;
+}
+
+impl GetIndexEntries for Inline {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef> {
+        match self {
+            Inline::IndexEntry(entry) => vec![Arc::clone(entry)],
+            Inline::Bold(bo) => bo
+                .value
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Inline::Italic(it) => it
+                .value
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Inline::Underlined(un) => un
+                .value
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Inline::Striked(st) => st
+                .value
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Inline::Superscript(sup) => sup
+                .value
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Inline::Colored(col) => col.value.get_index_entries(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl GetIndexEntries for TextLine {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef> {
+        self.subtext
+            .iter()
+            .flat_map(|i| i.get_index_entries())
+            .collect()
+    }
+}
+
+impl GetIndexEntries for Line {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef> {
+        match self {
+            Line::Text(text) => text.get_index_entries(),
+            Line::Centered(center) => center.line.get_index_entries(),
+            Line::Anchor(anchor) => anchor.inner.get_index_entries(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl GetIndexEntries for ListItem {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef> {
+        let mut entries = self.text.get_index_entries();
+        entries.append(
+            &mut self
+                .children
+                .iter()
+                .flat_map(|child| child.get_index_entries())
+                .collect(),
+        );
+
+        entries
+    }
+}
+
+impl GetIndexEntries for Block {
+    fn get_index_entries(&self) -> Vec<IndexEntryRef> {
+        match self {
+            Block::Section(sec) => sec
+                .header
+                .line
+                .get_index_entries()
+                .into_iter()
+                .chain(sec.elements.iter().flat_map(|b| b.get_index_entries()))
+                .collect(),
+            Block::Paragraph(par) => par
+                .elements
+                .iter()
+                .flat_map(|l| l.get_index_entries())
+                .collect(),
+            Block::List(list) => list
+                .items
+                .iter()
+                .flat_map(|i| i.get_index_entries())
+                .collect(),
+            Block::Quote(q) => q.text.iter().flat_map(|t| t.get_index_entries()).collect(),
+            Block::Table(table) => std::iter::once(&table.header)
+                .chain(table.rows.iter())
+                .flat_map(|row| row.cells.iter())
+                .flat_map(|cell| cell.text.get_index_entries())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Creates the index of all marked terms in the given blocks.
+/// The terms are sorted alphabetically, grouped by their first letter
+/// and link to every place they occur at
+pub fn create_index_list(blocks: &[Block], l10n: &Localisation) -> DefinitionList {
+    let mut terms: BTreeMap<SortKey, IndexTerm> = BTreeMap::new();
+    let mut keys = HashSet::new();
+
+    for occurrence in get_index_occurrences(blocks, None) {
+        let entry = occurrence.entry.read().unwrap();
+        let mut term = terms
+            .entry(get_sort_key(&entry.term))
+            .or_insert_with(|| IndexTerm::new(entry.term.clone()));
+        keys.insert(get_index_key(&entry.term, None));
+
+        if let Some(sub_term) = &entry.sub_term {
+            term = term
+                .sub_terms
+                .entry(get_sort_key(sub_term))
+                .or_insert_with(|| IndexTerm::new(sub_term.clone()));
+            keys.insert(get_index_key(&entry.term, Some(sub_term)));
+        }
+        if let Some(cross_reference) = &entry.cross_reference {
+            if !term.cross_references.contains(cross_reference) {
+                term.cross_references.push(cross_reference.clone());
+            }
+        } else if let Some(id) = &entry.id {
+            term.occurrences.push((id.clone(), occurrence.section));
+        }
+    }
+    let mut list = DefinitionList::new();
+
+    for term in terms.values() {
+        list.add_definition(
+            get_group_title(&term.name),
+            term.get_definition(None, &keys, l10n),
+        );
+    }
+
+    list
+}
+
+/// Returns all index entries of the blocks with the title of the section they are in
+fn get_index_occurrences(blocks: &[Block], section: Option<&TextLine>) -> Vec<IndexOccurrence> {
+    let mut occurrences = Vec::new();
+
+    for block in blocks {
+        if let Block::Section(sec) = block {
+            let title = sec
+                .header
+                .line
+                .as_raw_text()
+                .subtext
+                .iter()
+                .map(|i| i.as_plain_text().value)
+                .collect::<String>();
+            let title_line = if title.trim().is_empty() {
+                None
+            } else {
+                let mut line = TextLine::new();
+                line.subtext.push(plain_text!(title.trim().to_string()));
+                Some(line)
+            };
+            occurrences.extend(
+                sec.header
+                    .line
+                    .get_index_entries()
+                    .into_iter()
+                    .map(|entry| IndexOccurrence {
+                        entry,
+                        section: title_line.clone(),
+                    }),
+            );
+            occurrences.append(&mut get_index_occurrences(
+                &sec.elements,
+                title_line.as_ref(),
+            ));
+        } else {
+            occurrences.extend(block.get_index_entries().into_iter().map(|entry| {
+                IndexOccurrence {
+                    entry,
+                    section: section.cloned(),
+                }
+            }));
+        }
+    }
+
+    occurrences
+}
+
+/// Splits a term of the form `term!sub` into the term and the sub term
+pub(crate) fn split_term(value: &str) -> (&str, Option<&str>) {
+    match value.split_once(INDEX_SUB) {
+        Some((term, sub_term)) => (term.trim(), Some(sub_term.trim())),
+        None => (value.trim(), None),
+    }
+}
+
+/// Returns the anchor of a term in the index
+fn get_index_key(term: &str, sub_term: Option<&str>) -> String {
+    let mut key = format!("index-{}", get_slug(term));
+    if let Some(sub_term) = sub_term {
+        key.push('-');
+        key.push_str(&get_slug(sub_term));
+    }
+
+    key
+}
+
+/// Returns a lowercase version of the value that can be used in urls.
+/// Whitespace is replaced with dashes and other special characters with their code
+fn get_slug(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .map(|c| {
+                    if c.is_alphanumeric() {
+                        c.to_string()
+                    } else {
+                        format!("_{:x}", c as u32)
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Returns the key terms are sorted and merged by so that
+/// terms that only differ in case are listed as one
+fn get_sort_key(term: &str) -> SortKey {
+    term.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::parse_test_files;
+
+    fn get_text(line: &TextLine) -> String {
+        get_inline_text(&line.subtext)
+    }
+
+    fn get_inline_text(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Plain(plain) => plain.value.clone(),
+                Inline::Bold(bold) => get_inline_text(&bold.value),
+                Inline::Italic(italic) => get_inline_text(&italic.value),
+                Inline::Url(url) => get_inline_text(url.description.as_deref().unwrap_or(&[])),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    fn get_index(name: &str, text: &str) -> DefinitionList {
+        let document = parse_test_files(name, &[("main.sd", text)]);
+        let l10n = document.config.lock().get_localisation();
+
+        create_index_list(&document.elements, &l10n)
+    }
+
+    #[test]
+    fn it_parses_index_entries() {
+        let document = parse_test_files(
+            "index-entries",
+            &[(
+                "main.sd",
+                "[!Rust] [! Rust ! ownership ] [!Borrowing|see Rust!ownership] \
+                [!Cargo|see also Rust] [!Crate|look Rust]\n",
+            )],
+        );
+        let entries: Vec<IndexEntry> = document
+            .elements
+            .iter()
+            .flat_map(|b| b.get_index_entries())
+            .map(|e| e.read().unwrap().clone())
+            .collect();
+
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].term, "Rust");
+        assert_eq!(entries[0].sub_term, None);
+        assert_eq!(entries[1].term, "Rust");
+        assert_eq!(entries[1].sub_term, Some("ownership".to_string()));
+        assert_eq!(
+            entries[2].cross_reference,
+            Some(IndexCrossReference::See("Rust!ownership".to_string()))
+        );
+        assert_eq!(
+            entries[3].cross_reference,
+            Some(IndexCrossReference::SeeAlso("Rust".to_string()))
+        );
+        assert!(entries.iter().all(|e| e.id.is_some()));
+    }
+
+    #[test]
+    fn it_sorts_and_groups_terms() {
+        let index = get_index(
+            "index-sorting",
+            "# Zoo\n\n[!zebra] [!Apple] [!apple!pie] [!banana]\n\n# Kitchen\n\n[!apple]\n",
+        );
+        let titles: Vec<&str> = index.groups.iter().map(|g| g.title.as_str()).collect();
+        assert_eq!(titles, vec!["A", "B", "Z"]);
+
+        let apple = &index.groups[0].definitions[0];
+        assert_eq!(index.groups[0].definitions.len(), 1);
+        assert_eq!(get_text(&apple.term), "Apple");
+        assert_eq!(get_text(&apple.description), "Zoo, Kitchen");
+        assert_eq!(apple.children.len(), 1);
+        assert_eq!(get_text(&apple.children[0].term), "pie");
+        assert_eq!(apple.children[0].key, "index-apple-pie");
+    }
+
+    #[test]
+    fn it_adds_cross_references() {
+        let index = get_index(
+            "index-see",
+            "[!Rust!ownership] [!Borrowing|see Rust!ownership] [!Cargo|see also Rust] \
+            [!Crate|see Unknown]\n",
+        );
+        let descriptions: Vec<String> = index
+            .groups
+            .iter()
+            .flat_map(|g| g.definitions.iter())
+            .map(|d| get_text(&d.description))
+            .collect();
+
+        assert_eq!(
+            descriptions,
+            vec!["see Rust, ownership", "see also Rust", "see Unknown", ""]
+        );
+        let borrowing = &index.groups[0].definitions[0].description.subtext;
+        assert!(
+            matches!(borrowing.last(), Some(Inline::Url(url)) if url.url == "#index-rust-ownership")
+        );
+        let crate_term = &index.groups[1].definitions[1].description.subtext;
+        assert!(matches!(crate_term.last(), Some(Inline::Plain(_))));
+    }
+}
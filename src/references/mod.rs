@@ -5,6 +5,12 @@
  */
 
 pub mod bibliography;
+pub mod bibtex;
+pub mod citation_styles;
+pub mod citations;
+pub mod cross_references;
 pub mod glossary;
+pub mod glossary_import;
+pub mod index;
 pub mod placeholders;
 pub mod templates;
@@ -5,9 +5,20 @@
  */
 
 use crate::elements::*;
-use crate::references::bibliography::create_bib_list;
-use chrono::prelude::*;
+use crate::references::bibliography::{add_bib_list_links, create_bib_list};
+use crate::references::citation_styles::sort_bib_entries;
+use crate::references::citations::{assign_citation_scopes, get_citation_list};
+use crate::references::index::create_index_list;
+use crate::settings::formatting_settings::{DEFAULT_DATE_FORMAT, DEFAULT_TIME_FORMAT};
+use crate::settings::style_settings::StyleSettings;
+use crate::utils::dates::DateFormatter;
+use crate::utils::localisation::{L_GLOSSARY_TITLE, L_INDEX_TITLE, L_TOC_TITLE};
+use bibliographix::bibliography::bibliography_entry::BibliographyEntryReference;
 use regex::Regex;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::plain_text;
 
 macro_rules! block {
     ($inner:expr) => {
@@ -31,13 +42,21 @@ macro_rules! inline {
 pub(crate) trait ProcessPlaceholders {
     fn process_placeholders(&mut self);
     fn process_definitions(&mut self);
+    fn process_variables(&mut self);
+    fn process_citation_scopes(&mut self);
 }
 
 const S_VALUE: &str = "value";
+const S_SCOPE: &str = "scope";
+const S_BACKREFS: &str = "backrefs";
+const S_TITLE: &str = "title";
+
+const V_SCOPE_SECTION: &str = "section";
 
 const P_TOC: &str = "toc";
 const P_BIB: &str = "bib";
 const P_GLS: &str = "gls";
+const P_INDEX: &str = "index";
 const P_DATE: &str = "date";
 const P_TIME: &str = "time";
 const P_DATETIME: &str = "datetime";
@@ -47,6 +66,7 @@ const P_TITLE: &str = "title";
 impl ProcessPlaceholders for Document {
     /// parses all placeholders and assigns values to them
     fn process_placeholders(&mut self) {
+        let mut scope_count = 0;
         self.placeholders.iter().for_each(|p| {
             let mut pholder = p.write().unwrap();
             match pholder.name.to_lowercase().as_str() {
@@ -56,23 +76,98 @@ impl ProcessPlaceholders for Document {
                     } else {
                         false
                     };
-                    pholder.set_value(block!(Block::List(self.create_toc(ordered))))
+                    let title = self.config.lock().get_localisation().get(L_TOC_TITLE);
+                    let toc = Block::List(self.create_toc(ordered));
+                    let value = block!(with_title(toc, &pholder.metadata, title, P_TOC));
+                    pholder.set_value(value)
+                }
+                P_BIB => {
+                    let (style, dates, l10n) = {
+                        let config = self.config.lock();
+                        (
+                            config.style.clone(),
+                            DateFormatter::new(
+                                &config.formatting.date_format,
+                                &config.metadata.language,
+                            ),
+                            config.get_localisation(),
+                        )
+                    };
+                    let back_references = match &pholder.metadata {
+                        Some(meta) if meta.data.contains_key(S_BACKREFS) => {
+                            meta.get_bool(S_BACKREFS)
+                        }
+                        _ => style.bib_back_references,
+                    };
+                    let list = if let Some(citations) = &pholder.citation_scope {
+                        scope_count += 1;
+                        let scope = format!("bib{}", scope_count);
+                        let mut list = create_bib_list(
+                            assign_scoped_citations(citations, &scope, &style),
+                            &style,
+                            &dates,
+                            &l10n,
+                        );
+                        let back_references = if back_references {
+                            get_back_references(citations)
+                        } else {
+                            HashMap::new()
+                        };
+                        add_bib_list_links(&mut list, Some(&scope), &back_references, &l10n);
+                        list
+                    } else {
+                        let mut list = create_bib_list(
+                            self.bibliography.get_entry_list_by_occurrence(),
+                            &style,
+                            &dates,
+                            &l10n,
+                        );
+                        if back_references {
+                            add_bib_list_links(
+                                &mut list,
+                                None,
+                                &get_back_references(&get_citation_list(&self.elements)),
+                                &l10n,
+                            );
+                        }
+                        list
+                    };
+                    pholder.set_value(block!(Block::List(list)))
+                }
+                P_GLS => {
+                    let title = self.config.lock().get_localisation().get(L_GLOSSARY_TITLE);
+                    let list = Block::DefinitionList(self.glossary.lock().create_glossary_list());
+                    let value = block!(with_title(list, &pholder.metadata, title, P_GLS));
+                    pholder.set_value(value)
+                }
+                P_INDEX => {
+                    let l10n = self.config.lock().get_localisation();
+                    let list = Block::DefinitionList(create_index_list(&self.elements, &l10n));
+                    let title = l10n.get(L_INDEX_TITLE);
+                    let value = block!(with_title(list, &pholder.metadata, title, P_INDEX));
+                    pholder.set_value(value)
+                }
+                P_DATE => {
+                    let format = self.config.lock().formatting.date_format.clone();
+                    let value =
+                        self.get_timestamp_string(format, DEFAULT_DATE_FORMAT, &pholder.metadata);
+                    pholder.set_value(inline!(Inline::Plain(PlainText { value })))
+                }
+                P_TIME => {
+                    let format = self.config.lock().formatting.time_format.clone();
+                    let value =
+                        self.get_timestamp_string(format, DEFAULT_TIME_FORMAT, &pholder.metadata);
+                    pholder.set_value(inline!(Inline::Plain(PlainText { value })))
+                }
+                P_DATETIME => {
+                    let format = self.config.lock().formatting.get_datetime_format();
+                    let value = self.get_timestamp_string(
+                        format,
+                        &format!("{} {}", DEFAULT_DATE_FORMAT, DEFAULT_TIME_FORMAT),
+                        &pholder.metadata,
+                    );
+                    pholder.set_value(inline!(Inline::Plain(PlainText { value })))
                 }
-                P_BIB => pholder.set_value(block!(Block::List(create_bib_list(
-                    self.bibliography.get_entry_list_by_occurrence()
-                )))),
-                P_GLS => pholder.set_value(block!(Block::List(
-                    self.glossary.lock().create_glossary_list()
-                ))),
-                P_DATE => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: get_date_string()
-                }))),
-                P_TIME => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: get_time_string()
-                }))),
-                P_DATETIME => pholder.set_value(inline!(Inline::Plain(PlainText {
-                    value: format!("{} {}", get_date_string(), get_time_string())
-                }))),
                 P_AUTHOR => {
                     if let Some(value) = self.config.lock().metadata.author.clone() {
                         pholder.set_value(inline!(Inline::Plain(PlainText { value })))
@@ -124,14 +219,139 @@ impl ProcessPlaceholders for Document {
             }
         });
     }
+
+    /// Assigns the variables passed to an imported document
+    /// to the placeholders of that document.
+    /// Assigned placeholders are removed so that they aren't processed again by the parent
+    fn process_variables(&mut self) {
+        if self.variables.is_empty() {
+            return;
+        }
+        let variables = &self.variables;
+        self.placeholders.retain(|p| {
+            let mut pholder = p.write().unwrap();
+            if pholder.value.is_some() {
+                return true;
+            }
+            if let Some(value) = variables.get(&pholder.name.to_lowercase()).cloned() {
+                pholder.set_value(inline!(Inline::Plain(PlainText { value })));
+                false
+            } else {
+                true
+            }
+        })
+    }
+
+    /// Assigns the citations of the enclosing section or imported document
+    /// to bibliography placeholders with `scope=section`
+    fn process_citation_scopes(&mut self) {
+        assign_citation_scopes(&self.elements, &|pholder: &Placeholder| {
+            pholder.name.to_lowercase() == P_BIB
+                && pholder
+                    .metadata
+                    .as_ref()
+                    .and_then(|m| m.get_string(S_SCOPE))
+                    .as_deref()
+                    == Some(V_SCOPE_SECTION)
+        });
+    }
 }
 
-fn get_time_string() -> String {
-    let now = Local::now();
-    format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second())
+/// Returns the block in a section with the localised title as its header
+/// if the placeholder has the `title` flag (`[[toc]][title]`)
+fn with_title(
+    block: Block,
+    metadata: &Option<InlineMetadata>,
+    title: String,
+    anchor: &str,
+) -> Block {
+    if !metadata.as_ref().is_some_and(|m| m.get_bool(S_TITLE)) {
+        return block;
+    }
+    let mut line = TextLine::new();
+    line.subtext.push(plain_text!(title));
+    let mut header = Header::new(Line::Text(line), anchor.to_string());
+    header.size = 1;
+    let mut section = Section::new(header);
+    section.add_element(block);
+
+    Block::Section(section)
+}
+
+/// Assigns numbers and link targets that are local to the scope to the given citations
+/// and returns the cited entries
+fn assign_scoped_citations(
+    citations: &[Arc<RwLock<BibCitation>>],
+    scope: &str,
+    style: &StyleSettings,
+) -> Vec<BibliographyEntryReference> {
+    let mut entries: Vec<BibliographyEntryReference> = Vec::new();
+
+    for citation in citations {
+        for reference in &citation.read().unwrap().references {
+            if let Some(entry) = reference.entry_anchor.lock().entry.clone() {
+                if !entries.iter().any(|e| Arc::ptr_eq(e, &entry)) {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+    sort_bib_entries(&mut entries, &style.get_bib_sorting());
+    let numbers: HashMap<String, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| (entry.lock().key(), index + 1))
+        .collect();
+
+    for citation in citations {
+        for reference in &mut citation.write().unwrap().references {
+            if let Some(number) = numbers.get(&reference.key) {
+                if reference.target.is_none() {
+                    reference.number = Some(number.to_string());
+                    reference.target = Some(format!("{}-{}", scope, reference.key));
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// Returns the ids of the given citations for every cited key
+fn get_back_references(citations: &[Arc<RwLock<BibCitation>>]) -> HashMap<String, Vec<String>> {
+    let mut back_references: HashMap<String, Vec<String>> = HashMap::new();
+
+    for citation in citations {
+        for reference in &citation.read().unwrap().references {
+            if let Some(id) = &reference.id {
+                back_references
+                    .entry(reference.key.clone())
+                    .or_default()
+                    .push(id.clone());
+            }
+        }
+    }
+
+    back_references
 }
 
-fn get_date_string() -> String {
-    let now = Local::now();
-    format!("{:02}.{:02}.{:04}", now.day(), now.month(), now.year())
+impl Document {
+    /// Returns the current time or the configured fixed timestamp formatted
+    /// with the format of the placeholder metadata or the given default format.
+    /// The fallback format is used if the format is invalid
+    fn get_timestamp_string(
+        &self,
+        default_format: String,
+        fallback_format: &str,
+        metadata: &Option<InlineMetadata>,
+    ) -> String {
+        let format = metadata
+            .as_ref()
+            .and_then(|m| m.get_string("format"))
+            .unwrap_or(default_format);
+        let config = self.config.lock();
+
+        DateFormatter::with_fallback(&format, fallback_format, &config.metadata.language)
+            .format(&config.get_timestamp())
+    }
 }
@@ -11,6 +11,9 @@ pub struct FeatureSettings {
     pub embed_external: bool,
     pub smart_arrows: bool,
     pub include_mathjax: bool,
+    pub reproducible: bool,
+    pub legacy_header_anchors: bool,
+    pub numbered_headings: bool,
 }
 
 impl Default for FeatureSettings {
@@ -19,6 +22,9 @@ impl Default for FeatureSettings {
             embed_external: true,
             smart_arrows: true,
             include_mathjax: true,
+            reproducible: false,
+            legacy_header_anchors: false,
+            numbered_headings: false,
         }
     }
 }
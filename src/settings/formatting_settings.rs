@@ -0,0 +1,72 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+pub const DEFAULT_DATE_FORMAT: &str = "%d.%m.%Y";
+pub const DEFAULT_TIME_FORMAT: &str = "%H:%M:%S";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FormattingSettings {
+    pub date_format: String,
+    pub time_format: String,
+    pub datetime_format: Option<String>,
+    pub timestamp: Option<i64>,
+}
+
+impl Default for FormattingSettings {
+    fn default() -> Self {
+        Self {
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            time_format: DEFAULT_TIME_FORMAT.to_string(),
+            datetime_format: None,
+            timestamp: None,
+        }
+    }
+}
+
+impl FormattingSettings {
+    /// Returns the format for date and time which defaults
+    /// to the date format followed by the time format
+    pub fn get_datetime_format(&self) -> String {
+        self.datetime_format
+            .clone()
+            .unwrap_or_else(|| format!("{} {}", self.date_format, self.time_format))
+    }
+
+    /// Returns the fixed timestamp from the settings or the `SOURCE_DATE_EPOCH`
+    /// environment variable if one is set
+    pub fn get_fixed_timestamp(&self) -> Option<i64> {
+        self.timestamp.or_else(|| {
+            env::var("SOURCE_DATE_EPOCH")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+        })
+    }
+
+    /// Returns the fixed timestamp as date and time in UTC
+    /// or None if none is set or the timestamp is out of range
+    pub fn get_fixed_datetime(&self) -> Option<NaiveDateTime> {
+        let timestamp = self.get_fixed_timestamp()?;
+        let datetime = NaiveDateTime::from_timestamp_opt(timestamp, 0);
+        if datetime.is_none() {
+            log::warn!(
+                "Timestamp {} is out of range and will be ignored.",
+                timestamp
+            );
+        }
+
+        datetime
+    }
+
+    /// Returns the fixed timestamp in UTC or the current local time if none is set
+    pub fn get_timestamp(&self) -> NaiveDateTime {
+        self.get_fixed_datetime()
+            .unwrap_or_else(|| Local::now().naive_local())
+    }
+}
@@ -5,6 +5,7 @@
  */
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ImportSettings {
@@ -12,6 +13,7 @@ pub struct ImportSettings {
     pub included_stylesheets: Vec<String>,
     pub included_bibliography: Vec<String>,
     pub included_glossaries: Vec<String>,
+    pub glossary_columns: HashMap<String, String>,
 }
 
 impl Default for ImportSettings {
@@ -21,6 +23,7 @@ impl Default for ImportSettings {
             included_stylesheets: vec!["style.css".to_string()],
             included_bibliography: vec!["Bibliography.toml".to_string()],
             included_glossaries: vec!["Glossary.toml".to_string()],
+            glossary_columns: HashMap::new(),
         }
     }
 }
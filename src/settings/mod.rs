@@ -6,11 +6,15 @@
 
 use crate::elements::{Metadata, MetadataValue};
 use crate::settings::feature_settings::FeatureSettings;
+use crate::settings::formatting_settings::FormattingSettings;
 use crate::settings::image_settings::ImageSettings;
 use crate::settings::import_settings::ImportSettings;
 use crate::settings::metadata_settings::MetadataSettings;
 use crate::settings::pdf_settings::PDFSettings;
 use crate::settings::style_settings::StyleSettings;
+use crate::settings::validation::{validate_config, ConfigIssueKind};
+use crate::utils::localisation::Localisation;
+use chrono::NaiveDateTime;
 use config::{ConfigError, Source};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -21,19 +25,27 @@ use std::mem;
 use std::path::PathBuf;
 
 pub mod feature_settings;
+pub mod formatting_settings;
 pub mod image_settings;
 pub mod import_settings;
 pub mod metadata_settings;
 pub mod pdf_settings;
 pub mod style_settings;
+pub mod validation;
 
 pub type SettingsResult<T> = Result<T, SettingsError>;
 
+/// The prefix of environment variables that override settings
+const ENV_PREFIX: &str = "SNEKDOWN_";
+/// The separator of nested keys in environment variables
+const ENV_SEPARATOR: &str = "__";
+
 #[derive(Debug)]
 pub enum SettingsError {
     IoError(io::Error),
     ConfigError(ConfigError),
     TomlError(toml::ser::Error),
+    UnknownSetting(String),
 }
 
 impl Display for SettingsError {
@@ -42,6 +54,7 @@ impl Display for SettingsError {
             Self::IoError(e) => write!(f, "IO Error: {}", e),
             Self::ConfigError(e) => write!(f, "Config Error: {}", e),
             Self::TomlError(e) => write!(f, "Toml Error: {}", e),
+            Self::UnknownSetting(e) => write!(f, "Config Error: {}", e),
         }
     }
 }
@@ -74,7 +87,20 @@ pub struct Settings {
     pub pdf: PDFSettings,
     pub images: ImageSettings,
     pub style: StyleSettings,
+    pub formatting: FormattingSettings,
+    pub localisation: HashMap<String, String>,
     pub custom_attributes: HashMap<String, String>,
+    pub lint: HashMap<String, String>,
+}
+
+/// Settings that are applied on top of the imported manifests.
+/// Config files are merged first, followed by environment variables
+/// prefixed with `SNEKDOWN_` and finally single values.
+/// Nested settings are separated with `__` in environment variables (`SNEKDOWN_STYLE__THEME`).
+#[derive(Clone, Debug, Default)]
+pub struct SettingsOverrides {
+    pub files: Vec<PathBuf>,
+    pub values: Vec<(String, String)>,
 }
 
 impl Source for Settings {
@@ -117,6 +143,64 @@ impl Settings {
         Ok(())
     }
 
+    /// Merges the current settings with the given toml value
+    pub fn merge_value(&mut self, value: &toml::Value) -> SettingsResult<()> {
+        let source = toml::to_string(value)?;
+        let mut settings = config::Config::default();
+        settings
+            .merge(self.clone())?
+            .merge(config::File::from_str(&source, config::FileFormat::Toml))?;
+        let mut settings: Self = settings.try_into()?;
+        mem::swap(self, &mut settings);
+
+        Ok(())
+    }
+
+    /// Applies the given overrides to the current settings
+    pub fn apply_overrides(&mut self, overrides: &SettingsOverrides) -> SettingsResult<()> {
+        let mut settings = config::Config::default();
+        settings.merge(self.clone())?;
+
+        for path in &overrides.files {
+            settings.merge(config::File::from(path.clone()))?;
+        }
+        for (name, key, value) in get_env_overrides() {
+            if let Err(e) = merge_override(&mut settings, &key, &value, &name) {
+                log::warn!("Ignoring environment variable {}: {}", name, e);
+            }
+        }
+        for (key, value) in &overrides.values {
+            merge_override(&mut settings, key, value, "--set")?;
+        }
+        let mut settings: Self = settings.try_into()?;
+        mem::swap(self, &mut settings);
+
+        Ok(())
+    }
+
+    /// Returns if the output should be identical for identical inputs.
+    /// This is the case if enabled in the features or if a fixed timestamp is set
+    pub fn is_reproducible(&self) -> bool {
+        self.features.reproducible || self.formatting.get_fixed_timestamp().is_some()
+    }
+
+    /// Returns the timestamp for generated dates.
+    /// Reproducible builds without a valid fixed timestamp use the unix epoch
+    pub fn get_timestamp(&self) -> NaiveDateTime {
+        if self.features.reproducible {
+            self.formatting.get_fixed_datetime().unwrap_or_else(|| {
+                NaiveDateTime::from_timestamp_opt(0, 0).expect("the unix epoch is in range")
+            })
+        } else {
+            self.formatting.get_timestamp()
+        }
+    }
+
+    /// Returns the fixed strings for the document language
+    pub fn get_localisation(&self) -> Localisation {
+        Localisation::new(&self.metadata.language, &self.localisation)
+    }
+
     pub fn append_metadata<M: Metadata>(&mut self, metadata: M) {
         let entries = metadata.get_string_map();
         for (key, value) in entries {
@@ -129,3 +213,114 @@ impl Settings {
             .insert(key.to_string(), value.to_string());
     }
 }
+
+/// Returns the names, dotted keys and values of environment variables that override
+/// settings like `SNEKDOWN_STYLE__THEME`. Variables that don't start with a settings table like `SNEKDOWN_LOG`
+/// are ignored
+fn get_env_overrides() -> Vec<(String, String, String)> {
+    let tables = match toml::Value::try_from(Settings::default()) {
+        Ok(toml::Value::Table(tables)) => tables,
+        _ => return Vec::new(),
+    };
+    let mut overrides = std::env::vars()
+        .filter_map(|(name, value)| {
+            let key = name
+                .strip_prefix(ENV_PREFIX)?
+                .to_lowercase()
+                .replace(ENV_SEPARATOR, ".");
+            let (table, _) = key.split_once('.')?;
+
+            if tables.contains_key(table) {
+                Some((name, key, value))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<(String, String, String)>>();
+    overrides.sort();
+
+    overrides
+}
+
+/// Merges a single override value into the settings.
+/// Values that aren't valid toml are treated as plain strings
+fn merge_override(
+    settings: &mut config::Config,
+    key: &str,
+    value: &str,
+    file: &str,
+) -> SettingsResult<()> {
+    let value = if format!("value = {}", value).parse::<toml::Value>().is_ok() {
+        value.to_string()
+    } else {
+        toml::Value::String(value.to_string()).to_string()
+    };
+    let source = format!("{} = {}", key, value);
+    if let Some(issue) = validate_config(&source, file)
+        .into_iter()
+        .find(|i| i.kind == ConfigIssueKind::UnknownKey)
+    {
+        return Err(SettingsError::UnknownSetting(issue.message));
+    }
+    settings.merge(config::File::from_str(&source, config::FileFormat::Toml))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_is_reproducible_with_a_fixed_timestamp() {
+        let mut settings = Settings::default();
+        settings.formatting.timestamp = Some(1_600_000_000);
+
+        assert!(settings.is_reproducible());
+        assert_eq!(
+            settings.get_timestamp(),
+            NaiveDateTime::from_timestamp(1_600_000_000, 0)
+        );
+    }
+
+    #[test]
+    fn it_prefers_the_fixed_timestamp_in_reproducible_mode() {
+        let mut settings = Settings::default();
+        settings.features.reproducible = true;
+        settings.formatting.timestamp = Some(86_400);
+
+        assert!(settings.is_reproducible());
+        assert_eq!(
+            settings.get_timestamp(),
+            NaiveDateTime::from_timestamp(86_400, 0)
+        );
+    }
+
+    #[test]
+    fn it_uses_the_unix_epoch_in_reproducible_mode() {
+        if std::env::var("SOURCE_DATE_EPOCH").is_ok() {
+            return;
+        }
+        let mut settings = Settings::default();
+        assert!(!settings.is_reproducible());
+
+        settings.features.reproducible = true;
+        assert!(settings.is_reproducible());
+        assert_eq!(
+            settings.get_timestamp(),
+            NaiveDateTime::from_timestamp(0, 0)
+        );
+    }
+
+    #[test]
+    fn it_enables_reproducible_mode_through_overrides() {
+        let mut settings = Settings::default();
+        let overrides = SettingsOverrides {
+            values: vec![("features.reproducible".to_string(), "true".to_string())],
+            ..Default::default()
+        };
+        settings.apply_overrides(&overrides).unwrap();
+
+        assert!(settings.features.reproducible);
+    }
+}
@@ -8,19 +8,126 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct StyleSettings {
-    pub bib_ref_display: String,
+    pub bib_ref_display: Option<String>,
+    pub citation_style: CitationStyle,
+    pub bib_sorting: Option<BibSorting>,
+    pub bib_back_references: bool,
+    pub glossary_first_use: Option<String>,
     pub theme: Theme,
 }
 
 impl Default for StyleSettings {
     fn default() -> Self {
         Self {
-            bib_ref_display: "{{number}}".to_string(),
+            bib_ref_display: None,
+            citation_style: CitationStyle::Numeric,
+            bib_sorting: None,
+            bib_back_references: false,
+            glossary_first_use: None,
             theme: Theme::GitHub,
         }
     }
 }
 
+impl StyleSettings {
+    /// Returns the template for references which defaults to the one of the citation style
+    pub fn get_bib_ref_display(&self) -> String {
+        self.bib_ref_display
+            .clone()
+            .unwrap_or_else(|| self.citation_style.get_reference_display().to_string())
+    }
+
+    /// Returns the template for the first reference to a glossary entry
+    pub fn get_glossary_first_use(&self) -> String {
+        self.glossary_first_use
+            .clone()
+            .unwrap_or_else(|| "{{long}}".to_string())
+    }
+
+    /// Returns the sorting of the bibliography which defaults to the one of the citation style
+    pub fn get_bib_sorting(&self) -> BibSorting {
+        self.bib_sorting
+            .clone()
+            .unwrap_or_else(|| self.citation_style.get_default_sorting())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum CitationStyle {
+    Numeric,
+    #[serde(rename = "IEEE")]
+    Ieee,
+    #[serde(rename = "APA")]
+    Apa,
+    Chicago,
+    AuthorYear,
+}
+
+impl CitationStyle {
+    /// Returns the template for a single reference inside a citation
+    pub fn get_reference_display(&self) -> &'static str {
+        match self {
+            CitationStyle::Numeric | CitationStyle::Ieee => "{{number}}",
+            CitationStyle::Apa => "{{author}}, {{year}}",
+            CitationStyle::Chicago | CitationStyle::AuthorYear => "{{author}} {{year}}",
+        }
+    }
+
+    /// Returns the template for a reference in a textual citation
+    /// that follows the name of the author
+    pub fn get_textual_display(&self) -> &'static str {
+        if self.is_numbered() {
+            "{{number}}"
+        } else {
+            "{{year}}"
+        }
+    }
+
+    /// Returns the opening and closing brackets around a citation
+    pub fn get_brackets(&self) -> (&'static str, &'static str) {
+        match self {
+            CitationStyle::Numeric => ("", ""),
+            CitationStyle::Ieee => ("[", "]"),
+            _ => ("(", ")"),
+        }
+    }
+
+    /// Returns the separator between multiple references of a citation
+    pub fn get_separator(&self) -> &'static str {
+        if self.is_numbered() {
+            ", "
+        } else {
+            "; "
+        }
+    }
+
+    /// Returns if entries are referenced by their number in the bibliography
+    pub fn is_numbered(&self) -> bool {
+        matches!(self, CitationStyle::Numeric | CitationStyle::Ieee)
+    }
+
+    /// Returns if references in the text are rendered as superscript
+    pub fn is_superscript(&self) -> bool {
+        *self == CitationStyle::Numeric
+    }
+
+    /// Returns the default sorting of the bibliography
+    pub fn get_default_sorting(&self) -> BibSorting {
+        if self.is_numbered() {
+            BibSorting::Occurrence
+        } else {
+            BibSorting::Author
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum BibSorting {
+    Occurrence,
+    Author,
+    Year,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum Theme {
     GitHub,
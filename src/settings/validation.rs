@@ -0,0 +1,313 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::settings::Settings;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+/// The maximum edit distance of a key to be suggested for an unknown key
+const MAX_SUGGESTION_DISTANCE: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConfigIssueKind {
+    Syntax,
+    UnknownKey,
+    InvalidValue,
+}
+
+/// An issue found while validating a config file
+#[derive(Clone, Debug)]
+pub struct ConfigIssue {
+    pub kind: ConfigIssueKind,
+    pub file: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "{}\n\t--> {}:{}\n", self.message, self.file, line)
+        } else {
+            write!(f, "{}\n\t--> {}\n", self.message, self.file)
+        }
+    }
+}
+
+impl ConfigIssue {
+    /// Returns if the issue prevents the config from being loaded
+    pub fn is_error(&self) -> bool {
+        self.kind != ConfigIssueKind::UnknownKey
+    }
+
+    /// Logs the issue as an error or warning
+    pub fn log(&self) {
+        if self.is_error() {
+            log::error!("{}", self);
+        } else {
+            log::warn!("{}", self);
+        }
+    }
+}
+
+/// Validates the config file at the given path
+pub fn validate_config_file(path: &Path) -> Vec<ConfigIssue> {
+    let file = path.to_string_lossy().to_string();
+
+    match fs::read_to_string(path) {
+        Ok(contents) => validate_config(&contents, &file),
+        Err(e) => vec![ConfigIssue {
+            kind: ConfigIssueKind::Syntax,
+            file,
+            line: None,
+            message: format!("Failed to read config file: {}", e),
+        }],
+    }
+}
+
+/// Validates the contents of a config file
+pub fn validate_config(contents: &str, file: &str) -> Vec<ConfigIssue> {
+    match contents.parse::<toml::Value>() {
+        Ok(value) => validate_config_value(&value, contents, file),
+        Err(e) => vec![ConfigIssue {
+            kind: ConfigIssueKind::Syntax,
+            file: file.to_string(),
+            line: e.line_col().map(|(l, _)| l + 1),
+            message: format!("Invalid toml: {}", e),
+        }],
+    }
+}
+
+/// Validates a parsed config value.
+/// Unknown keys are reported with a suggestion for a similar known key and
+/// every value is checked against the type of the setting it belongs to.
+/// The contents are only used to find the lines of the reported keys.
+pub fn validate_config_value(value: &toml::Value, contents: &str, file: &str) -> Vec<ConfigIssue> {
+    // serialized with json so that unset optional settings are included as null values
+    let schema = serde_json::to_value(Settings::default()).unwrap();
+    let mut issues = Vec::new();
+
+    if let Some(table) = value.as_table() {
+        validate_table(table, &schema, &mut Vec::new(), contents, file, &mut issues);
+    }
+    issues.sort_by_key(|i| i.line);
+
+    issues
+}
+
+/// Recursively validates the keys of a table against the schema
+fn validate_table(
+    table: &toml::value::Table,
+    schema: &serde_json::Value,
+    path: &mut Vec<String>,
+    contents: &str,
+    file: &str,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let schema_keys = schema.as_object();
+
+    for (key, value) in table {
+        path.push(key.clone());
+        // an empty object in the schema is a map that accepts any key
+        let schema_value = match schema_keys {
+            Some(keys) if keys.is_empty() => None,
+            Some(keys) => match keys.get(key) {
+                Some(v) => Some(v),
+                None => {
+                    let suggestion = keys
+                        .keys()
+                        .map(|k| (strsim::damerau_levenshtein(k, key), k))
+                        .filter(|(d, _)| *d <= MAX_SUGGESTION_DISTANCE)
+                        .min_by_key(|(d, _)| *d)
+                        .map(|(_, k)| format!(" Did you mean \"{}\"?", k))
+                        .unwrap_or_default();
+                    issues.push(ConfigIssue {
+                        kind: ConfigIssueKind::UnknownKey,
+                        file: file.to_string(),
+                        line: find_key_line(contents, path),
+                        message: format!("Unknown key \"{}\".{}", path.join("."), suggestion),
+                    });
+                    path.pop();
+                    continue;
+                }
+            },
+            None => None,
+        };
+
+        match (value, schema_value) {
+            (toml::Value::Table(inner), Some(inner_schema)) if inner_schema.is_object() => {
+                validate_table(inner, inner_schema, path, contents, file, issues)
+            }
+            _ => {
+                if let Err(e) = check_value(path, value) {
+                    issues.push(ConfigIssue {
+                        kind: ConfigIssueKind::InvalidValue,
+                        file: file.to_string(),
+                        line: find_key_line(contents, path),
+                        message: format!("Invalid value for \"{}\": {}", path.join("."), e),
+                    });
+                }
+            }
+        }
+        path.pop();
+    }
+}
+
+/// Checks if the value can be deserialized into the setting at the given path
+fn check_value(path: &[String], value: &toml::Value) -> Result<(), toml::de::Error> {
+    let mut settings = toml::Value::try_from(Settings::default()).unwrap();
+    let mut table = &mut settings;
+
+    for key in &path[..path.len() - 1] {
+        let inner = table.as_table_mut().unwrap();
+        table = inner
+            .entry(key.clone())
+            .or_insert_with(|| toml::Value::Table(toml::value::Table::new()));
+    }
+    table
+        .as_table_mut()
+        .unwrap()
+        .insert(path[path.len() - 1].clone(), value.clone());
+
+    settings.try_into::<Settings>().map(|_| ())
+}
+
+/// Returns the line number of the key with the given path
+fn find_key_line(contents: &str, path: &[String]) -> Option<usize> {
+    let mut current_table: Vec<String> = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            current_table = split_key(line.trim_matches(|c| c == '[' || c == ']'));
+
+            if current_table == path {
+                return Some(index + 1);
+            }
+        } else if let Some((key, _)) = line.split_once('=') {
+            let mut key_path = current_table.clone();
+            key_path.append(&mut split_key(key));
+
+            if key_path == path {
+                return Some(index + 1);
+            }
+        }
+    }
+
+    None
+}
+
+/// Splits a dotted toml key into its parts keeping dots inside of quoted parts
+fn split_key(key: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut quote = None;
+
+    for c in key.chars() {
+        match c {
+            '"' | '\'' if quote.is_none() => quote = Some(c),
+            c if quote == Some(c) => quote = None,
+            '.' if quote.is_none() => parts.push(std::mem::take(&mut part)),
+            c => part.push(c),
+        }
+    }
+    parts.push(part);
+
+    parts.into_iter().map(|p| p.trim().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[features]
+smart_arows = true
+numbered_headings = "yes"
+
+[style]
+theme = "GitHub"
+"style.citation_style" = "APA"
+
+[custom_attributes]
+edition = "student"
+
+[styl]
+theme = "OceanDark"
+"#;
+
+    #[test]
+    fn it_suggests_similar_keys() {
+        let issues = validate_config(CONFIG, "Manifest.toml");
+        let unknown: Vec<&ConfigIssue> = issues
+            .iter()
+            .filter(|i| i.kind == ConfigIssueKind::UnknownKey)
+            .collect();
+
+        assert_eq!(unknown.len(), 3);
+        assert_eq!(
+            unknown[0].message,
+            "Unknown key \"features.smart_arows\". Did you mean \"smart_arrows\"?"
+        );
+        assert_eq!(unknown[0].line, Some(3));
+        assert!(!unknown[0].is_error());
+        assert_eq!(
+            unknown[1].message,
+            "Unknown key \"style.style.citation_style\"."
+        );
+        assert_eq!(unknown[1].line, Some(8));
+        assert_eq!(
+            unknown[2].message,
+            "Unknown key \"styl\". Did you mean \"style\"?"
+        );
+        assert_eq!(unknown[2].line, Some(13));
+    }
+
+    #[test]
+    fn it_reports_values_of_the_wrong_type() {
+        let issues = validate_config(CONFIG, "Manifest.toml");
+        let invalid: Vec<&ConfigIssue> = issues
+            .iter()
+            .filter(|i| i.kind == ConfigIssueKind::InvalidValue)
+            .collect();
+
+        assert_eq!(invalid.len(), 1);
+        assert!(invalid[0]
+            .message
+            .starts_with("Invalid value for \"features.numbered_headings\""));
+        assert_eq!(invalid[0].line, Some(4));
+        assert!(invalid[0].is_error());
+        assert_eq!(
+            invalid[0].to_string(),
+            format!("{}\n\t--> Manifest.toml:4\n", invalid[0].message)
+        );
+    }
+
+    #[test]
+    fn it_reports_the_line_of_syntax_errors() {
+        let issues = validate_config("[style]\ntheme = \n", "Manifest.toml");
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, ConfigIssueKind::Syntax);
+        assert_eq!(issues[0].line, Some(2));
+    }
+
+    #[test]
+    fn it_finds_dotted_keys() {
+        let contents = "style.theme = \"GitHub\"\n[pdf]\n\"display_header_footer\" = true\n";
+        let path = |p: &[&str]| p.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        assert_eq!(find_key_line(contents, &path(&["style", "theme"])), Some(1));
+        assert_eq!(find_key_line(contents, &path(&["pdf"])), Some(2));
+        assert_eq!(
+            find_key_line(contents, &path(&["pdf", "display_header_footer"])),
+            Some(3)
+        );
+        assert_eq!(find_key_line(contents, &path(&["images"])), None);
+    }
+}
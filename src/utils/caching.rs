@@ -26,6 +26,11 @@ impl CacheStorage {
         }
     }
 
+    /// Creates a cache storage in the given directory
+    pub fn with_location(location: PathBuf) -> Self {
+        Self { location }
+    }
+
     /// Returns the cache path for a given file
     pub fn get_file_path(&self, path: &PathBuf) -> PathBuf {
         let mut hasher = sha2::Sha256::default();
@@ -56,6 +61,7 @@ impl CacheStorage {
     /// Reads the corresponding cache file
     pub fn write<R: AsRef<[u8]>>(&self, path: &PathBuf, contents: R) -> io::Result<()> {
         let cache_path = self.get_file_path(path);
+        fs::create_dir_all(&self.location)?;
 
         fs::write(cache_path, contents)
     }
@@ -0,0 +1,209 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::settings::formatting_settings::DEFAULT_DATE_FORMAT;
+use crate::utils::localisation::get_language_code;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Date, Datelike, Local, NaiveDateTime};
+
+struct LocaleNames {
+    months: [&'static str; 12],
+    short_months: [&'static str; 12],
+    weekdays: [&'static str; 7],
+    short_weekdays: [&'static str; 7],
+}
+
+const NAMES_DE: LocaleNames = LocaleNames {
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    short_months: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    short_weekdays: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+};
+
+const NAMES_FR: LocaleNames = LocaleNames {
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    short_months: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.",
+        "déc.",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    short_weekdays: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+};
+
+const NAMES_ES: LocaleNames = LocaleNames {
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    short_months: [
+        "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+    ],
+    weekdays: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    short_weekdays: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+};
+
+/// Formats dates with a chrono format string.
+/// Month and weekday names are localised for the given language.
+/// Invalid format strings are replaced with a fallback format.
+#[derive(Clone, Debug)]
+pub struct DateFormatter {
+    format: String,
+    language: String,
+}
+
+impl DateFormatter {
+    pub fn new(format: &str, language: &str) -> Self {
+        Self::with_fallback(format, DEFAULT_DATE_FORMAT, language)
+    }
+
+    /// Creates a new formatter that uses the fallback format
+    /// if the given format is not a valid format string
+    pub fn with_fallback(format: &str, fallback: &str, language: &str) -> Self {
+        let format = if is_valid_format(format) {
+            format
+        } else {
+            log::warn!(
+                "Invalid date format \"{}\". Using \"{}\" instead.",
+                format,
+                fallback
+            );
+            fallback
+        };
+
+        Self {
+            format: format.to_string(),
+            language: language.to_string(),
+        }
+    }
+
+    /// Formats the given date and time
+    pub fn format(&self, datetime: &NaiveDateTime) -> String {
+        let format = self.get_localized_format(datetime);
+
+        datetime.format(&format).to_string()
+    }
+
+    /// Formats the given date
+    pub fn format_date(&self, date: &Date<Local>) -> String {
+        self.format(&date.naive_local().and_hms(0, 0, 0))
+    }
+
+    /// Replaces the month and weekday specifiers of the format
+    /// with the localised names for the given date
+    fn get_localized_format(&self, datetime: &NaiveDateTime) -> String {
+        let names = match get_language_code(&self.language).as_str() {
+            "de" => &NAMES_DE,
+            "fr" => &NAMES_FR,
+            "es" => &NAMES_ES,
+            _ => return self.format.clone(),
+        };
+        let month = datetime.month0() as usize;
+        let weekday = datetime.weekday().num_days_from_monday() as usize;
+        let mut format = String::with_capacity(self.format.len());
+        let mut chars = self.format.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                format.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('B') => format.push_str(names.months[month]),
+                Some('b') | Some('h') => format.push_str(names.short_months[month]),
+                Some('A') => format.push_str(names.weekdays[weekday]),
+                Some('a') => format.push_str(names.short_weekdays[weekday]),
+                Some(c) => {
+                    format.push('%');
+                    format.push(c);
+                }
+                None => format.push('%'),
+            }
+        }
+
+        format
+    }
+}
+
+/// Returns if the given string is a valid chrono format string
+fn is_valid_format(format: &str) -> bool {
+    !StrftimeItems::new(format).any(|item| item == Item::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn it_falls_back_for_invalid_formats() {
+        let datetime = NaiveDate::from_ymd(2021, 3, 14).and_hms(15, 9, 26);
+
+        for format in &["%Q", "%Y-%m-%d %"] {
+            let formatter = DateFormatter::with_fallback(format, "%Y", "en");
+            assert_eq!(formatter.format(&datetime), "2021");
+        }
+        assert_eq!(
+            DateFormatter::new("%d. %B %Y", "de").format(&datetime),
+            "14. März 2021"
+        );
+    }
+}
@@ -8,14 +8,26 @@ use crate::utils::caching::CacheStorage;
 use indicatif::{ProgressBar, ProgressStyle};
 use parking_lot::Mutex;
 use rayon::prelude::*;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::fs::read;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The time a successful url check is cached for
+const URL_CHECK_CACHE_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// The time a downloaded file is used from the cache before it is revalidated
+const DOWNLOAD_CACHE_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
 
 /// A manager for downloading urls in parallel
 #[derive(Clone, Debug)]
 pub struct DownloadManager {
     downloads: Vec<Arc<Mutex<PendingDownload>>>,
+    offline: bool,
+    cache: CacheStorage,
 }
 
 impl DownloadManager {
@@ -23,12 +35,25 @@ impl DownloadManager {
     pub fn new() -> Self {
         Self {
             downloads: Vec::new(),
+            offline: false,
+            cache: CacheStorage::new(),
         }
     }
 
+    /// Sets if only cached files should be used for downloads
+    pub fn set_offline(&mut self, offline: bool) {
+        self.offline = offline;
+    }
+
+    /// Sets the cache downloaded files are stored in
+    pub fn set_cache(&mut self, cache: CacheStorage) {
+        self.cache = cache;
+    }
+
     /// Adds a new pending download
     pub fn add_download(&mut self, path: String) -> Arc<Mutex<PendingDownload>> {
-        let download = PendingDownload::new(path.clone());
+        let mut download = PendingDownload::new(path.clone(), self.cache.clone());
+        download.offline = self.offline;
         let pending = Arc::new(Mutex::new(download));
         self.downloads.push(Arc::clone(&pending));
         log::debug!("Added download {}", path);
@@ -61,16 +86,18 @@ pub struct PendingDownload {
     pub(crate) path: String,
     pub(crate) data: Option<Vec<u8>>,
     pub(crate) use_cache: bool,
+    pub(crate) offline: bool,
     cache: CacheStorage,
 }
 
 impl PendingDownload {
-    pub fn new(path: String) -> Self {
+    pub fn new(path: String, cache: CacheStorage) -> Self {
         Self {
             path,
             data: None,
             use_cache: true,
-            cache: CacheStorage::new(),
+            offline: false,
+            cache,
         }
     }
 
@@ -85,49 +112,280 @@ impl PendingDownload {
 
         if path.exists() {
             read(path).ok()
-        } else if let Some(contents) = self.read_from_cache() {
-            log::debug!("Read {} from cache.", self.path.clone());
-            Some(contents)
+        } else if self.use_cache {
+            download_cached(&self.path, &self.cache, self.offline)
         } else {
-            if let Some(data) = self.download_content() {
-                self.store_to_cache(&data);
-                Some(data)
+            download_path(self.path.clone())
+        }
+    }
+}
+
+/// The validators of a cached response used to revalidate the cache entry
+/// and the time the response was fetched or last revalidated at
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct CacheValidators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: Option<u64>,
+}
+
+impl CacheValidators {
+    /// Returns if the cache entry doesn't need to be revalidated yet
+    fn is_fresh(&self) -> bool {
+        self.fetched_at
+            .map(|f| get_unix_time().saturating_sub(f) < DOWNLOAD_CACHE_DURATION.as_secs())
+            .unwrap_or(false)
+    }
+}
+
+/// Returns if the given path is a http or https url
+pub fn is_remote(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+pub fn download_path(path: String) -> Option<Vec<u8>> {
+    reqwest::blocking::get(&path)
+        .ok()
+        .map(|c| c.bytes())
+        .and_then(|b| b.ok())
+        .map(|b| b.to_vec())
+}
+
+/// Downloads the given url and stores the response in the cache.
+/// Cached responses are used for a day and revalidated with the ETag
+/// and Last-Modified headers of the previous response afterwards.
+/// In offline mode only the cached response is returned.
+pub fn download_cached(url: &str, cache: &CacheStorage, offline: bool) -> Option<Vec<u8>> {
+    let path = PathBuf::from(url);
+    let validators_path = PathBuf::from(format!("{}.validators", url));
+    let cached = if cache.has_file(&path) {
+        cache.read(&path).ok()
+    } else {
+        None
+    };
+
+    if offline {
+        if cached.is_none() {
+            log::error!(
+                "{} is not cached and can't be downloaded in offline mode",
+                url
+            );
+        }
+        return cached;
+    }
+    let mut request = reqwest::blocking::Client::new().get(url);
+    let mut validators = CacheValidators::default();
+
+    if cached.is_some() {
+        validators = cache
+            .read(&validators_path)
+            .ok()
+            .and_then(|v| toml::from_slice(&v).ok())
+            .unwrap_or_default();
+        if validators.is_fresh() {
+            log::debug!("Read {} from cache.", url);
+            return cached;
+        }
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send() {
+        Ok(response) => response,
+        Err(e) => {
+            if cached.is_some() {
+                log::warn!(
+                    "Failed to revalidate {}, using the cached version: {}",
+                    url,
+                    e
+                );
             } else {
-                None
+                log::error!("Failed to download {}: {}", url, e);
             }
+            return cached;
         }
+    };
+
+    if response.status() == StatusCode::NOT_MODIFIED && cached.is_some() {
+        log::debug!("Read {} from cache.", url);
+        validators.fetched_at = Some(get_unix_time());
+        write_validators(cache, &validators_path, &validators, url);
+
+        return cached;
     }
+    if !response.status().is_success() {
+        log::error!("Failed to download {}: {}", url, response.status());
+        return cached;
+    }
+    let header_value = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    let validators = CacheValidators {
+        etag: header_value(ETAG),
+        last_modified: header_value(LAST_MODIFIED),
+        fetched_at: Some(get_unix_time()),
+    };
+    let data = response.bytes().ok()?.to_vec();
+
+    if cache.write(&path, &data).is_err() {
+        log::warn!("Failed to write file to cache: {}", url);
+    }
+    write_validators(cache, &validators_path, &validators, url);
+
+    Some(data)
+}
 
-    /// Stores the data to a cache file to retrieve it later
-    fn store_to_cache(&self, data: &Vec<u8>) {
-        if self.use_cache {
-            let path = PathBuf::from(&self.path);
-            self.cache
-                .write(&path, data.clone())
-                .unwrap_or_else(|_| log::warn!("Failed to write file to cache: {}", self.path));
+/// Writes the validators of a cached response to the cache
+fn write_validators(cache: &CacheStorage, path: &PathBuf, validators: &CacheValidators, url: &str) {
+    if let Ok(validators) = toml::to_vec(validators) {
+        if cache.write(path, validators).is_err() {
+            log::warn!("Failed to write validators to cache: {}", url);
         }
     }
+}
 
-    fn read_from_cache(&self) -> Option<Vec<u8>> {
-        let path = PathBuf::from(&self.path);
+/// Returns the current unix time in seconds
+fn get_unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
 
-        if self.cache.has_file(&path) && self.use_cache {
-            self.cache.read(&path).ok()
-        } else {
-            None
+/// Checks if the given url can be reached with a HEAD request.
+/// Servers that don't support HEAD requests are checked with a GET request.
+/// Successful checks are cached for a day if a cache is given
+/// so that they aren't repeated on every run
+pub fn check_url(
+    url: &str,
+    client: &reqwest::blocking::Client,
+    cache: Option<&CacheStorage>,
+) -> Result<(), String> {
+    let path = PathBuf::from(format!("{}.checked", url));
+    let now = get_unix_time();
+    let checked_at = cache
+        .and_then(|c| c.read(&path).ok())
+        .and_then(|c| String::from_utf8(c).ok())
+        .and_then(|c| c.trim().parse::<u64>().ok());
+
+    if let Some(checked_at) = checked_at {
+        if now.saturating_sub(checked_at) < URL_CHECK_CACHE_DURATION.as_secs() {
+            log::debug!("Using cached check result for {}", url);
+            return Ok(());
         }
     }
+    let mut response = client.head(url).send().map_err(|e| e.to_string())?;
 
-    /// Downloads the content from the given url
-    fn download_content(&self) -> Option<Vec<u8>> {
-        download_path(self.path.clone())
+    if response.status() == StatusCode::METHOD_NOT_ALLOWED
+        || response.status() == StatusCode::NOT_IMPLEMENTED
+    {
+        response = client.get(url).send().map_err(|e| e.to_string())?;
     }
+    if !response.status().is_success() {
+        return Err(response.status().to_string());
+    }
+    if let Some(cache) = cache {
+        if cache.write(&path, now.to_string()).is_err() {
+            log::warn!("Failed to write check result to cache: {}", url);
+        }
+    }
+
+    Ok(())
 }
 
-pub fn download_path(path: String) -> Option<Vec<u8>> {
-    reqwest::blocking::get(&path)
-        .ok()
-        .map(|c| c.bytes())
-        .and_then(|b| b.ok())
-        .map(|b| b.to_vec())
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// The request heads received by a test server
+    pub(crate) type ReceivedRequests = Arc<Mutex<Vec<String>>>;
+
+    /// Starts a http server that answers every request with the response
+    /// returned by the handler for the lowercase request head.
+    /// Returns the base url of the server and the received request heads
+    pub(crate) fn start_test_server<F>(handler: F) -> (String, ReceivedRequests)
+    where
+        F: Fn(&str) -> String + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = ReceivedRequests::default();
+        let received = Arc::clone(&requests);
+
+        thread::spawn(move || {
+            for mut stream in listener.incoming().filter_map(|s| s.ok()) {
+                let mut head = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => head.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+                let response = handler(&head);
+                received.lock().push(head);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Returns a cache in a new temporary directory
+    pub(crate) fn create_test_cache(name: &str) -> CacheStorage {
+        let location =
+            std::env::temp_dir().join(format!("snekdown-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&location);
+
+        CacheStorage::with_location(location)
+    }
+
+    #[test]
+    fn it_revalidates_stale_cache_entries() {
+        let (url, requests) = start_test_server(|head| {
+            if head.contains("if-none-match: \"v1\"")
+                && head.contains("if-modified-since: wed, 21 oct 2015 07:28:00 gmt")
+            {
+                "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 21 Oct 2015 07:28:00 GMT\r\nContent-Length: 7\r\nConnection: close\r\n\r\ncontent".to_string()
+            }
+        });
+        let url = format!("{}/style.css", url);
+        let cache = create_test_cache("revalidate");
+        let validators_path = PathBuf::from(format!("{}.validators", url));
+
+        assert_eq!(download_cached(&url, &cache, false).unwrap(), b"content");
+        // fresh entries are read from the cache without a request
+        assert_eq!(download_cached(&url, &cache, false).unwrap(), b"content");
+        assert_eq!(requests.lock().len(), 1);
+
+        let mut validators: CacheValidators =
+            toml::from_slice(&cache.read(&validators_path).unwrap()).unwrap();
+        validators.fetched_at = Some(0);
+        write_validators(&cache, &validators_path, &validators, &url);
+
+        assert_eq!(download_cached(&url, &cache, false).unwrap(), b"content");
+        assert_eq!(requests.lock().len(), 2);
+        assert!(requests.lock()[1].contains("if-none-match"));
+        let validators: CacheValidators =
+            toml::from_slice(&cache.read(&validators_path).unwrap()).unwrap();
+        assert!(validators.is_fresh());
+
+        assert_eq!(download_cached(&url, &cache, true).unwrap(), b"content");
+        assert_eq!(requests.lock().len(), 2);
+        let _ = cache.clear();
+    }
 }
@@ -0,0 +1,243 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use std::collections::HashMap;
+
+pub const L_CITATION_NEEDED: &str = "citation_needed";
+pub const L_BIB_IN: &str = "bib_in";
+pub const L_BIB_NUMBER: &str = "bib_number";
+pub const L_BIB_PAGES: &str = "bib_pages";
+pub const L_BIB_URL: &str = "bib_url";
+pub const L_BIB_ON: &str = "bib_on";
+pub const L_BIB_PUBLISHED: &str = "bib_published";
+pub const L_BIB_PUBLISHED_AS: &str = "bib_published_as";
+pub const L_BIB_PUBLISHED_ON: &str = "bib_published_on";
+pub const L_BIB_PUBLISHED_BY: &str = "bib_published_by";
+pub const L_BIB_EDITOR: &str = "bib_editor";
+pub const L_BIB_BY: &str = "bib_by";
+pub const L_BIB_LICENSE: &str = "bib_license";
+pub const L_BIB_AT: &str = "bib_at";
+pub const L_BIB_ACCESSED: &str = "bib_accessed";
+pub const L_BIB_AND: &str = "bib_and";
+pub const L_BIB_ET_AL: &str = "bib_et_al";
+pub const L_BIB_NO_DATE: &str = "bib_no_date";
+pub const L_BIB_VOLUME_SHORT: &str = "bib_volume_short";
+pub const L_BIB_NUMBER_SHORT: &str = "bib_number_short";
+pub const L_BIB_PAGES_SHORT: &str = "bib_pages_short";
+pub const L_BIB_CITED_IN: &str = "bib_cited_in";
+pub const L_INDEX_SEE: &str = "index_see";
+pub const L_INDEX_SEE_ALSO: &str = "index_see_also";
+pub const L_TOC_TITLE: &str = "toc_title";
+pub const L_GLOSSARY_TITLE: &str = "glossary_title";
+pub const L_INDEX_TITLE: &str = "index_title";
+
+const STRINGS_EN: &[(&str, &str)] = &[
+    (L_CITATION_NEEDED, "citation needed"),
+    (L_BIB_IN, "In"),
+    (L_BIB_NUMBER, "Number"),
+    (L_BIB_PAGES, "Pages"),
+    (L_BIB_URL, "URL"),
+    (L_BIB_ON, "on"),
+    (L_BIB_PUBLISHED, "Published"),
+    (L_BIB_PUBLISHED_AS, "as"),
+    (L_BIB_PUBLISHED_ON, "Published On"),
+    (L_BIB_PUBLISHED_BY, "Published By"),
+    (L_BIB_EDITOR, "Editor"),
+    (L_BIB_BY, "by"),
+    (L_BIB_LICENSE, "License"),
+    (L_BIB_AT, "at"),
+    (L_BIB_ACCESSED, "accessed"),
+    (L_BIB_AND, "and"),
+    (L_BIB_ET_AL, "et al."),
+    (L_BIB_NO_DATE, "n.d."),
+    (L_BIB_VOLUME_SHORT, "vol."),
+    (L_BIB_NUMBER_SHORT, "no."),
+    (L_BIB_PAGES_SHORT, "pp."),
+    (L_BIB_CITED_IN, "Cited in"),
+    (L_INDEX_SEE, "see"),
+    (L_INDEX_SEE_ALSO, "see also"),
+    (L_TOC_TITLE, "Table of Contents"),
+    (L_GLOSSARY_TITLE, "Glossary"),
+    (L_INDEX_TITLE, "Index"),
+];
+
+const STRINGS_DE: &[(&str, &str)] = &[
+    (L_CITATION_NEEDED, "Quelle benötigt"),
+    (L_BIB_IN, "In"),
+    (L_BIB_NUMBER, "Nummer"),
+    (L_BIB_PAGES, "Seiten"),
+    (L_BIB_URL, "URL"),
+    (L_BIB_ON, "am"),
+    (L_BIB_PUBLISHED, "Veröffentlicht"),
+    (L_BIB_PUBLISHED_AS, "als"),
+    (L_BIB_PUBLISHED_ON, "Veröffentlicht am"),
+    (L_BIB_PUBLISHED_BY, "Verlegt von"),
+    (L_BIB_EDITOR, "Herausgeber"),
+    (L_BIB_BY, "von"),
+    (L_BIB_LICENSE, "Lizenz"),
+    (L_BIB_AT, "an"),
+    (L_BIB_ACCESSED, "abgerufen"),
+    (L_BIB_AND, "und"),
+    (L_BIB_ET_AL, "et al."),
+    (L_BIB_NO_DATE, "o. J."),
+    (L_BIB_VOLUME_SHORT, "Bd."),
+    (L_BIB_NUMBER_SHORT, "Nr."),
+    (L_BIB_PAGES_SHORT, "S."),
+    (L_BIB_CITED_IN, "Zitiert in"),
+    (L_INDEX_SEE, "siehe"),
+    (L_INDEX_SEE_ALSO, "siehe auch"),
+    (L_TOC_TITLE, "Inhaltsverzeichnis"),
+    (L_GLOSSARY_TITLE, "Glossar"),
+    (L_INDEX_TITLE, "Stichwortverzeichnis"),
+];
+
+const STRINGS_FR: &[(&str, &str)] = &[
+    (L_CITATION_NEEDED, "citation nécessaire"),
+    (L_BIB_IN, "Dans"),
+    (L_BIB_NUMBER, "Numéro"),
+    (L_BIB_PAGES, "Pages"),
+    (L_BIB_URL, "URL"),
+    (L_BIB_ON, "le"),
+    (L_BIB_PUBLISHED, "Publié"),
+    (L_BIB_PUBLISHED_AS, "comme"),
+    (L_BIB_PUBLISHED_ON, "Publié le"),
+    (L_BIB_PUBLISHED_BY, "Publié par"),
+    (L_BIB_EDITOR, "Éditeur"),
+    (L_BIB_BY, "par"),
+    (L_BIB_LICENSE, "Licence"),
+    (L_BIB_AT, "à"),
+    (L_BIB_ACCESSED, "consulté"),
+    (L_BIB_AND, "et"),
+    (L_BIB_ET_AL, "et al."),
+    (L_BIB_NO_DATE, "s.d."),
+    (L_BIB_VOLUME_SHORT, "vol."),
+    (L_BIB_NUMBER_SHORT, "n°"),
+    (L_BIB_PAGES_SHORT, "p."),
+    (L_BIB_CITED_IN, "Cité dans"),
+    (L_INDEX_SEE, "voir"),
+    (L_INDEX_SEE_ALSO, "voir aussi"),
+    (L_TOC_TITLE, "Table des matières"),
+    (L_GLOSSARY_TITLE, "Glossaire"),
+    (L_INDEX_TITLE, "Index"),
+];
+
+const STRINGS_ES: &[(&str, &str)] = &[
+    (L_CITATION_NEEDED, "cita requerida"),
+    (L_BIB_IN, "En"),
+    (L_BIB_NUMBER, "Número"),
+    (L_BIB_PAGES, "Páginas"),
+    (L_BIB_URL, "URL"),
+    (L_BIB_ON, "el"),
+    (L_BIB_PUBLISHED, "Publicado"),
+    (L_BIB_PUBLISHED_AS, "como"),
+    (L_BIB_PUBLISHED_ON, "Publicado el"),
+    (L_BIB_PUBLISHED_BY, "Publicado por"),
+    (L_BIB_EDITOR, "Editor"),
+    (L_BIB_BY, "por"),
+    (L_BIB_LICENSE, "Licencia"),
+    (L_BIB_AT, "en"),
+    (L_BIB_ACCESSED, "consultado"),
+    (L_BIB_AND, "y"),
+    (L_BIB_ET_AL, "et al."),
+    (L_BIB_NO_DATE, "s.f."),
+    (L_BIB_VOLUME_SHORT, "vol."),
+    (L_BIB_NUMBER_SHORT, "n.º"),
+    (L_BIB_PAGES_SHORT, "pp."),
+    (L_BIB_CITED_IN, "Citado en"),
+    (L_INDEX_SEE, "véase"),
+    (L_INDEX_SEE_ALSO, "véase también"),
+    (L_TOC_TITLE, "Índice"),
+    (L_GLOSSARY_TITLE, "Glosario"),
+    (L_INDEX_TITLE, "Índice alfabético"),
+];
+
+/// Fixed strings of generated content in the language of the document.
+/// The built-in strings can be overridden in the `localisation` table of the manifest
+#[derive(Clone, Debug)]
+pub struct Localisation {
+    strings: HashMap<String, String>,
+}
+
+impl Localisation {
+    pub fn new(language: &str, overrides: &HashMap<String, String>) -> Self {
+        let builtin = match get_language_code(language).as_str() {
+            "de" => STRINGS_DE,
+            "fr" => STRINGS_FR,
+            "es" => STRINGS_ES,
+            _ => STRINGS_EN,
+        };
+        let mut strings: HashMap<String, String> = STRINGS_EN
+            .iter()
+            .chain(builtin.iter())
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        strings.extend(overrides.clone());
+
+        Self { strings }
+    }
+
+    /// Returns the string for the given key
+    pub fn get(&self, key: &str) -> String {
+        self.strings
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| key.to_string())
+    }
+}
+
+/// Returns the lowercase language code without a region, e.g. `de` for `de-DE`
+pub fn get_language_code(language: &str) -> String {
+    language
+        .split(&['-', '_'][..])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{parse_test_files, render_html};
+
+    #[test]
+    fn it_uses_the_strings_of_the_language() {
+        let l10n = Localisation::new("de-DE", &HashMap::new());
+        assert_eq!(l10n.get(L_BIB_NO_DATE), "o. J.");
+        assert_eq!(l10n.get(L_TOC_TITLE), "Inhaltsverzeichnis");
+        assert_eq!(
+            Localisation::new("pt", &HashMap::new()).get(L_BIB_NO_DATE),
+            "n.d."
+        );
+        assert_eq!(l10n.get("unknown"), "unknown");
+    }
+
+    #[test]
+    fn it_prefers_manifest_overrides() {
+        let document = parse_test_files(
+            "localisation-overrides",
+            &[
+                (
+                    "main.sd",
+                    "[[toc]][title]\n\n# Chapter\n\n[!API|see Interface] and ~~API\n\n\
+                    [[gls]][title]\n\n[[index]][title]\n\n\
+                    ~~API[long=\"Application Programming Interface\"]\n",
+                ),
+                (
+                    "Manifest.toml",
+                    "[metadata]\nlanguage = \"de\"\n\n\
+                    [localisation]\ntoc_title = \"Inhalt\"\nindex_see = \"vgl.\"\n",
+                ),
+            ],
+        );
+        let html = render_html(&document);
+        assert!(html.contains(">Inhalt</h1>"), "{}", html);
+        assert!(html.contains(">Glossar</h1>"));
+        assert!(html.contains(">Stichwortverzeichnis</h1>"));
+        assert!(html.contains("vgl."));
+        assert!(!html.contains("Inhaltsverzeichnis"));
+        assert!(!html.contains("siehe"));
+    }
+}
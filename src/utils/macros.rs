@@ -32,10 +32,7 @@ macro_rules! italic_text {
 #[macro_export]
 macro_rules! url_text {
     ($e:expr) => {
-        Inline::Url(Url {
-            url: $e,
-            description: None,
-        })
+        Inline::Url(Url::new(None, $e))
     };
 }
 
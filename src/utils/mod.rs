@@ -5,7 +5,11 @@
  */
 
 pub mod caching;
+pub mod dates;
 pub mod downloads;
 pub mod image_converting;
+pub mod localisation;
 pub mod macros;
 pub mod parsing;
+#[cfg(test)]
+pub(crate) mod testing;
@@ -4,6 +4,7 @@
  * See LICENSE for more information.
  */
 
+use crate::utils::downloads::is_remote;
 use regex::Regex;
 #[macro_export]
 macro_rules! parse {
@@ -19,3 +20,79 @@ pub(crate) fn remove_single_backlslash<S: ToString>(content: S) -> String {
 
     R.replace_all(&*content, "$c").to_string()
 }
+
+/// Returns a lowercase version of the value that only contains
+/// alphanumeric characters with words separated by single dashes
+pub(crate) fn slugify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+/// Returns the line of the table for a key in a toml file
+pub(crate) fn get_toml_line(contents: &str, key: &str) -> Option<usize> {
+    let headers = [format!("[{}]", key), format!("[\"{}\"]", key)];
+
+    contents
+        .lines()
+        .position(|line| headers.iter().any(|h| line.trim() == h))
+        .map(|line| line + 1)
+}
+
+/// Returns the path without a query and fragment if the url points to a local file
+pub(crate) fn get_local_path(url: &str) -> Option<&str> {
+    if url.starts_with('#') || is_remote(url) || has_scheme(url) {
+        return None;
+    }
+    let path = url.split(&['#', '?'][..]).next().unwrap_or_default();
+
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Returns if the url starts with a scheme like `mailto:`.
+/// Single letters are not treated as a scheme so that windows paths are local files
+fn has_scheme(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_returns_local_paths() {
+        assert_eq!(get_local_path("file.pdf"), Some("file.pdf"));
+        assert_eq!(get_local_path("docs/file.sd#intro"), Some("docs/file.sd"));
+        assert_eq!(get_local_path("file.pdf?download=1"), Some("file.pdf"));
+        assert_eq!(get_local_path("file.pdf?page=1#top"), Some("file.pdf"));
+        assert_eq!(get_local_path("C:/file.pdf"), Some("C:/file.pdf"));
+    }
+
+    #[test]
+    fn it_ignores_remote_urls_and_anchors() {
+        assert_eq!(get_local_path("#intro"), None);
+        assert_eq!(get_local_path("?download=1"), None);
+        assert_eq!(get_local_path("https://example.com/file.pdf"), None);
+        assert_eq!(get_local_path("mailto:someone@example.com"), None);
+    }
+}
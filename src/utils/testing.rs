@@ -0,0 +1,79 @@
+/*
+ * Snekdown - Custom Markdown flavour and parser
+ * Copyright (C) 2021  Trivernis
+ * See LICENSE for more information.
+ */
+
+use crate::elements::Document;
+use crate::format::html::html_writer::HTMLWriter;
+use crate::format::html::to_html::ToHtml;
+use crate::parser::{Parser, ParserOptions};
+use crate::settings::style_settings::Theme;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A writer that keeps the written bytes accessible after it was moved into the html writer
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the given files into a new temporary directory and returns its path
+pub(crate) fn write_test_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snekdown-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, content) in files {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(path, content).unwrap();
+    }
+
+    dir
+}
+
+/// Parses the given files with the first one as the main document
+/// and removes them afterwards
+pub(crate) fn parse_test_files(name: &str, files: &[(&str, &str)]) -> Document {
+    parse_test_files_with_options(name, files, |options| options)
+}
+
+/// Parses the given files with the first one as the main document and the
+/// options modified by the given function and removes them afterwards
+pub(crate) fn parse_test_files_with_options<F>(
+    name: &str,
+    files: &[(&str, &str)],
+    options: F,
+) -> Document
+where
+    F: FnOnce(ParserOptions) -> ParserOptions,
+{
+    let dir = write_test_files(name, files);
+    let options = options(ParserOptions::default().add_path(dir.join(files[0].0)));
+    let document = Parser::with_defaults(options).parse();
+    let _ = fs::remove_dir_all(&dir);
+
+    document
+}
+
+/// Renders the given element to html
+pub(crate) fn render_html<T: ToHtml>(element: &T) -> String {
+    let buffer = SharedBuffer::default();
+    let mut writer = HTMLWriter::new(Box::new(buffer.clone()), Theme::GitHub);
+    element.to_html(&mut writer).unwrap();
+    let html = buffer.0.lock().unwrap().clone();
+
+    String::from_utf8(html).unwrap()
+}